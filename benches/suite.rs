@@ -0,0 +1,271 @@
+//! Throughput of the core on the workloads in `benches/roms`.
+//!
+//! Each measurement is printed to stdout as one JSON object per line:
+//!
+//! ```text
+//! {"bench":"step","config":"cache on","unit":"instructions/s","value":123456789.0,"workload":"alu"}
+//! ```
+//!
+//! `bench` is one of `step` (`Machine::step` with and without the
+//! decoded-instruction cache), `run_frame` (one entry per backend), `batch`
+//! (a `MachineBatch` of `LANES` lanes), `decode` (every 16-bit opcode through
+//! the decoder alone), `draw` (`MachDisplay::draw` with wrapping and
+//! clipping sprites) and `state` (`save_state` and `load_state`). `value` is the median
+//! of `SAMPLES` runs. `speedup` entries divide the `step` and `run_frame`
+//! rates by `step` with the cache off on the same workload. A human-readable
+//! table goes to stderr.
+//!
+//! Run with `cargo bench --bench suite > results.jsonl`; any other argument
+//! keeps only the measurements whose `bench/workload/config` contains it.
+
+use std::{env, fs, hint::black_box, path::PathBuf, time::Instant};
+
+use chip8emu::machine::{
+    mach, Backend, CloneRandomSource, Command, Key, MachDisplay, Machine, MachineBatch, Platform,
+    SeededRandom, INSTRUCTIONS_PER_FRAME,
+};
+use serde_json::json;
+
+const SAMPLES: usize = 5;
+const STEPS: u64 = 2_000_000;
+const LANES: usize = 64;
+const BATCH_FRAMES: usize = 200;
+const DECODE_ROUNDS: u64 = 20;
+const DRAWS: u64 = 2_000_000;
+const STATES: u64 = 20_000;
+
+/// The ROMs in `benches/roms`, which documents what each one does.
+const WORKLOADS: &[(&str, Platform)] = &[
+    ("alu", Platform::Chip8),
+    ("draw", Platform::Chip8),
+    ("memcpy", Platform::Chip8),
+    ("mixed", Platform::Chip8),
+];
+
+struct Workload {
+    name: &'static str,
+    platform: Platform,
+    rom: Vec<u8>,
+}
+
+impl Workload {
+    fn machine(&self) -> Machine {
+        let mut mach = Machine::with_quirks(self.platform.quirks());
+        mach.load(&self.rom).unwrap();
+        mach
+    }
+}
+
+struct Suite {
+    filter: Vec<String>,
+}
+
+impl Suite {
+    fn wants(&self, bench: &str, workload: &str, config: &str) -> bool {
+        let id = format!("{}/{}/{}", bench, workload, config);
+        self.filter.is_empty() || self.filter.iter().any(|f| id.contains(f.as_str()))
+    }
+
+    /// Runs `sample` `SAMPLES` times unless filtered out, reports the median
+    /// of `count / seconds` it returns and hands it back.
+    fn measure(
+        &self,
+        bench: &str,
+        workload: &str,
+        config: &str,
+        unit: &str,
+        mut sample: impl FnMut() -> (u64, f64),
+    ) -> Option<f64> {
+        if !self.wants(bench, workload, config) {
+            return None;
+        }
+        let mut rates: Vec<f64> = (0..SAMPLES)
+            .map(|_| {
+                let (count, seconds) = sample();
+                count as f64 / seconds
+            })
+            .collect();
+        rates.sort_by(f64::total_cmp);
+        let value = rates[SAMPLES / 2];
+        self.report(bench, workload, config, unit, value);
+        Some(value)
+    }
+
+    /// Reports `rate` as a multiple of `baseline` when both were measured.
+    fn speedup(&self, workload: &str, config: &str, rate: Option<f64>, baseline: Option<f64>) {
+        if let (Some(rate), Some(baseline)) = (rate, baseline) {
+            self.report("speedup", workload, config, "x", rate / baseline);
+        }
+    }
+
+    fn report(&self, bench: &str, workload: &str, config: &str, unit: &str, value: f64) {
+        println!(
+            "{}",
+            json!({
+                "bench": bench,
+                "workload": workload,
+                "config": config,
+                "unit": unit,
+                "value": value,
+            })
+        );
+        let precision = if unit == "x" { 2 } else { 0 };
+        eprintln!(
+            "{:<9} {:<7} {:<11} {:>14.*} {}",
+            bench, workload, config, precision, value, unit
+        );
+    }
+}
+
+fn timed(count: u64, run: impl FnOnce()) -> (u64, f64) {
+    let start = Instant::now();
+    run();
+    (count, start.elapsed().as_secs_f64())
+}
+
+/// Returns the rate with the cache off, the baseline for the speedups.
+fn step(suite: &Suite, workload: &Workload) -> Option<f64> {
+    let mut baseline = None;
+    for (config, decode_cache) in [("cache off", false), ("cache on", true)] {
+        let rate = suite.measure("step", workload.name, config, "instructions/s", || {
+            let mut mach = workload.machine();
+            mach.set_decode_cache(decode_cache);
+            timed(STEPS, || {
+                for _ in 0..STEPS {
+                    black_box(mach.step().unwrap());
+                }
+            })
+        });
+        if decode_cache {
+            suite.speedup(workload.name, config, rate, baseline);
+        } else {
+            baseline = rate;
+        }
+    }
+    baseline
+}
+
+fn run_frame(suite: &Suite, workload: &Workload, baseline: Option<f64>) {
+    let mut backends = vec![
+        ("interpreter", Backend::Interpreter),
+        ("threaded", Backend::Threaded),
+    ];
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    backends.push(("jit", Backend::Jit));
+    for (config, backend) in backends {
+        let rate = suite.measure("run_frame", workload.name, config, "instructions/s", || {
+            let mut mach = workload.machine();
+            mach.set_backend(backend);
+            timed(STEPS, || {
+                mach.run_frame(STEPS as usize).unwrap();
+                black_box(mach.cycles());
+            })
+        });
+        suite.speedup(workload.name, config, rate, baseline);
+    }
+}
+
+fn batch(suite: &Suite, workload: &Workload) {
+    let config = format!("{} lanes", LANES);
+    suite.measure("batch", workload.name, &config, "instructions/s", || {
+        let mut batch = MachineBatch::new(LANES, workload.platform.quirks(), |lane| {
+            Box::new(SeededRandom::new(lane as u64 + 1)) as Box<dyn CloneRandomSource>
+        });
+        batch.load(&workload.rom).unwrap();
+        let keys = [0; LANES];
+        let instructions = (LANES * BATCH_FRAMES * INSTRUCTIONS_PER_FRAME) as u64;
+        timed(instructions, || {
+            for _ in 0..BATCH_FRAMES {
+                black_box(
+                    batch
+                        .run_frame(&keys, INSTRUCTIONS_PER_FRAME)
+                        .unwrap()
+                        .len(),
+                );
+            }
+        })
+    });
+}
+
+fn state(suite: &Suite, workload: &Workload) {
+    let mut mach = workload.machine();
+    for frame in 0..600 {
+        mach.set_key(Key::from(frame as u8 / 8), frame % 16 < 8);
+        mach.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+    }
+    let saved = mach.save_state();
+    suite.measure("state", workload.name, "save", "states/s", || {
+        timed(STATES, || {
+            for _ in 0..STATES {
+                black_box(mach.save_state());
+            }
+        })
+    });
+    suite.measure("state", workload.name, "load", "states/s", || {
+        let mut target = Machine::new();
+        timed(STATES, || {
+            for _ in 0..STATES {
+                target.load_state(black_box(&saved)).unwrap();
+            }
+        })
+    });
+}
+
+fn decode(suite: &Suite) {
+    suite.measure("decode", "opcodes", "all", "opcodes/s", || {
+        timed(DECODE_ROUNDS * 0x10000, || {
+            for _ in 0..DECODE_ROUNDS {
+                for opcode in 0..=u16::MAX {
+                    let command: Result<Command, _> =
+                        mach::RawCommand(black_box(opcode)).try_into();
+                    black_box(command.ok());
+                }
+            }
+        })
+    });
+}
+
+// A 15-row sprite walked across the screen, so draws hit every alignment and
+// the edges wrap or clip.
+fn draw(suite: &Suite, config: &str, clip: bool) {
+    const SPRITE: [u8; 15] = [
+        0x18, 0x3C, 0x7E, 0xFF, 0xDB, 0xFF, 0x66, 0x3C, 0x18, 0x3C, 0x7E, 0xFF, 0xDB, 0xFF, 0x66,
+    ];
+    suite.measure("draw", "sprite", config, "sprites/s", || {
+        let mut display = MachDisplay::default();
+        timed(DRAWS, || {
+            for n in 0..DRAWS {
+                let x = (n * 7) as u8;
+                let y = (n * 3) as u8;
+                black_box(display.draw(&SPRITE, x, y, clip));
+            }
+        })
+    });
+}
+
+fn main() {
+    let suite = Suite {
+        filter: env::args()
+            .skip(1)
+            .filter(|arg| !arg.starts_with('-'))
+            .collect(),
+    };
+    let roms = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("benches/roms");
+    let workloads: Vec<Workload> = WORKLOADS
+        .iter()
+        .map(|&(name, platform)| Workload {
+            name,
+            platform,
+            rom: fs::read(roms.join(format!("{}.ch8", name))).unwrap(),
+        })
+        .collect();
+    for workload in &workloads {
+        let baseline = step(&suite, workload);
+        run_frame(&suite, workload, baseline);
+        batch(&suite, workload);
+        state(&suite, workload);
+    }
+    decode(&suite);
+    draw(&suite, "wrap", false);
+    draw(&suite, "clip", true);
+}
@@ -0,0 +1,63 @@
+//! Builds chip8-core the way firmware would: `no_std`, no allocator, and a
+//! panic handler of its own. CI also builds it for a real bare-metal target,
+//! `thumbv7em-none-eabihf`; see `.github/workflows/ci.yml`.
+
+#![no_std]
+
+use chip8_core::{
+    Key, Machine, RandomErr, RandomSource, SeededRandom, INSTRUCTIONS_PER_FRAME, RANDOM_STATE_LEN,
+};
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+/// Runs `frames` frames of `rom` and returns the number of lit pixels.
+pub fn run(rom: &[u8], frames: u32, keys: u16) -> u32 {
+    let mut mach = Machine::<SeededRandom>::new();
+    if mach.load(rom).is_err() {
+        return 0;
+    }
+    for key in 0..16 {
+        mach.set_key(Key::from(key), keys & (1 << key) != 0);
+    }
+    for _ in 0..frames {
+        if mach.run_frame(INSTRUCTIONS_PER_FRAME).is_err() {
+            break;
+        }
+    }
+    mach.display()
+        .rows()
+        .iter()
+        .map(|row| row.count_ones())
+        .sum()
+}
+
+/// A random source defined outside the core. The workspace build enables
+/// chip8-core's `alloc` feature, so this also checks that turning it on does
+/// not add anything an implementor has to provide.
+#[derive(Debug)]
+pub struct CountingRandom(pub u8);
+
+impl RandomSource for CountingRandom {
+    fn next_byte(&mut self) -> u8 {
+        self.0 = self.0.wrapping_add(1);
+        self.0
+    }
+
+    fn save(&self, out: &mut [u8; RANDOM_STATE_LEN]) -> usize {
+        out[0] = self.0;
+        1
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), RandomErr> {
+        match state {
+            [byte] => {
+                self.0 = *byte;
+                Ok(())
+            }
+            _ => Err(RandomErr),
+        }
+    }
+}
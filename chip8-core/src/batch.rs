@@ -0,0 +1,436 @@
+//! Many machines advanced together.
+//!
+//! `MachineBatch` holds N machines in structure-of-arrays layout: one array of
+//! PCs, one of index registers, one of register files, one of framebuffers and
+//! so on, each indexed by lane. `run_frame` takes one keypad mask per lane and
+//! advances every lane by a frame, running one instruction across all lanes
+//! at a time so that each array is walked in order. All lanes share quirks,
+//! but each has its own random source. A
+//! lane that faults stops in the state `Machine::run_frame` leaves behind when
+//! it returns the error, and stays stopped.
+//!
+//! `chunks_mut` splits the batch into disjoint runs of lanes that can be
+//! handed to separate threads.
+
+use alloc::{vec, vec::Vec};
+
+use enum_iterator::all;
+
+use super::{
+    decode,
+    exec::{self, ExecState},
+    memory,
+    reg::RegBank,
+    Key, MachDisplay, Machine, MachineErr, Memory, Quirks, RandomSource, Reg, SeededRandom, Stack,
+    DISPLAY_HEIGHT, DISPLAY_WIDTH, LOAD_OFFSET, MEMORY_SIZE,
+};
+
+type Display = MachDisplay<DISPLAY_WIDTH, DISPLAY_HEIGHT>;
+
+#[derive(Debug, Clone)]
+pub struct MachineBatch<R: RandomSource = SeededRandom> {
+    quirks: Quirks,
+    strict_memory: bool,
+    pc: Vec<u16>,
+    index: Vec<u16>,
+    regs: Vec<[u8; 16]>,
+    stack: Vec<Stack>,
+    delay_timer: Vec<u8>,
+    sound_timer: Vec<u8>,
+    keys: Vec<u16>,
+    cycles: Vec<u64>,
+    fault: Vec<bool>,
+    memory: Vec<Memory<MEMORY_SIZE>>,
+    display: Vec<Display>,
+    random: Vec<R>,
+}
+
+/// A run of lanes borrowed from a [`MachineBatch`].
+#[derive(Debug)]
+pub struct BatchChunk<'a, R: RandomSource> {
+    start: usize,
+    quirks: Quirks,
+    strict_memory: bool,
+    pc: &'a mut [u16],
+    index: &'a mut [u16],
+    regs: &'a mut [[u8; 16]],
+    stack: &'a mut [Stack],
+    delay_timer: &'a mut [u8],
+    sound_timer: &'a mut [u8],
+    keys: &'a mut [u16],
+    cycles: &'a mut [u64],
+    fault: &'a mut [bool],
+    memory: &'a mut [Memory<MEMORY_SIZE>],
+    display: &'a mut [Display],
+    random: &'a mut [R],
+}
+
+/// What one lane shows after a frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Observation<'a> {
+    pub screen: &'a Display,
+    /// The sound timer is running.
+    pub sound: bool,
+    /// The lane has faulted and no longer runs.
+    pub fault: bool,
+}
+
+#[derive(Debug)]
+pub struct BatchErr;
+
+impl<R: RandomSource> MachineBatch<R> {
+    /// `lanes` freshly reset machines. Lane `n` draws its `CXNN` values from
+    /// `random(n)`; independent rollouts want a different seed per lane, such
+    /// as `|lane| SeededRandom::new(seed + lane as u64)`.
+    pub fn new(lanes: usize, quirks: Quirks, random: impl FnMut(usize) -> R) -> Self {
+        Self::from_machine(&Machine::<SeededRandom>::with_quirks(quirks), lanes, random)
+    }
+
+    /// `lanes` copies of `mach`, keypad included. Like [`MachineBatch::new`],
+    /// lane `n` gets `random(n)` rather than a copy of `mach`'s source.
+    pub fn from_machine<T: RandomSource>(
+        mach: &Machine<T>,
+        lanes: usize,
+        random: impl FnMut(usize) -> R,
+    ) -> Self {
+        let mut keys = 0;
+        for key in all::<Key>() {
+            if mach.key.get_value(key) {
+                keys |= 1 << u8::from(key);
+            }
+        }
+        Self {
+            quirks: mach.quirks,
+            strict_memory: mach.strict_memory,
+            pc: vec![mach.pc; lanes],
+            index: vec![mach.index; lanes],
+            regs: vec![mach.reg.to_array(); lanes],
+            stack: vec![mach.stack.clone(); lanes],
+            delay_timer: vec![mach.delay_timer.get_value(); lanes],
+            sound_timer: vec![mach.sound_timer.get_value(); lanes],
+            keys: vec![keys; lanes],
+            cycles: vec![mach.cycles; lanes],
+            fault: vec![false; lanes],
+            memory: vec![mach.memory; lanes],
+            display: vec![mach.display; lanes],
+            random: (0..lanes).map(random).collect(),
+        }
+    }
+}
+
+impl<R: RandomSource + Clone> MachineBatch<R> {
+    /// A standalone machine in the state of `lane`.
+    pub fn machine(&self, lane: usize) -> Machine<R> {
+        let mut mach = Machine::with_random(self.quirks, self.random[lane].clone());
+        mach.strict_memory = self.strict_memory;
+        mach.pc = self.pc[lane];
+        mach.index = self.index[lane];
+        mach.reg = RegBank::from_array(self.regs[lane]);
+        mach.stack = self.stack[lane].clone();
+        mach.delay_timer.set_value(self.delay_timer[lane]);
+        mach.sound_timer.set_value(self.sound_timer[lane]);
+        for key in all::<Key>() {
+            mach.key
+                .set_value(key, self.keys[lane] & (1 << u8::from(key)) != 0);
+        }
+        mach.cycles = self.cycles[lane];
+        mach.memory = self.memory[lane];
+        mach.display = self.display[lane];
+        mach
+    }
+}
+
+impl<R: RandomSource> MachineBatch<R> {
+    pub fn len(&self) -> usize {
+        self.pc.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pc.is_empty()
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_strict_memory(&mut self, strict: bool) {
+        self.strict_memory = strict;
+    }
+
+    pub fn set_random(&mut self, lane: usize, random: R) {
+        self.random[lane] = random;
+    }
+
+    /// Loads the same program into every lane.
+    pub fn load(&mut self, prog_data: &[u8]) -> Result<(), BatchErr> {
+        for lane in 0..self.len() {
+            self.load_lane(lane, prog_data)?;
+        }
+        Ok(())
+    }
+
+    pub fn load_lane(&mut self, lane: usize, prog_data: &[u8]) -> Result<(), BatchErr> {
+        let mem_data = self.memory[lane]
+            .get_mut_data(LOAD_OFFSET, prog_data.len())
+            .map_err(|_| BatchErr)?;
+        mem_data.copy_from_slice(prog_data);
+        Ok(())
+    }
+
+    pub fn pcs(&self) -> &[u16] {
+        &self.pc
+    }
+
+    pub fn indexes(&self) -> &[u16] {
+        &self.index
+    }
+
+    /// V0 to VF of every lane.
+    pub fn regs(&self) -> &[[u8; 16]] {
+        &self.regs
+    }
+
+    pub fn cycles(&self) -> &[u64] {
+        &self.cycles
+    }
+
+    pub fn displays(&self) -> &[Display] {
+        &self.display
+    }
+
+    pub fn faults(&self) -> &[bool] {
+        &self.fault
+    }
+
+    pub fn observation(&self, lane: usize) -> Observation<'_> {
+        Observation {
+            screen: &self.display[lane],
+            sound: self.sound_timer[lane] > 0,
+            fault: self.fault[lane],
+        }
+    }
+
+    pub fn observations(&self) -> impl ExactSizeIterator<Item = Observation<'_>> {
+        (0..self.len()).map(|lane| self.observation(lane))
+    }
+
+    /// Holds each lane's keypad, one bit per key with key 0 in bit 0, runs
+    /// `instructions` instructions on every lane that has not faulted and
+    /// ticks their timers.
+    pub fn run_frame(
+        &mut self,
+        keys: &[u16],
+        instructions: usize,
+    ) -> Result<impl ExactSizeIterator<Item = Observation<'_>>, BatchErr> {
+        let len = self.len();
+        if keys.len() != len {
+            return Err(BatchErr);
+        }
+        self.chunks_mut(len.max(1))
+            .try_for_each(|mut chunk| chunk.run_frame(keys, instructions))?;
+        Ok(self.observations())
+    }
+
+    /// Splits the lanes into runs of at most `lanes` each, in order.
+    pub fn chunks_mut(&mut self, lanes: usize) -> impl Iterator<Item = BatchChunk<'_, R>> {
+        assert!(lanes > 0, "chunks must hold at least one lane");
+        let mut rest = Some(BatchChunk {
+            start: 0,
+            quirks: self.quirks,
+            strict_memory: self.strict_memory,
+            pc: &mut self.pc,
+            index: &mut self.index,
+            regs: &mut self.regs,
+            stack: &mut self.stack,
+            delay_timer: &mut self.delay_timer,
+            sound_timer: &mut self.sound_timer,
+            keys: &mut self.keys,
+            cycles: &mut self.cycles,
+            fault: &mut self.fault,
+            memory: &mut self.memory,
+            display: &mut self.display,
+            random: &mut self.random,
+        });
+        core::iter::from_fn(move || {
+            let chunk = rest.take().filter(|chunk| !chunk.pc.is_empty())?;
+            let mid = lanes.min(chunk.pc.len());
+            let (head, tail) = chunk.split_at(mid);
+            rest = Some(tail);
+            Some(head)
+        })
+    }
+}
+
+impl<'a, R: RandomSource> BatchChunk<'a, R> {
+    /// The batch lanes this chunk covers.
+    pub fn lanes(&self) -> core::ops::Range<usize> {
+        self.start..self.start + self.pc.len()
+    }
+
+    /// Like [`MachineBatch::run_frame`] for this chunk's lanes, with one
+    /// keypad mask per lane of the chunk.
+    pub fn run_frame(&mut self, keys: &[u16], instructions: usize) -> Result<(), BatchErr> {
+        let len = self.pc.len();
+        if keys.len() != len {
+            return Err(BatchErr);
+        }
+        self.keys.copy_from_slice(keys);
+        for _ in 0..instructions {
+            for lane in 0..len {
+                if !self.fault[lane] && self.step(lane).is_err() {
+                    self.fault[lane] = true;
+                }
+            }
+        }
+        for lane in (0..len).filter(|&lane| !self.fault[lane]) {
+            self.delay_timer[lane] = self.delay_timer[lane].saturating_sub(1);
+            self.sound_timer[lane] = self.sound_timer[lane].saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    fn split_at(self, mid: usize) -> (Self, Self) {
+        let (pc, pc_rest) = self.pc.split_at_mut(mid);
+        let (index, index_rest) = self.index.split_at_mut(mid);
+        let (regs, regs_rest) = self.regs.split_at_mut(mid);
+        let (stack, stack_rest) = self.stack.split_at_mut(mid);
+        let (delay_timer, delay_timer_rest) = self.delay_timer.split_at_mut(mid);
+        let (sound_timer, sound_timer_rest) = self.sound_timer.split_at_mut(mid);
+        let (keys, keys_rest) = self.keys.split_at_mut(mid);
+        let (cycles, cycles_rest) = self.cycles.split_at_mut(mid);
+        let (fault, fault_rest) = self.fault.split_at_mut(mid);
+        let (memory, memory_rest) = self.memory.split_at_mut(mid);
+        let (display, display_rest) = self.display.split_at_mut(mid);
+        let (random, random_rest) = self.random.split_at_mut(mid);
+        (
+            Self {
+                start: self.start,
+                quirks: self.quirks,
+                strict_memory: self.strict_memory,
+                pc,
+                index,
+                regs,
+                stack,
+                delay_timer,
+                sound_timer,
+                keys,
+                cycles,
+                fault,
+                memory,
+                display,
+                random,
+            },
+            Self {
+                start: self.start + mid,
+                quirks: self.quirks,
+                strict_memory: self.strict_memory,
+                pc: pc_rest,
+                index: index_rest,
+                regs: regs_rest,
+                stack: stack_rest,
+                delay_timer: delay_timer_rest,
+                sound_timer: sound_timer_rest,
+                keys: keys_rest,
+                cycles: cycles_rest,
+                fault: fault_rest,
+                memory: memory_rest,
+                display: display_rest,
+                random: random_rest,
+            },
+        )
+    }
+
+    fn step(&mut self, lane: usize) -> Result<(), MachineErr> {
+        let pc = self.pc[lane];
+        let opcode = u16::from_be_bytes(self.memory[lane].get_command_data(pc)?);
+        self.pc[lane] = pc.wrapping_add(2);
+        let command = decode(opcode, self.quirks)?;
+        exec::execute(&mut Lane { chunk: self, lane }, command)?;
+        self.cycles[lane] += 1;
+        Ok(())
+    }
+}
+
+/// One lane of a chunk, seen as a single machine.
+struct Lane<'c, 'a, R: RandomSource> {
+    chunk: &'c mut BatchChunk<'a, R>,
+    lane: usize,
+}
+
+impl<R: RandomSource> ExecState for Lane<'_, '_, R> {
+    fn quirks(&self) -> Quirks {
+        self.chunk.quirks
+    }
+
+    fn reg(&self, reg: Reg) -> u8 {
+        self.chunk.regs[self.lane][reg as usize]
+    }
+
+    fn set_reg(&mut self, reg: Reg, val: u8) {
+        self.chunk.regs[self.lane][reg as usize] = val;
+    }
+
+    fn pc(&self) -> u16 {
+        self.chunk.pc[self.lane]
+    }
+
+    fn set_pc(&mut self, pc: u16) {
+        self.chunk.pc[self.lane] = pc;
+    }
+
+    fn index(&self) -> u16 {
+        self.chunk.index[self.lane]
+    }
+
+    fn set_index(&mut self, index: u16) {
+        self.chunk.index[self.lane] = index;
+    }
+
+    fn stack_mut(&mut self) -> &mut Stack {
+        &mut self.chunk.stack[self.lane]
+    }
+
+    fn delay_timer(&self) -> u8 {
+        self.chunk.delay_timer[self.lane]
+    }
+
+    fn set_delay_timer(&mut self, val: u8) {
+        self.chunk.delay_timer[self.lane] = val;
+    }
+
+    fn set_sound_timer(&mut self, val: u8) {
+        self.chunk.sound_timer[self.lane] = val;
+    }
+
+    fn key(&self, key: Key) -> bool {
+        self.chunk.keys[self.lane] & (1 << u8::from(key)) != 0
+    }
+
+    fn pressed_key(&self) -> Option<Key> {
+        match self.chunk.keys[self.lane] {
+            0 => None,
+            keys => Some(Key::from(keys.trailing_zeros() as u8)),
+        }
+    }
+
+    fn random_byte(&mut self) -> u8 {
+        self.chunk.random[self.lane].next_byte()
+    }
+
+    fn display_mut(&mut self) -> &mut Display {
+        &mut self.chunk.display[self.lane]
+    }
+
+    fn read_index(&self, buf: &mut [u8]) -> Result<(), MachineErr> {
+        let memory = self.chunk.memory[self.lane].as_slice();
+        memory::read_index(memory, self.index(), self.chunk.strict_memory, buf)?;
+        Ok(())
+    }
+
+    fn write_index(&mut self, data: &[u8]) -> Result<(), MachineErr> {
+        let index = self.index();
+        let memory = self.chunk.memory[self.lane].as_mut_slice();
+        memory::write_index(memory, index, self.chunk.strict_memory, data)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! Decoded-instruction cache.
+//!
+//! Decoding depends only on the two opcode bytes and the quirks, and the
+//! quirks are fixed for a machine's lifetime, so a decoded command stays valid
+//! until one of its bytes is written. Every memory write goes through
+//! [`DecodeCache::invalidate`], which keeps self-modifying ROMs correct.
+
+use alloc::{vec, vec::Vec};
+use core::fmt::{self, Debug};
+
+use super::{Command, MEMORY_SIZE};
+
+fn table(enabled: bool) -> Vec<Option<Command>> {
+    if enabled {
+        vec![None; MEMORY_SIZE]
+    } else {
+        Vec::new()
+    }
+}
+
+#[derive(Clone)]
+pub struct DecodeCache {
+    // Indexed by the address of the opcode's first byte, and empty while
+    // disabled. Allocated up front so stepping never allocates.
+    entries: Vec<Option<Command>>,
+}
+
+impl Debug for DecodeCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecodeCache")
+            .field("enabled", &self.is_enabled())
+            .field("cached", &self.entries.iter().flatten().count())
+            .finish()
+    }
+}
+
+impl Default for DecodeCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecodeCache {
+    pub fn new() -> Self {
+        Self {
+            entries: table(true),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.entries.is_empty()
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.entries = table(enabled);
+    }
+
+    pub fn get(&self, addr: u16) -> Option<Command> {
+        self.entries.get(addr as usize).copied().flatten()
+    }
+
+    pub fn insert(&mut self, addr: u16, command: Command) {
+        if let Some(entry) = self.entries.get_mut(addr as usize) {
+            *entry = Some(command);
+        }
+    }
+
+    /// Forgets every command that overlaps the `len` bytes at `addr`,
+    /// including the one starting just before it.
+    pub fn invalidate(&mut self, addr: usize, len: usize) {
+        if !self.is_enabled() {
+            return;
+        }
+        let end = (addr + len).min(self.entries.len());
+        for entry in &mut self.entries[addr.saturating_sub(1)..end] {
+            *entry = None;
+        }
+    }
+}
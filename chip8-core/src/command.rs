@@ -1,3 +1,5 @@
+use core::fmt::Display;
+
 use super::reg::Reg;
 
 #[allow(dead_code)]
@@ -43,6 +45,53 @@ pub enum Command {
     StoreWithIndexIncrement(Reg),
 }
 
+impl Display for Command {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Command::ExecuteMachineRoutine(addr) => write!(f, "SYS {:#05x}", addr),
+            Command::ClearScreen => write!(f, "CLS"),
+            Command::Jump(addr) => write!(f, "JP {:#05x}", addr),
+            Command::Call(addr) => write!(f, "CALL {:#05x}", addr),
+            Command::Return => write!(f, "RET"),
+            Command::Skip => write!(f, "SKIP"),
+            Command::SkipIfRegVal(reg_x, val) => write!(f, "SE {}, {:#04x}", reg_x, val),
+            Command::SkipIfRegValNot(reg_x, val) => write!(f, "SNE {}, {:#04x}", reg_x, val),
+            Command::SkipIfRegEqual(reg_x, reg_y) => write!(f, "SE {}, {}", reg_x, reg_y),
+            Command::SkipIfRegNotEqual(reg_x, reg_y) => write!(f, "SNE {}, {}", reg_x, reg_y),
+            Command::SetVal(reg_x, val) => write!(f, "LD {}, {:#04x}", reg_x, val),
+            Command::AddVal(reg_x, val) => write!(f, "ADD {}, {:#04x}", reg_x, val),
+            Command::SetReg(reg_x, reg_y) => write!(f, "LD {}, {}", reg_x, reg_y),
+            Command::BinOR(reg_x, reg_y) => write!(f, "OR {}, {}", reg_x, reg_y),
+            Command::BinAND(reg_x, reg_y) => write!(f, "AND {}, {}", reg_x, reg_y),
+            Command::LogXOR(reg_x, reg_y) => write!(f, "XOR {}, {}", reg_x, reg_y),
+            Command::AddReg(reg_x, reg_y) => write!(f, "ADD {}, {}", reg_x, reg_y),
+            Command::SubReg(reg_x, reg_y) => write!(f, "SUB {}, {}", reg_x, reg_y),
+            Command::SubRegRev(reg_x, reg_y) => write!(f, "SUBN {}, {}", reg_x, reg_y),
+            Command::ShiftLeft(reg_x, reg_y) => write!(f, "SHL {}, {}", reg_x, reg_y),
+            Command::ShiftRight(reg_x, reg_y) => write!(f, "SHR {}, {}", reg_x, reg_y),
+            Command::SetIndex(addr) => write!(f, "LD I, {:#05x}", addr),
+            Command::JumpWithOffset(addr, reg_x) => write!(f, "JP {}, {:#05x}", reg_x, addr),
+            Command::Random(reg_x, val) => write!(f, "RND {}, {:#04x}", reg_x, val),
+            Command::Display(reg_x, reg_y, val) => {
+                write!(f, "DRW {}, {}, {:#03x}", reg_x, reg_y, val)
+            }
+            Command::SkipIfKey(reg_x) => write!(f, "SKP {}", reg_x),
+            Command::SkipIfNotKey(reg_x) => write!(f, "SKNP {}", reg_x),
+            Command::SetRegFromDelayTimer(reg_x) => write!(f, "LD {}, DT", reg_x),
+            Command::SetDelayTimerFromReg(reg_x) => write!(f, "LD DT, {}", reg_x),
+            Command::SetSoundTimerFromReg(reg_x) => write!(f, "LD ST, {}", reg_x),
+            Command::AddIndex(reg_x) => write!(f, "ADD I, {}", reg_x),
+            Command::GetKey(reg_x) => write!(f, "LD {}, K", reg_x),
+            Command::Font(reg_x) => write!(f, "LD F, {}", reg_x),
+            Command::BCDConv(reg_x) => write!(f, "LD B, {}", reg_x),
+            Command::Load(reg_x) => write!(f, "LD {}, [I]", reg_x),
+            Command::Store(reg_x) => write!(f, "LD [I], {}", reg_x),
+            Command::LoadWithIndexIncrement(reg_x) => write!(f, "LD {}, [I+]", reg_x),
+            Command::StoreWithIndexIncrement(reg_x) => write!(f, "LD [I+], {}", reg_x),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct RawCommand(pub u16);
 
@@ -62,7 +111,7 @@ impl RawCommand {
     }
 
     fn val8(self) -> u8 {
-        self.0 as u8 & 0xFF
+        self.0 as u8
     }
 
     fn val12(self) -> u16 {
@@ -96,6 +145,8 @@ impl RawCommand {
                 },
                 0x9 => Ok(Command::SkipIfRegNotEqual(self.reg_x(), self.reg_y())),
                 0xA => Ok(Command::SetIndex(self.val12())),
+                0xB => Ok(Command::JumpWithOffset(self.val12(), Reg::V0)),
+                0xC => Ok(Command::Random(self.reg_x(), self.val8())),
                 0xD => Ok(Command::Display(self.reg_x(), self.reg_y(), self.val4())),
                 0xE => match command & 0x00FF {
                     0x9E => Ok(Command::SkipIfKey(self.reg_x())),
@@ -0,0 +1,187 @@
+//! Bit-packed framebuffer.
+//!
+//! Each row is one machine word with column 0 in the most significant bit, so
+//! a sprite row is placed with a shift (or a rotate when it wraps), drawn with
+//! one XOR and collision-tested with one AND. Rows are `u64`, the width of the
+//! CHIP-8 screen.
+
+use core::fmt::{self, Debug};
+use core::ops::{Deref, DerefMut};
+
+/// A packed display row as wide as the display.
+pub trait Row: Copy + Debug + PartialEq {
+    const BITS: usize;
+    const EMPTY: Self;
+
+    /// An 8-pixel sprite row with its first pixel at column `x`. Pixels past
+    /// the right edge are dropped when clipping and wrap to column 0 otherwise.
+    fn sprite(byte: u8, x: usize, clip: bool) -> Self;
+
+    /// XORs `sprite` in and reports whether it turned any pixel off.
+    fn draw(&mut self, sprite: Self) -> bool;
+
+    fn get(self, x: usize) -> bool;
+
+    fn set(&mut self, x: usize, val: bool);
+}
+
+macro_rules! impl_row {
+    ($ty:ty) => {
+        impl Row for $ty {
+            const BITS: usize = <$ty>::BITS as usize;
+            const EMPTY: Self = 0;
+
+            fn sprite(byte: u8, x: usize, clip: bool) -> Self {
+                let sprite = (byte as $ty) << (<Self as Row>::BITS - 8);
+                if clip {
+                    sprite >> x
+                } else {
+                    sprite.rotate_right(x as u32)
+                }
+            }
+
+            fn draw(&mut self, sprite: Self) -> bool {
+                let collided = *self & sprite != 0;
+                *self ^= sprite;
+                collided
+            }
+
+            fn get(self, x: usize) -> bool {
+                self & (1 << (<Self as Row>::BITS - 1 - x)) != 0
+            }
+
+            fn set(&mut self, x: usize, val: bool) {
+                let bit = 1 << (<Self as Row>::BITS - 1 - x);
+                if val {
+                    *self |= bit;
+                } else {
+                    *self &= !bit;
+                }
+            }
+        }
+    };
+}
+
+impl_row!(u64);
+
+#[derive(Debug, Clone, Copy)]
+pub struct MachDisplay<const X: usize, const Y: usize, R: Row = u64> {
+    rows: [R; Y],
+}
+
+#[derive(Debug)]
+pub struct DisplayErr;
+
+impl<const X: usize, const Y: usize, R: Row> Default for MachDisplay<X, Y, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const X: usize, const Y: usize, R: Row> MachDisplay<X, Y, R> {
+    const ROW_FITS: () = assert!(X == R::BITS, "display width must match its row type");
+
+    pub fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::ROW_FITS;
+        Self {
+            rows: [R::EMPTY; Y],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Result<bool, DisplayErr> {
+        if x >= X || y >= Y {
+            return Err(DisplayErr);
+        }
+        Ok(self.rows[y].get(x))
+    }
+
+    /// Like [`MachDisplay::pixel`], but by reference, as it was when the
+    /// screen was a grid of `bool`s.
+    pub fn get_pixel(&self, x: usize, y: usize) -> Result<&bool, DisplayErr> {
+        Ok(if self.pixel(x, y)? { &true } else { &false })
+    }
+
+    /// The pixel at `x`, `y` behind a guard that writes it back when dropped.
+    #[deprecated(note = "use `set_pixel`")]
+    pub fn get_pixel_mut(&mut self, x: usize, y: usize) -> Result<PixelMut<'_, R>, DisplayErr> {
+        let val = self.pixel(x, y)?;
+        Ok(PixelMut {
+            row: &mut self.rows[y],
+            x,
+            val,
+        })
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, val: bool) -> Result<(), DisplayErr> {
+        if x >= X || y >= Y {
+            return Err(DisplayErr);
+        }
+        self.rows[y].set(x, val);
+        Ok(())
+    }
+
+    /// The packed rows, top to bottom, with column 0 in the high bit.
+    pub fn rows(&self) -> &[R] {
+        &self.rows
+    }
+
+    pub fn clear_screen(&mut self) {
+        self.rows = [R::EMPTY; Y];
+    }
+
+    /// XORs a sprite onto the screen and reports whether any pixel was
+    /// turned off. Rows past the bottom edge are dropped when clipping and
+    /// wrap to row 0 otherwise.
+    pub fn draw(&mut self, sprite_data: &[u8], x: u8, y: u8, clip: bool) -> bool {
+        let x = x as usize % X;
+        let y = y as usize % Y;
+        let height = if clip { Y - y } else { Y };
+        let mut collided = false;
+        for (i, byte) in sprite_data.iter().take(height).enumerate() {
+            collided |= self.rows[(y + i) % Y].draw(R::sprite(*byte, x, clip));
+        }
+        collided
+    }
+}
+
+/// A pixel borrowed from a [`MachDisplay`]; see [`MachDisplay::get_pixel_mut`].
+#[derive(Debug)]
+pub struct PixelMut<'a, R: Row> {
+    row: &'a mut R,
+    x: usize,
+    val: bool,
+}
+
+impl<R: Row> Deref for PixelMut<'_, R> {
+    type Target = bool;
+
+    fn deref(&self) -> &bool {
+        &self.val
+    }
+}
+
+impl<R: Row> DerefMut for PixelMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut bool {
+        &mut self.val
+    }
+}
+
+impl<R: Row> Drop for PixelMut<'_, R> {
+    fn drop(&mut self) {
+        self.row.set(self.x, self.val);
+    }
+}
+
+/// Renders the screen as rows of `0` and `1`, followed by a blank line.
+impl<const X: usize, const Y: usize, R: Row> fmt::Display for MachDisplay<X, Y, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.rows {
+            for x in 0..X {
+                write!(f, "{}", row.get(x) as u8)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f)
+    }
+}
@@ -0,0 +1,409 @@
+//! Instruction semantics.
+//!
+//! [`execute`] runs one decoded instruction against anything that implements
+//! [`ExecState`]: a [`Machine`] or one lane of a batch. Implementors only say
+//! where their registers, stack, timers, keypad, screen and memory live. Each
+//! instruction is one function below; `execute` dispatches to them and every
+//! threaded handler is a call to one of them, so the interpreter, the batch
+//! and the threaded backend share all their semantics.
+//!
+//! The JIT and the recompiler do not: they emit their own native code for
+//! register, I and branch instructions. `tests/backends.rs` and
+//! `tests/aot.rs` run them in lockstep with the interpreter.
+
+use enum_iterator::all;
+
+use super::{
+    font, offset_jump, Command, Key, MachDisplay, Machine, MachineErr, Quirks, RandomSource, Reg,
+    Stack, DISPLAY_HEIGHT, DISPLAY_WIDTH, FONT_OFFSET, MEMORY_SIZE,
+};
+
+pub(crate) trait ExecState {
+    fn quirks(&self) -> Quirks;
+
+    fn reg(&self, reg: Reg) -> u8;
+
+    fn set_reg(&mut self, reg: Reg, val: u8);
+
+    fn pc(&self) -> u16;
+
+    fn set_pc(&mut self, pc: u16);
+
+    fn index(&self) -> u16;
+
+    fn set_index(&mut self, index: u16);
+
+    fn stack_mut(&mut self) -> &mut Stack;
+
+    fn delay_timer(&self) -> u8;
+
+    fn set_delay_timer(&mut self, val: u8);
+
+    fn set_sound_timer(&mut self, val: u8);
+
+    fn key(&self, key: Key) -> bool;
+
+    /// The lowest-numbered key held down, for `FX0A`.
+    fn pressed_key(&self) -> Option<Key>;
+
+    fn random_byte(&mut self) -> u8;
+
+    fn display_mut(&mut self) -> &mut MachDisplay<DISPLAY_WIDTH, DISPLAY_HEIGHT>;
+
+    /// Fills `buf` from memory at I, wrapping at the end of memory unless
+    /// strict memory makes that a fault.
+    fn read_index(&self, buf: &mut [u8]) -> Result<(), MachineErr>;
+
+    /// Writes `data` to memory at I, with the same wrapping as `read_index`.
+    fn write_index(&mut self, data: &[u8]) -> Result<(), MachineErr>;
+}
+
+/// Runs `command` with PC already past it.
+pub(crate) fn execute<S: ExecState>(s: &mut S, command: Command) -> Result<(), MachineErr> {
+    match command {
+        Command::ClearScreen => clear_screen(s),
+        Command::Jump(addr) => jump(s, addr),
+        Command::JumpWithOffset(addr, x) => jump_with_offset(s, addr, x),
+        Command::Call(addr) => call(s, addr),
+        Command::Return => ret(s),
+        Command::SkipIfRegVal(x, val) => skip_if_val(s, x, val),
+        Command::SkipIfRegValNot(x, val) => skip_if_not_val(s, x, val),
+        Command::SkipIfRegEqual(x, y) => skip_if_equal(s, x, y),
+        Command::SkipIfRegNotEqual(x, y) => skip_if_not_equal(s, x, y),
+        Command::SetVal(x, val) => set_val(s, x, val),
+        Command::AddVal(x, val) => add_val(s, x, val),
+        Command::SetReg(x, y) => set_reg(s, x, y),
+        Command::BinOR(x, y) => or(s, x, y),
+        Command::BinAND(x, y) => and(s, x, y),
+        Command::LogXOR(x, y) => xor(s, x, y),
+        Command::AddReg(x, y) => add_reg(s, x, y),
+        Command::SubReg(x, y) => sub_reg(s, x, y),
+        Command::SubRegRev(x, y) => sub_reg_rev(s, x, y),
+        Command::ShiftLeft(x, y) => shift_left(s, x, y),
+        Command::ShiftRight(x, y) => shift_right(s, x, y),
+        Command::SetIndex(addr) => set_index(s, addr),
+        Command::Random(x, val) => random(s, x, val),
+        Command::Display(x, y, rows) => display(s, x, y, rows),
+        Command::SkipIfKey(x) => skip_if_key(s, x),
+        Command::SkipIfNotKey(x) => skip_if_not_key(s, x),
+        Command::SetRegFromDelayTimer(x) => get_delay_timer(s, x),
+        Command::SetDelayTimerFromReg(x) => set_delay_timer(s, x),
+        Command::SetSoundTimerFromReg(x) => set_sound_timer(s, x),
+        Command::AddIndex(x) => add_index(s, x),
+        Command::GetKey(x) => get_key(s, x),
+        Command::Font(x) => font(s, x),
+        Command::BCDConv(x) => bcd(s, x),
+        Command::Store(x) => store(s, x),
+        Command::Load(x) => load(s, x),
+        Command::StoreWithIndexIncrement(x) => store_increment(s, x),
+        Command::LoadWithIndexIncrement(x) => load_increment(s, x),
+        Command::ExecuteMachineRoutine(_) | Command::Skip => Err(MachineErr),
+    }
+}
+
+// One function per instruction. They all return a `Result` so that
+// `execute` and the threaded handlers can forward them unchanged.
+
+pub(crate) fn clear_screen<S: ExecState>(s: &mut S) -> Result<(), MachineErr> {
+    s.display_mut().clear_screen();
+    Ok(())
+}
+
+pub(crate) fn jump<S: ExecState>(s: &mut S, addr: u16) -> Result<(), MachineErr> {
+    s.set_pc(addr);
+    Ok(())
+}
+
+pub(crate) fn jump_with_offset<S: ExecState>(
+    s: &mut S,
+    addr: u16,
+    x: Reg,
+) -> Result<(), MachineErr> {
+    s.set_pc(offset_jump(addr, s.reg(x)));
+    Ok(())
+}
+
+pub(crate) fn call<S: ExecState>(s: &mut S, addr: u16) -> Result<(), MachineErr> {
+    let pc = s.pc();
+    s.stack_mut().push(pc)?;
+    s.set_pc(addr);
+    Ok(())
+}
+
+pub(crate) fn ret<S: ExecState>(s: &mut S) -> Result<(), MachineErr> {
+    let pc = s.stack_mut().pop()?;
+    s.set_pc(pc);
+    Ok(())
+}
+
+fn skip_if<S: ExecState>(s: &mut S, condition: bool) -> Result<(), MachineErr> {
+    if condition {
+        s.set_pc(s.pc().wrapping_add(2));
+    }
+    Ok(())
+}
+
+pub(crate) fn skip_if_val<S: ExecState>(s: &mut S, x: Reg, val: u8) -> Result<(), MachineErr> {
+    skip_if(s, s.reg(x) == val)
+}
+
+pub(crate) fn skip_if_not_val<S: ExecState>(s: &mut S, x: Reg, val: u8) -> Result<(), MachineErr> {
+    skip_if(s, s.reg(x) != val)
+}
+
+pub(crate) fn skip_if_equal<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    skip_if(s, s.reg(x) == s.reg(y))
+}
+
+pub(crate) fn skip_if_not_equal<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    skip_if(s, s.reg(x) != s.reg(y))
+}
+
+pub(crate) fn set_val<S: ExecState>(s: &mut S, x: Reg, val: u8) -> Result<(), MachineErr> {
+    s.set_reg(x, val);
+    Ok(())
+}
+
+pub(crate) fn add_val<S: ExecState>(s: &mut S, x: Reg, val: u8) -> Result<(), MachineErr> {
+    s.set_reg(x, s.reg(x).wrapping_add(val));
+    Ok(())
+}
+
+pub(crate) fn set_reg<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    s.set_reg(x, s.reg(y));
+    Ok(())
+}
+
+fn logic<S: ExecState>(s: &mut S, x: Reg, result: u8) -> Result<(), MachineErr> {
+    s.set_reg(x, result);
+    if s.quirks().vf_reset {
+        s.set_reg(Reg::VF, 0);
+    }
+    Ok(())
+}
+
+pub(crate) fn or<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    logic(s, x, s.reg(x) | s.reg(y))
+}
+
+pub(crate) fn and<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    logic(s, x, s.reg(x) & s.reg(y))
+}
+
+pub(crate) fn xor<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    logic(s, x, s.reg(x) ^ s.reg(y))
+}
+
+// VF is written last, so it holds the flag even when it is also VX.
+fn set_alu_result<S: ExecState>(s: &mut S, x: Reg, result: u8, flag: u8) -> Result<(), MachineErr> {
+    s.set_reg(x, result);
+    s.set_reg(Reg::VF, flag);
+    Ok(())
+}
+
+pub(crate) fn add_reg<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    let (sum, carry) = s.reg(x).overflowing_add(s.reg(y));
+    set_alu_result(s, x, sum, carry as u8)
+}
+
+pub(crate) fn sub_reg<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    let (diff, borrow) = s.reg(x).overflowing_sub(s.reg(y));
+    set_alu_result(s, x, diff, !borrow as u8)
+}
+
+pub(crate) fn sub_reg_rev<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    let (diff, borrow) = s.reg(y).overflowing_sub(s.reg(x));
+    set_alu_result(s, x, diff, !borrow as u8)
+}
+
+fn shift_source<S: ExecState>(s: &S, x: Reg, y: Reg) -> u8 {
+    s.reg(if s.quirks().shift_uses_vy { y } else { x })
+}
+
+pub(crate) fn shift_left<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    let val = shift_source(s, x, y);
+    set_alu_result(s, x, val << 1, val >> 7)
+}
+
+pub(crate) fn shift_right<S: ExecState>(s: &mut S, x: Reg, y: Reg) -> Result<(), MachineErr> {
+    let val = shift_source(s, x, y);
+    set_alu_result(s, x, val >> 1, val & 0x01)
+}
+
+pub(crate) fn set_index<S: ExecState>(s: &mut S, addr: u16) -> Result<(), MachineErr> {
+    s.set_index(addr);
+    Ok(())
+}
+
+pub(crate) fn random<S: ExecState>(s: &mut S, x: Reg, val: u8) -> Result<(), MachineErr> {
+    let byte = s.random_byte();
+    s.set_reg(x, byte & val);
+    Ok(())
+}
+
+pub(crate) fn display<S: ExecState>(s: &mut S, x: Reg, y: Reg, rows: u8) -> Result<(), MachineErr> {
+    let sprite = &mut [0; 15][..rows as usize];
+    s.read_index(sprite)?;
+    let (x, y) = (s.reg(x), s.reg(y));
+    let clip = s.quirks().clip_sprites;
+    let collided = s.display_mut().draw(sprite, x, y, clip);
+    s.set_reg(Reg::VF, collided as u8);
+    Ok(())
+}
+
+pub(crate) fn skip_if_key<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    skip_if(s, s.key(Key::from(s.reg(x))))
+}
+
+pub(crate) fn skip_if_not_key<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    skip_if(s, !s.key(Key::from(s.reg(x))))
+}
+
+pub(crate) fn get_delay_timer<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    s.set_reg(x, s.delay_timer());
+    Ok(())
+}
+
+pub(crate) fn set_delay_timer<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    s.set_delay_timer(s.reg(x));
+    Ok(())
+}
+
+pub(crate) fn set_sound_timer<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    s.set_sound_timer(s.reg(x));
+    Ok(())
+}
+
+// VF is set when I leaves the 12-bit address space and left alone when it
+// does not.
+pub(crate) fn add_index<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    let index = s.index().wrapping_add(s.reg(x) as u16);
+    if index & 0xF000 != 0 {
+        s.set_reg(Reg::VF, 1);
+    }
+    s.set_index(index & 0x0FFF);
+    Ok(())
+}
+
+pub(crate) fn get_key<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    match s.pressed_key() {
+        Some(key) => s.set_reg(x, key.into()),
+        None => s.set_pc(s.pc().wrapping_sub(2)),
+    }
+    Ok(())
+}
+
+pub(crate) fn font<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    let digit = (s.reg(x) & 0x0F) as u16;
+    s.set_index(FONT_OFFSET + digit * font::GLYPH_LEN as u16);
+    Ok(())
+}
+
+pub(crate) fn bcd<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    let val = s.reg(x);
+    s.write_index(&[val / 100, val / 10 % 10, val % 10])
+}
+
+pub(crate) fn store<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    let mut data = [0; 16];
+    for (reg, byte) in all::<Reg>().zip(data.iter_mut()) {
+        *byte = s.reg(reg);
+    }
+    s.write_index(&data[..=x as usize])
+}
+
+pub(crate) fn load<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    let mut data = [0; 16];
+    s.read_index(&mut data[..=x as usize])?;
+    for (reg, byte) in all::<Reg>().zip(data[..=x as usize].iter()) {
+        s.set_reg(reg, *byte);
+    }
+    Ok(())
+}
+
+fn advance_index<S: ExecState>(s: &mut S, len: usize) {
+    s.set_index(((s.index() as usize + len) % MEMORY_SIZE) as u16);
+}
+
+pub(crate) fn store_increment<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    store(s, x)?;
+    advance_index(s, x as usize + 1);
+    Ok(())
+}
+
+pub(crate) fn load_increment<S: ExecState>(s: &mut S, x: Reg) -> Result<(), MachineErr> {
+    load(s, x)?;
+    advance_index(s, x as usize + 1);
+    Ok(())
+}
+
+impl<R: RandomSource> ExecState for Machine<R> {
+    fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    fn reg(&self, reg: Reg) -> u8 {
+        self.reg.get_value(reg)
+    }
+
+    fn set_reg(&mut self, reg: Reg, val: u8) {
+        self.reg.set_value(reg, val);
+    }
+
+    fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    fn index(&self) -> u16 {
+        self.index
+    }
+
+    fn set_index(&mut self, index: u16) {
+        self.index = index;
+    }
+
+    fn stack_mut(&mut self) -> &mut Stack {
+        &mut self.stack
+    }
+
+    fn delay_timer(&self) -> u8 {
+        self.delay_timer.get_value()
+    }
+
+    fn set_delay_timer(&mut self, val: u8) {
+        self.delay_timer.set_value(val);
+    }
+
+    fn set_sound_timer(&mut self, val: u8) {
+        self.sound_timer.set_value(val);
+    }
+
+    fn key(&self, key: Key) -> bool {
+        self.key.get_value(key)
+    }
+
+    fn pressed_key(&self) -> Option<Key> {
+        self.key.get_key_pressed()
+    }
+
+    fn random_byte(&mut self) -> u8 {
+        self.random.next_byte()
+    }
+
+    fn display_mut(&mut self) -> &mut MachDisplay<DISPLAY_WIDTH, DISPLAY_HEIGHT> {
+        &mut self.display
+    }
+
+    // The inherent methods also drop cached decodes and blocks on writes.
+    fn read_index(&self, buf: &mut [u8]) -> Result<(), MachineErr> {
+        Machine::read_index(self, buf)
+    }
+
+    fn write_index(&mut self, data: &[u8]) -> Result<(), MachineErr> {
+        Machine::write_index(self, data)
+    }
+}
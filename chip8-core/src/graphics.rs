@@ -0,0 +1,131 @@
+//! Renders a [`MachDisplay`] onto an embedded-graphics [`DrawTarget`].
+//!
+//! Each CHIP-8 pixel becomes a `scale` x `scale` block placed at `offset` on
+//! the target. A row is pushed as one `fill_contiguous` call, so targets that
+//! stream rectangles to a panel get one transfer per row. With dirty tracking
+//! on, the renderer remembers the rows it last pushed and skips the ones that
+//! have not changed since.
+
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    pixelcolor::{BinaryColor, PixelColor},
+    primitives::Rectangle,
+};
+
+use crate::display::{MachDisplay, Row};
+
+#[derive(Debug, Clone)]
+pub struct DisplayRenderer<const X: usize, const Y: usize, R: Row = u64, C = BinaryColor> {
+    scale: u32,
+    offset: Point,
+    on: C,
+    off: C,
+    track_dirty: bool,
+    // The rows as last pushed, or `None` until the first full render.
+    pushed: Option<[R; Y]>,
+}
+
+impl<const X: usize, const Y: usize, R: Row> Default for DisplayRenderer<X, Y, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const X: usize, const Y: usize, R: Row> DisplayRenderer<X, Y, R> {
+    /// A renderer at scale 1 and offset 0 that draws lit pixels as
+    /// `BinaryColor::On` and pushes every row on every render.
+    pub fn new() -> Self {
+        Self::with_colors(BinaryColor::On, BinaryColor::Off)
+    }
+}
+
+impl<const X: usize, const Y: usize, R: Row, C: PixelColor> DisplayRenderer<X, Y, R, C> {
+    pub fn with_colors(on: C, off: C) -> Self {
+        Self {
+            scale: 1,
+            offset: Point::zero(),
+            on,
+            off,
+            track_dirty: false,
+            pushed: None,
+        }
+    }
+
+    /// Draws each pixel as a `scale` x `scale` block. A scale of 0 is treated
+    /// as 1.
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self.invalidate();
+        self
+    }
+
+    /// Places the top left pixel at `offset` on the target.
+    pub fn with_offset(mut self, offset: Point) -> Self {
+        self.offset = offset;
+        self.invalidate();
+        self
+    }
+
+    /// Only pushes rows that changed since the last render.
+    pub fn with_dirty_tracking(mut self, track_dirty: bool) -> Self {
+        self.track_dirty = track_dirty;
+        self.pushed = None;
+        self
+    }
+
+    /// The area of the target the display covers.
+    pub fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(
+            self.offset,
+            Size::new(X as u32 * self.scale, Y as u32 * self.scale),
+        )
+    }
+
+    /// Forgets what was pushed, so the next render redraws every row. Call it
+    /// after something else drew over the display's area.
+    pub fn invalidate(&mut self) {
+        self.pushed = None;
+    }
+
+    /// Draws `display` onto `target` and returns how many rows were pushed.
+    pub fn render<D>(
+        &mut self,
+        display: &MachDisplay<X, Y, R>,
+        target: &mut D,
+    ) -> Result<usize, D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let mut pushed = 0;
+        for (y, &row) in display.rows().iter().enumerate() {
+            if self.track_dirty && self.pushed.is_some_and(|rows| rows[y] == row) {
+                continue;
+            }
+            self.push_row(y, row, target)?;
+            pushed += 1;
+        }
+        if self.track_dirty {
+            let mut rows = [R::EMPTY; Y];
+            rows.copy_from_slice(display.rows());
+            self.pushed = Some(rows);
+        }
+        Ok(pushed)
+    }
+
+    fn push_row<D>(&self, y: usize, row: R, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let scale = self.scale as usize;
+        let area = Rectangle::new(
+            self.offset + Point::new(0, (y * scale) as i32),
+            Size::new((X * scale) as u32, self.scale),
+        );
+        let (on, off) = (self.on, self.off);
+        let colors = (0..scale).flat_map(move |_| {
+            (0..X * scale).map(move |x| if row.get(x / scale) { on } else { off })
+        });
+        target.fill_contiguous(&area, colors)
+    }
+}
@@ -0,0 +1,589 @@
+//! x86-64 dynamic recompiler.
+//!
+//! Once execution has entered an address [`HOT`] times, the straight-line run
+//! of instructions starting there is compiled to native code. Only register,
+//! I and branch instructions are compiled: a block ends after a jump or
+//! register skip, and just before anything that touches memory, the stack,
+//! the screen, the keypad, the timers or the random source. The machine runs
+//! those through [`Machine::step`], so draws, key waits and timer reads always
+//! go to the interpreter.
+//!
+//! While [`Machine::run_frame`] runs, V0-VF, I and PC live in a [`Context`]
+//! that native blocks take in `rdi` and update in place; it is copied back to
+//! the machine before every interpreted instruction. Compiled code never writes
+//! memory, so a block cannot change under itself. When an interpreted store
+//! lands on compiled code, the blocks covering it are dropped and the written
+//! bytes are never compiled again, which leaves self-modifying code to the
+//! interpreter.
+//!
+//! Code lives in one mapping that is writable only while a block is being
+//! copied in and executable otherwise. A full mapping is flushed and refilled.
+
+#[cfg(not(all(target_arch = "x86_64", target_os = "linux")))]
+compile_error!("the jit feature needs x86-64 Linux");
+
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    mem, ptr,
+};
+
+use super::{font, reg::RegBank, Command, Machine, MachineErr, Quirks, RandomSource, Reg};
+use super::{FONT_OFFSET, MEMORY_SIZE};
+
+/// Entries into an address before the block starting there is compiled.
+pub const HOT: u8 = 8;
+const MAX_BLOCK_LEN: usize = 32;
+const CODE_LEN: usize = 256 * 1024;
+// Room for the longest block: every instruction, and the exit after the last
+// one, takes at most 32 bytes.
+const MAX_BLOCK_CODE: usize = (MAX_BLOCK_LEN + 1) * 32;
+
+/// The machine state native blocks work on.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Context {
+    v: [u8; 16],
+    index: u16,
+    pc: u16,
+}
+
+// Byte offsets into `Context`.
+const VF: u8 = 15;
+const INDEX: u8 = 16;
+const PC: u8 = 18;
+
+type BlockFn = unsafe extern "sysv64" fn(*mut Context);
+
+#[derive(Debug, Clone, Copy)]
+struct Block {
+    offset: usize,
+    len: usize,
+}
+
+struct CodeBuffer {
+    ptr: *mut u8,
+    used: usize,
+}
+
+// The mapping is owned by one buffer and only changed through `&mut self`.
+unsafe impl Send for CodeBuffer {}
+unsafe impl Sync for CodeBuffer {}
+
+impl CodeBuffer {
+    fn new() -> Option<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                CODE_LEN,
+                libc::PROT_READ | libc::PROT_EXEC,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return None;
+        }
+        Some(Self {
+            ptr: ptr.cast(),
+            used: 0,
+        })
+    }
+
+    /// Copies `code` in and returns its offset, or `None` when it is full.
+    fn push(&mut self, code: &[u8]) -> Option<usize> {
+        if self.used + code.len() > CODE_LEN {
+            return None;
+        }
+        let offset = self.used;
+        unsafe {
+            self.protect(libc::PROT_READ | libc::PROT_WRITE);
+            ptr::copy_nonoverlapping(code.as_ptr(), self.ptr.add(offset), code.len());
+            self.protect(libc::PROT_READ | libc::PROT_EXEC);
+        }
+        self.used += code.len();
+        Some(offset)
+    }
+
+    unsafe fn protect(&mut self, prot: libc::c_int) {
+        let result = libc::mprotect(self.ptr.cast(), CODE_LEN, prot);
+        assert_eq!(result, 0, "mprotect failed on the JIT code buffer");
+    }
+
+    /// Runs the block at `offset`.
+    ///
+    /// # Safety
+    ///
+    /// `offset` must be the start of a block written by [`JitCache::compile`].
+    unsafe fn call(&self, offset: usize, ctx: &mut Context) {
+        let entry: BlockFn = mem::transmute(self.ptr.add(offset));
+        entry(ctx);
+    }
+}
+
+impl Drop for CodeBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr.cast(), CODE_LEN);
+        }
+    }
+}
+
+pub struct JitCache {
+    // `None` while another backend is selected.
+    code: Option<CodeBuffer>,
+    // Indexed by address, like the other tables.
+    blocks: Vec<Option<Block>>,
+    heat: Vec<u8>,
+    // Bytes that belonged to some block since the last flush.
+    compiled: Vec<bool>,
+    // Bytes written after they were compiled; never compiled again.
+    modified: Vec<bool>,
+    scratch: Vec<u8>,
+}
+
+// Compiled code is not copied; a clone starts cold with its own mapping.
+impl Clone for JitCache {
+    fn clone(&self) -> Self {
+        let mut jit = Self::new();
+        jit.set_enabled(self.is_enabled());
+        jit
+    }
+}
+
+impl Debug for JitCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JitCache")
+            .field("enabled", &self.is_enabled())
+            .field("blocks", &self.blocks.iter().flatten().count())
+            .field("code", &self.code.as_ref().map_or(0, |code| code.used))
+            .finish()
+    }
+}
+
+impl Default for JitCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JitCache {
+    pub fn new() -> Self {
+        Self {
+            code: None,
+            blocks: Vec::new(),
+            heat: Vec::new(),
+            compiled: Vec::new(),
+            modified: Vec::new(),
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.code.is_some()
+    }
+
+    /// Stays disabled if executable memory cannot be mapped.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        *self = Self::new();
+        if !enabled {
+            return;
+        }
+        let Some(code) = CodeBuffer::new() else {
+            return;
+        };
+        self.code = Some(code);
+        self.blocks = vec![None; MEMORY_SIZE];
+        self.heat = vec![0; MEMORY_SIZE];
+        self.compiled = vec![false; MEMORY_SIZE];
+        self.modified = vec![false; MEMORY_SIZE];
+        self.scratch = Vec::with_capacity(MAX_BLOCK_CODE);
+    }
+
+    fn get(&self, addr: u16) -> Option<Block> {
+        self.blocks.get(addr as usize).copied().flatten()
+    }
+
+    #[cfg(test)]
+    pub fn is_compiled(&self, addr: u16) -> bool {
+        self.get(addr).is_some()
+    }
+
+    // Counts an entry into `addr` and reports whether it is now hot.
+    fn warm(&mut self, addr: u16) -> bool {
+        let Some(heat) = self.heat.get_mut(addr as usize) else {
+            return false;
+        };
+        *heat = heat.saturating_add(1);
+        *heat >= HOT
+    }
+
+    fn is_modified(&self, addr: u16) -> bool {
+        self.modified[addr as usize..][..2].contains(&true)
+    }
+
+    fn flush(&mut self) {
+        self.blocks.fill(None);
+        self.heat.fill(0);
+        self.compiled.fill(false);
+        if let Some(code) = self.code.as_mut() {
+            code.used = 0;
+        }
+    }
+
+    /// Forgets everything, including which bytes were modified. Used when a
+    /// new program is loaded.
+    pub fn reset(&mut self) {
+        self.flush();
+        self.modified.fill(false);
+    }
+
+    /// Drops every block that overlaps the `len` bytes at `addr` and marks
+    /// the compiled bytes among them as modified.
+    pub fn invalidate(&mut self, addr: usize, len: usize) {
+        let end = (addr + len).min(self.compiled.len());
+        for addr in addr..end {
+            if !self.compiled[addr] {
+                continue;
+            }
+            self.modified[addr] = true;
+            for start in addr.saturating_sub(MAX_BLOCK_LEN * 2 - 1)..=addr {
+                if self.blocks[start].is_some_and(|block| start + block.len * 2 > addr) {
+                    self.blocks[start] = None;
+                }
+            }
+        }
+    }
+
+    fn compile(&mut self, pc: u16, commands: &[Command], quirks: Quirks) -> Option<Block> {
+        if commands.is_empty() {
+            // Try again after another `HOT` entries.
+            self.heat[pc as usize] = 0;
+            return None;
+        }
+        self.scratch.clear();
+        let mut asm = Asm(&mut self.scratch);
+        let mut addr = pc;
+        for &command in commands {
+            emit(&mut asm, command, addr, quirks);
+            addr = addr.wrapping_add(2);
+        }
+        if !ends_block(commands[commands.len() - 1]) {
+            asm.set_word(PC, addr);
+            asm.ret();
+        }
+        debug_assert!(self.scratch.len() <= MAX_BLOCK_CODE);
+
+        let code = self.code.as_mut()?;
+        let offset = match code.push(&self.scratch) {
+            Some(offset) => offset,
+            None => {
+                self.flush();
+                self.code.as_mut()?.push(&self.scratch)?
+            }
+        };
+        let block = Block {
+            offset,
+            len: commands.len(),
+        };
+        self.compiled[pc as usize..addr as usize].fill(true);
+        self.blocks[pc as usize] = Some(block);
+        Some(block)
+    }
+}
+
+impl<R: RandomSource> Machine<R> {
+    fn jit_context(&self) -> Context {
+        Context {
+            v: self.reg.to_array(),
+            index: self.index,
+            pc: self.pc,
+        }
+    }
+
+    fn set_jit_context(&mut self, ctx: &Context) {
+        self.reg = RegBank::from_array(ctx.v);
+        self.index = ctx.index;
+        self.pc = ctx.pc;
+    }
+
+    pub(crate) fn run_jit(&mut self, instructions: usize) -> Result<(), MachineErr> {
+        let mut ctx = self.jit_context();
+        let mut left = instructions;
+        while left > 0 {
+            let block = match self.jit.get(ctx.pc) {
+                Some(block) => Some(block),
+                None if self.jit.warm(ctx.pc) => self.compile_block(ctx.pc),
+                None => None,
+            };
+            match (block, self.jit.code.as_ref()) {
+                (Some(block), Some(code)) if block.len <= left => {
+                    unsafe { code.call(block.offset, &mut ctx) };
+                    self.cycles += block.len as u64;
+                    left -= block.len;
+                }
+                _ => {
+                    self.set_jit_context(&ctx);
+                    self.step()?;
+                    ctx = self.jit_context();
+                    left -= 1;
+                }
+            }
+        }
+        self.set_jit_context(&ctx);
+        Ok(())
+    }
+
+    fn compile_block(&mut self, pc: u16) -> Option<Block> {
+        let mut commands = [Command::ClearScreen; MAX_BLOCK_LEN];
+        let mut len = 0;
+        let mut addr = pc;
+        while len < MAX_BLOCK_LEN {
+            let Some(command) = self
+                .peek_opcode(addr)
+                .ok()
+                .and_then(|opcode| self.decode_command(opcode).ok())
+            else {
+                break;
+            };
+            if self.jit.is_modified(addr) || !compiles(command) {
+                break;
+            }
+            commands[len] = command;
+            len += 1;
+            addr = addr.wrapping_add(2);
+            if ends_block(command) {
+                break;
+            }
+        }
+        self.jit.compile(pc, &commands[..len], self.quirks)
+    }
+}
+
+fn compiles(command: Command) -> bool {
+    matches!(
+        command,
+        Command::Jump(_)
+            | Command::SkipIfRegVal(..)
+            | Command::SkipIfRegValNot(..)
+            | Command::SkipIfRegEqual(..)
+            | Command::SkipIfRegNotEqual(..)
+            | Command::SetVal(..)
+            | Command::AddVal(..)
+            | Command::SetReg(..)
+            | Command::BinOR(..)
+            | Command::BinAND(..)
+            | Command::LogXOR(..)
+            | Command::AddReg(..)
+            | Command::SubReg(..)
+            | Command::SubRegRev(..)
+            | Command::ShiftLeft(..)
+            | Command::ShiftRight(..)
+            | Command::SetIndex(_)
+            | Command::AddIndex(_)
+            | Command::Font(_)
+    )
+}
+
+fn ends_block(command: Command) -> bool {
+    matches!(
+        command,
+        Command::Jump(_)
+            | Command::SkipIfRegVal(..)
+            | Command::SkipIfRegValNot(..)
+            | Command::SkipIfRegEqual(..)
+            | Command::SkipIfRegNotEqual(..)
+    )
+}
+
+fn reg(reg: Reg) -> u8 {
+    reg as u8
+}
+
+// Emits `command`, which sits at `addr`, mirroring its arm in
+// `Machine::execute_command`. `eax`, `ecx` and `edx` are scratch.
+fn emit(asm: &mut Asm, command: Command, addr: u16, quirks: Quirks) {
+    let next = addr.wrapping_add(2);
+    match command {
+        Command::Jump(target) => {
+            asm.set_word(PC, target);
+            asm.ret();
+        }
+        Command::SkipIfRegVal(x, val) => {
+            asm.cmp_byte(reg(x), val);
+            asm.skip_if(next, CMOVE);
+        }
+        Command::SkipIfRegValNot(x, val) => {
+            asm.cmp_byte(reg(x), val);
+            asm.skip_if(next, CMOVNE);
+        }
+        Command::SkipIfRegEqual(x, y) => {
+            asm.load(EDX, reg(x));
+            asm.alu(CMP, EDX, reg(y));
+            asm.skip_if(next, CMOVE);
+        }
+        Command::SkipIfRegNotEqual(x, y) => {
+            asm.load(EDX, reg(x));
+            asm.alu(CMP, EDX, reg(y));
+            asm.skip_if(next, CMOVNE);
+        }
+        Command::SetVal(x, val) => asm.set_byte(reg(x), val),
+        Command::AddVal(x, val) => asm.add_byte(reg(x), val),
+        Command::SetReg(x, y) => {
+            asm.load(EAX, reg(y));
+            asm.store(EAX, reg(x));
+        }
+        Command::BinOR(x, y) | Command::BinAND(x, y) | Command::LogXOR(x, y) => {
+            let op = match command {
+                Command::BinOR(..) => OR,
+                Command::BinAND(..) => AND,
+                _ => XOR,
+            };
+            asm.load(EAX, reg(x));
+            asm.alu(op, EAX, reg(y));
+            asm.store(EAX, reg(x));
+            if quirks.vf_reset {
+                asm.set_byte(VF, 0);
+            }
+        }
+        Command::AddReg(x, y) => {
+            asm.load(EAX, reg(x));
+            asm.alu(ADD, EAX, reg(y));
+            asm.bytes(&[0x0F, 0x92, 0xC1]); // setc cl
+            asm.store_result(reg(x));
+        }
+        Command::SubReg(x, y) => {
+            asm.load(EAX, reg(x));
+            asm.alu(SUB, EAX, reg(y));
+            asm.bytes(&[0x0F, 0x93, 0xC1]); // setnc cl
+            asm.store_result(reg(x));
+        }
+        Command::SubRegRev(x, y) => {
+            asm.load(EAX, reg(y));
+            asm.alu(SUB, EAX, reg(x));
+            asm.bytes(&[0x0F, 0x93, 0xC1]); // setnc cl
+            asm.store_result(reg(x));
+        }
+        Command::ShiftLeft(x, y) | Command::ShiftRight(x, y) => {
+            let source = if quirks.shift_uses_vy { y } else { x };
+            asm.load(EAX, reg(source));
+            asm.bytes(&[0x89, 0xC1]); // mov ecx, eax
+            match command {
+                // shr cl, 7; shl al, 1
+                Command::ShiftLeft(..) => asm.bytes(&[0xC0, 0xE9, 0x07, 0xD0, 0xE0]),
+                // and cl, 1; shr al, 1
+                _ => asm.bytes(&[0x80, 0xE1, 0x01, 0xD0, 0xE8]),
+            }
+            asm.store_result(reg(x));
+        }
+        Command::SetIndex(val) => asm.set_word(INDEX, val),
+        Command::AddIndex(x) => {
+            asm.load(EAX, reg(x));
+            asm.bytes(&[0x0F, 0xB7, 0x4F, INDEX]); // movzx ecx, word [rdi + INDEX]
+            asm.bytes(&[0x01, 0xC1]); // add ecx, eax
+            asm.bytes(&[0xF7, 0xC1, 0x00, 0xF0, 0x00, 0x00]); // test ecx, 0xF000
+            asm.bytes(&[0x74, 0x04]); // jz past the next instruction
+            asm.set_byte(VF, 1);
+            asm.bytes(&[0x81, 0xE1, 0xFF, 0x0F, 0x00, 0x00]); // and ecx, 0x0FFF
+            asm.bytes(&[0x66, 0x89, 0x4F, INDEX]); // mov [rdi + INDEX], cx
+        }
+        Command::Font(x) => {
+            asm.load(EAX, reg(x));
+            asm.bytes(&[0x83, 0xE0, 0x0F]); // and eax, 0x0F
+            asm.bytes(&[0x6B, 0xC0, font::GLYPH_LEN as u8]); // imul eax, eax, GLYPH_LEN
+            asm.bytes(&[0x05]); // add eax, FONT_OFFSET
+            asm.bytes(&(FONT_OFFSET as u32).to_le_bytes());
+            asm.bytes(&[0x66, 0x89, 0x47, INDEX]); // mov [rdi + INDEX], ax
+        }
+        _ => unreachable!("{:?} is not compiled", command),
+    }
+}
+
+// Register numbers for ModRM.
+const EAX: u8 = 0;
+const ECX: u8 = 1;
+const EDX: u8 = 2;
+
+// `op r8, byte [rdi + disp8]` opcodes.
+const ADD: u8 = 0x02;
+const OR: u8 = 0x0A;
+const AND: u8 = 0x22;
+const SUB: u8 = 0x2A;
+const XOR: u8 = 0x32;
+const CMP: u8 = 0x3A;
+
+// `cmovcc eax, ecx` second opcode bytes.
+const CMOVE: u8 = 0x44;
+const CMOVNE: u8 = 0x45;
+
+struct Asm<'a>(&'a mut Vec<u8>);
+
+impl Asm<'_> {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    // ModRM for `[rdi + disp8]` with `reg` in the reg field.
+    fn rdi(reg: u8) -> u8 {
+        0x47 | reg << 3
+    }
+
+    /// `movzx r32, byte [rdi + offset]`
+    fn load(&mut self, reg: u8, offset: u8) {
+        self.bytes(&[0x0F, 0xB6, Self::rdi(reg), offset]);
+    }
+
+    /// `mov byte [rdi + offset], r8`
+    fn store(&mut self, reg: u8, offset: u8) {
+        self.bytes(&[0x88, Self::rdi(reg), offset]);
+    }
+
+    /// `op r8, byte [rdi + offset]`
+    fn alu(&mut self, op: u8, reg: u8, offset: u8) {
+        self.bytes(&[op, Self::rdi(reg), offset]);
+    }
+
+    /// Stores `al` into VX and then `cl` into VF, so VF holds the flag even
+    /// when it is also VX.
+    fn store_result(&mut self, offset: u8) {
+        self.store(EAX, offset);
+        self.store(ECX, VF);
+    }
+
+    /// `mov byte [rdi + offset], val`
+    fn set_byte(&mut self, offset: u8, val: u8) {
+        self.bytes(&[0xC6, Self::rdi(0), offset, val]);
+    }
+
+    /// `add byte [rdi + offset], val`
+    fn add_byte(&mut self, offset: u8, val: u8) {
+        self.bytes(&[0x80, Self::rdi(0), offset, val]);
+    }
+
+    /// `cmp byte [rdi + offset], val`
+    fn cmp_byte(&mut self, offset: u8, val: u8) {
+        self.bytes(&[0x80, Self::rdi(7), offset, val]);
+    }
+
+    /// `mov word [rdi + offset], val`
+    fn set_word(&mut self, offset: u8, val: u16) {
+        let [lo, hi] = val.to_le_bytes();
+        self.bytes(&[0x66, 0xC7, Self::rdi(0), offset, lo, hi]);
+    }
+
+    /// Sets PC to `next`, or past it when `cmov` takes, and returns.
+    fn skip_if(&mut self, next: u16, cmov: u8) {
+        self.bytes(&[0xB8]); // mov eax, next
+        self.bytes(&(next as u32).to_le_bytes());
+        self.bytes(&[0xB9]); // mov ecx, next + 2
+        self.bytes(&(next.wrapping_add(2) as u32).to_le_bytes());
+        self.bytes(&[0x0F, cmov, 0xC1]); // cmovcc eax, ecx
+        self.bytes(&[0x66, 0x89, Self::rdi(EAX), PC]); // mov [rdi + PC], ax
+        self.ret();
+    }
+
+    fn ret(&mut self) {
+        self.bytes(&[0xC3]);
+    }
+}
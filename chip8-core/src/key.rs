@@ -45,25 +45,25 @@ impl From<u8> for Key {
     }
 }
 
-impl Into<u8> for Key {
-    fn into(self) -> u8 {
-        match self {
-            Self::Key0 => 0x0,
-            Self::Key1 => 0x1,
-            Self::Key2 => 0x2,
-            Self::Key3 => 0x3,
-            Self::Key4 => 0x4,
-            Self::Key5 => 0x5,
-            Self::Key6 => 0x6,
-            Self::Key7 => 0x7,
-            Self::Key8 => 0x8,
-            Self::Key9 => 0x9,
-            Self::KeyA => 0xA,
-            Self::KeyB => 0xB,
-            Self::KeyC => 0xC,
-            Self::KeyD => 0xD,
-            Self::KeyE => 0xE,
-            Self::KeyF => 0xF,
+impl From<Key> for u8 {
+    fn from(key: Key) -> Self {
+        match key {
+            Key::Key0 => 0x0,
+            Key::Key1 => 0x1,
+            Key::Key2 => 0x2,
+            Key::Key3 => 0x3,
+            Key::Key4 => 0x4,
+            Key::Key5 => 0x5,
+            Key::Key6 => 0x6,
+            Key::Key7 => 0x7,
+            Key::Key8 => 0x8,
+            Key::Key9 => 0x9,
+            Key::KeyA => 0xA,
+            Key::KeyB => 0xB,
+            Key::KeyC => 0xC,
+            Key::KeyD => 0xD,
+            Key::KeyE => 0xE,
+            Key::KeyF => 0xF,
         }
     }
 }
@@ -134,44 +134,36 @@ impl KeyBank {
     }
 
     fn get_key_ref_mut(&mut self, key: Key) -> &mut bool {
-        let bank_key: &mut bool;
         match key {
-            Key::Key0 => bank_key = &mut self.Key0,
-            Key::Key1 => bank_key = &mut self.Key1,
-            Key::Key2 => bank_key = &mut self.Key2,
-            Key::Key3 => bank_key = &mut self.Key3,
-            Key::Key4 => bank_key = &mut self.Key4,
-            Key::Key5 => bank_key = &mut self.Key5,
-            Key::Key6 => bank_key = &mut self.Key6,
-            Key::Key7 => bank_key = &mut self.Key7,
-            Key::Key8 => bank_key = &mut self.Key8,
-            Key::Key9 => bank_key = &mut self.Key9,
-            Key::KeyA => bank_key = &mut self.KeyA,
-            Key::KeyB => bank_key = &mut self.KeyB,
-            Key::KeyC => bank_key = &mut self.KeyC,
-            Key::KeyD => bank_key = &mut self.KeyD,
-            Key::KeyE => bank_key = &mut self.KeyE,
-            Key::KeyF => bank_key = &mut self.KeyF,
+            Key::Key0 => &mut self.Key0,
+            Key::Key1 => &mut self.Key1,
+            Key::Key2 => &mut self.Key2,
+            Key::Key3 => &mut self.Key3,
+            Key::Key4 => &mut self.Key4,
+            Key::Key5 => &mut self.Key5,
+            Key::Key6 => &mut self.Key6,
+            Key::Key7 => &mut self.Key7,
+            Key::Key8 => &mut self.Key8,
+            Key::Key9 => &mut self.Key9,
+            Key::KeyA => &mut self.KeyA,
+            Key::KeyB => &mut self.KeyB,
+            Key::KeyC => &mut self.KeyC,
+            Key::KeyD => &mut self.KeyD,
+            Key::KeyE => &mut self.KeyE,
+            Key::KeyF => &mut self.KeyF,
         }
-        bank_key
     }
 
     pub fn get_value(&self, key: Key) -> bool {
         self.get_key(key)
     }
 
-    #[allow(dead_code)]
     pub fn set_value(&mut self, key: Key, val: bool) {
         let bank_key = self.get_key_ref_mut(key);
         *bank_key = val;
     }
 
-    pub fn get_key_pressed(&self) -> u8 {
-        for key_num in all::<Key>() {
-            if self.get_key(key_num) {
-                return key_num.into();
-            }
-        }
-        0
+    pub fn get_key_pressed(&self) -> Option<Key> {
+        all::<Key>().find(|key| self.get_key(*key))
     }
 }
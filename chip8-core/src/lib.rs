@@ -0,0 +1,445 @@
+//! CHIP-8 interpreter core.
+//!
+//! The crate is `no_std` and, with default features off, allocation-free, so
+//! it runs on microcontrollers. The `alloc` feature (on by default) adds boxed
+//! random sources, [`ScriptedRandom`], saving and loading states, the decode
+//! cache, the threaded [`Backend`] and [`MachineBatch`]. Without it every
+//! instruction is fetched and decoded as it runs. The `embedded-graphics` feature
+//! adds [`DisplayRenderer`], and the `jit` feature, on x86-64 Linux only, adds
+//! a native-code backend.
+
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+mod batch;
+#[cfg(feature = "alloc")]
+mod cache;
+mod command;
+mod display;
+mod exec;
+mod font;
+#[cfg(feature = "embedded-graphics")]
+mod graphics;
+#[cfg(feature = "jit")]
+mod jit;
+mod key;
+mod memory;
+mod quirks;
+mod random;
+mod reg;
+mod stack;
+#[cfg(feature = "alloc")]
+mod state;
+#[cfg(feature = "alloc")]
+mod threaded;
+mod timer;
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests;
+
+use core::fmt::Display;
+
+#[cfg(feature = "alloc")]
+pub use batch::{BatchChunk, BatchErr, MachineBatch, Observation};
+#[cfg(feature = "alloc")]
+use cache::DecodeCache;
+pub use command::Command;
+pub use display::{MachDisplay, PixelMut, Row};
+#[cfg(feature = "embedded-graphics")]
+pub use graphics::DisplayRenderer;
+#[cfg(feature = "jit")]
+use jit::JitCache;
+pub use key::Key;
+use key::KeyBank;
+use memory::{Memory, MemoryErr};
+pub use quirks::{Platform, PlatformErr, Quirks};
+#[cfg(feature = "alloc")]
+pub use random::{CloneRandomSource, ScriptedRandom};
+pub use random::{RandomErr, RandomSource, SeededRandom, RANDOM_STATE_LEN};
+pub use reg::Reg;
+use reg::RegBank;
+use stack::{Stack, StackErr};
+#[cfg(feature = "alloc")]
+pub use state::StateErr;
+#[cfg(feature = "alloc")]
+pub use threaded::Backend;
+#[cfg(feature = "alloc")]
+use threaded::BlockCache;
+use timer::Timer;
+
+pub use self::command::CommandErr;
+pub use self::command::RawCommand;
+
+pub const MEMORY_SIZE: usize = 4096;
+pub const DISPLAY_WIDTH: usize = 64;
+pub const DISPLAY_HEIGHT: usize = 32;
+pub const FRAMES_PER_SECOND: u32 = 60;
+pub const INSTRUCTIONS_PER_FRAME: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct Machine<R: RandomSource = SeededRandom> {
+    memory: Memory<MEMORY_SIZE>,
+    display: MachDisplay<DISPLAY_WIDTH, DISPLAY_HEIGHT>,
+    pc: u16,
+    index: u16,
+    stack: Stack,
+    delay_timer: Timer,
+    sound_timer: Timer,
+    reg: RegBank,
+    key: KeyBank,
+    cycles: u64,
+    quirks: Quirks,
+    strict_memory: bool,
+    random: R,
+    #[cfg(feature = "alloc")]
+    decoded: DecodeCache,
+    #[cfg(feature = "alloc")]
+    threaded: BlockCache<R>,
+    #[cfg(feature = "jit")]
+    jit: JitCache,
+}
+
+impl<R: RandomSource> Display for Machine<R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "Machine {{")?;
+        writeln!(f, "PC: {:#x?}", self.pc)?;
+        writeln!(f, "Index: {:#x?}", self.index)?;
+        writeln!(f, "{:#x?}", self.stack)?;
+        writeln!(f, "{:#x?}", self.delay_timer)?;
+        writeln!(f, "{:#x?}", self.sound_timer)?;
+        writeln!(f, "{:#x?}", self.reg)?;
+        writeln!(f, "}}")
+    }
+}
+
+#[derive(Debug)]
+pub struct MachineErr;
+
+impl From<CommandErr> for MachineErr {
+    fn from(_: CommandErr) -> Self {
+        MachineErr
+    }
+}
+
+impl From<MemoryErr> for MachineErr {
+    fn from(_: MemoryErr) -> Self {
+        MachineErr
+    }
+}
+
+impl From<StackErr> for MachineErr {
+    fn from(_: StackErr) -> Self {
+        MachineErr
+    }
+}
+
+const LOAD_OFFSET: u16 = 0x200;
+const FONT_OFFSET: u16 = 0x050;
+
+fn decode(command: u16, quirks: Quirks) -> Result<Command, CommandErr> {
+    let command = RawCommand(command).try_into()?;
+    Ok(match command {
+        Command::Store(reg_x) if quirks.memory_increment => {
+            Command::StoreWithIndexIncrement(reg_x)
+        }
+        Command::Load(reg_x) if quirks.memory_increment => Command::LoadWithIndexIncrement(reg_x),
+        Command::JumpWithOffset(addr, _) if quirks.jump_uses_vx => {
+            Command::JumpWithOffset(addr, reg::Reg::from((addr >> 8) as u8))
+        }
+        command => command,
+    })
+}
+
+// The target of `BNNN`, which wraps within the 12-bit address space as I does.
+fn offset_jump(addr: u16, offset: u8) -> u16 {
+    (addr + offset as u16) & 0x0FFF
+}
+
+impl<R: RandomSource + Default> Default for Machine<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: RandomSource + Default> Machine<R> {
+    pub fn new() -> Self {
+        Self::with_quirks(Quirks::default())
+    }
+
+    pub fn with_quirks(quirks: Quirks) -> Self {
+        Self::with_random(quirks, R::default())
+    }
+}
+
+impl<R: RandomSource> Machine<R> {
+    pub fn with_random(quirks: Quirks, random: R) -> Self {
+        let mut memory = Memory::new();
+        memory.as_mut_slice()[FONT_OFFSET as usize..][..font::FONT.len()]
+            .copy_from_slice(&font::FONT);
+        Self {
+            memory,
+            display: MachDisplay::new(),
+            pc: LOAD_OFFSET,
+            index: 0,
+            stack: Stack::new(),
+            delay_timer: Timer::new(),
+            sound_timer: Timer::new(),
+            reg: RegBank::new(),
+            key: KeyBank::new(),
+            cycles: 0,
+            quirks,
+            strict_memory: false,
+            random,
+            #[cfg(feature = "alloc")]
+            decoded: DecodeCache::new(),
+            #[cfg(feature = "alloc")]
+            threaded: BlockCache::new(),
+            #[cfg(feature = "jit")]
+            jit: JitCache::new(),
+        }
+    }
+
+    pub fn load(&mut self, prog_data: &[u8]) -> Result<(), MachineErr> {
+        let mem_data = self.memory.get_mut_data(LOAD_OFFSET, prog_data.len())?;
+        mem_data.copy_from_slice(prog_data);
+        #[cfg(feature = "alloc")]
+        self.decoded
+            .invalidate(LOAD_OFFSET as usize, prog_data.len());
+        #[cfg(feature = "alloc")]
+        self.threaded
+            .invalidate(LOAD_OFFSET as usize, prog_data.len());
+        #[cfg(feature = "jit")]
+        self.jit.reset();
+        Ok(())
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    pub fn reg(&self, reg: Reg) -> u8 {
+        self.reg.get_value(reg)
+    }
+
+    pub fn stack(&self) -> &[u16] {
+        self.stack.as_slice()
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer.get_value()
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer.get_value()
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        self.memory.as_slice()
+    }
+
+    pub fn display(&self) -> &MachDisplay<DISPLAY_WIDTH, DISPLAY_HEIGHT> {
+        &self.display
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn strict_memory(&self) -> bool {
+        self.strict_memory
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn decode_cache(&self) -> bool {
+        self.decoded.is_enabled()
+    }
+
+    /// Index-relative accesses past the end of memory wrap around to 0x000
+    /// unless strict memory is on, in which case they fault.
+    pub fn set_strict_memory(&mut self, strict: bool) {
+        self.strict_memory = strict;
+    }
+
+    /// Turns the decoded-instruction cache on or off. It is on by default and
+    /// never changes behaviour, only speed.
+    #[cfg(feature = "alloc")]
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decoded.set_enabled(enabled);
+    }
+
+    pub fn set_random(&mut self, random: R) {
+        self.random = random;
+    }
+
+    pub fn key(&self, key: Key) -> bool {
+        self.key.get_value(key)
+    }
+
+    pub fn set_key(&mut self, key: Key, pressed: bool) {
+        self.key.set_value(key, pressed);
+    }
+
+    /// Sets VX. Like the other setters below, this is for code that drives
+    /// the machine without [`Machine::step`], such as recompiled ROMs.
+    pub fn set_reg(&mut self, reg: Reg, val: u8) {
+        self.reg.set_value(reg, val);
+    }
+
+    pub fn set_index(&mut self, index: u16) {
+        self.index = index;
+    }
+
+    /// Copies `data` into memory at `addr`, dropping any cached code there.
+    pub fn write_memory(&mut self, addr: u16, data: &[u8]) -> Result<(), MachineErr> {
+        self.memory
+            .get_mut_data(addr, data.len())?
+            .copy_from_slice(data);
+        #[cfg(feature = "alloc")]
+        self.decoded.invalidate(addr as usize, data.len());
+        #[cfg(feature = "alloc")]
+        self.threaded.invalidate(addr as usize, data.len());
+        #[cfg(feature = "jit")]
+        self.jit.invalidate(addr as usize, data.len());
+        Ok(())
+    }
+
+    /// Counts `instructions` as executed without running them.
+    pub fn retire(&mut self, instructions: u64) {
+        self.cycles += instructions;
+    }
+
+    pub fn peek_opcode(&self, addr: u16) -> Result<u16, MachineErr> {
+        Ok(u16::from_be_bytes(self.memory.get_command_data(addr)?))
+    }
+
+    pub fn peek_command(&self, addr: u16) -> Result<Command, MachineErr> {
+        if let Some(command) = self.cached_command(addr) {
+            return Ok(command);
+        }
+        let command = self.peek_opcode(addr)?;
+        Ok(self.decode_command(command)?)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn cached_command(&self, addr: u16) -> Option<Command> {
+        self.decoded.get(addr)
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn cached_command(&self, _addr: u16) -> Option<Command> {
+        None
+    }
+
+    fn fetch_command(&mut self) -> Result<u16, MachineErr> {
+        let command = self.peek_opcode(self.pc)?;
+        self.increment_pc();
+        Ok(command)
+    }
+
+    fn decode_command(&self, command: u16) -> Result<Command, CommandErr> {
+        decode(command, self.quirks)
+    }
+
+    fn increment_pc(&mut self) {
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    pub fn set_pc(&mut self, addr: u16) {
+        self.pc = addr;
+    }
+
+    fn read_index(&self, buf: &mut [u8]) -> Result<(), MachineErr> {
+        memory::read_index(self.memory.as_slice(), self.index, self.strict_memory, buf)?;
+        Ok(())
+    }
+
+    fn write_index(&mut self, data: &[u8]) -> Result<(), MachineErr> {
+        memory::write_index(
+            self.memory.as_mut_slice(),
+            self.index,
+            self.strict_memory,
+            data,
+        )?;
+        #[cfg(feature = "alloc")]
+        for addr in memory::index_addrs(MEMORY_SIZE, self.index, data.len()) {
+            self.decoded.invalidate(addr, 1);
+            self.threaded.invalidate(addr, 1);
+            #[cfg(feature = "jit")]
+            self.jit.invalidate(addr, 1);
+        }
+        Ok(())
+    }
+
+    fn execute_command(&mut self, command: Command) -> Result<(), MachineErr> {
+        exec::execute(self, command)
+    }
+
+    /// Runs `command` as the instruction at PC, exactly as [`Machine::step`]
+    /// would after fetching and decoding it.
+    pub fn execute(&mut self, command: Command) -> Result<(), MachineErr> {
+        self.increment_pc();
+        self.execute_command(command)?;
+        self.cycles += 1;
+        Ok(())
+    }
+
+    pub fn step(&mut self) -> Result<Command, MachineErr> {
+        let pc = self.pc;
+        let command = match self.cached_command(pc) {
+            Some(command) => {
+                self.increment_pc();
+                command
+            }
+            None => {
+                let command = self.fetch_command()?;
+                let command = self.decode_command(command)?;
+                #[cfg(feature = "alloc")]
+                self.decoded.insert(pc, command);
+                command
+            }
+        };
+        self.execute_command(command)?;
+        self.cycles += 1;
+        Ok(command)
+    }
+
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer.get_value() > 0 {
+            self.delay_timer.decrement();
+        }
+        if self.sound_timer.get_value() > 0 {
+            self.sound_timer.decrement();
+        }
+    }
+
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), MachineErr> {
+        #[cfg(feature = "alloc")]
+        match self.backend() {
+            Backend::Interpreter => {
+                for _ in 0..instructions {
+                    self.step()?;
+                }
+            }
+            Backend::Threaded => self.run_threaded(instructions)?,
+            #[cfg(feature = "jit")]
+            Backend::Jit => self.run_jit(instructions)?,
+        }
+        #[cfg(not(feature = "alloc"))]
+        for _ in 0..instructions {
+            self.step()?;
+        }
+        self.tick_timers();
+        Ok(())
+    }
+}
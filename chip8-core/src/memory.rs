@@ -0,0 +1,94 @@
+#[derive(Debug, Clone, Copy)]
+pub struct Memory<const N: usize> {
+    data: [u8; N],
+}
+
+#[derive(Debug)]
+pub struct MemoryErr;
+
+impl<const N: usize> Memory<N> {
+    pub fn new() -> Self {
+        Self { data: [0; N] }
+    }
+
+    pub fn get_data(&self, offset: u16, size: usize) -> Result<&[u8], MemoryErr> {
+        let offset = offset as usize;
+        if offset + size > N {
+            return Err(MemoryErr);
+        }
+        Ok(&self.data[offset..offset + size])
+    }
+
+    pub fn get_mut_data(&mut self, offset: u16, size: usize) -> Result<&mut [u8], MemoryErr> {
+        let offset = offset as usize;
+        if offset + size > N {
+            return Err(MemoryErr);
+        }
+        Ok(&mut self.data[offset..offset + size])
+    }
+
+    pub fn get_command_data(&self, pc: u16) -> Result<[u8; 2], MemoryErr> {
+        let command_data = self.get_data(pc, 2)?;
+        Ok([command_data[0], command_data[1]])
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+/// The addresses of `len` bytes starting at `index` in a memory of
+/// `memory_len` bytes, wrapping around to 0x000 past the end.
+pub(crate) fn index_addrs(
+    memory_len: usize,
+    index: u16,
+    len: usize,
+) -> impl Iterator<Item = usize> {
+    (0..len).map(move |offset| (index as usize + offset) % memory_len)
+}
+
+// Index-relative accesses past the end of memory wrap around unless `strict`
+// is set, in which case they fault without touching memory.
+fn check_index_range(
+    memory_len: usize,
+    index: u16,
+    len: usize,
+    strict: bool,
+) -> Result<(), MemoryErr> {
+    if strict && index as usize + len > memory_len {
+        return Err(MemoryErr);
+    }
+    Ok(())
+}
+
+pub(crate) fn read_index(
+    memory: &[u8],
+    index: u16,
+    strict: bool,
+    buf: &mut [u8],
+) -> Result<(), MemoryErr> {
+    check_index_range(memory.len(), index, buf.len(), strict)?;
+    let addrs = index_addrs(memory.len(), index, buf.len());
+    for (byte, addr) in buf.iter_mut().zip(addrs) {
+        *byte = memory[addr];
+    }
+    Ok(())
+}
+
+pub(crate) fn write_index(
+    memory: &mut [u8],
+    index: u16,
+    strict: bool,
+    data: &[u8],
+) -> Result<(), MemoryErr> {
+    check_index_range(memory.len(), index, data.len(), strict)?;
+    let addrs = index_addrs(memory.len(), index, data.len());
+    for (byte, addr) in data.iter().zip(addrs) {
+        memory[addr] = *byte;
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! Platform behaviour differences.
+//!
+//! CHIP-8 interpreters disagree on a handful of instructions. `Quirks` holds
+//! one switch per disagreement, and `Platform` names the combinations used by
+//! the common interpreters.
+
+use core::{fmt::Display, str::FromStr};
+
+use enum_iterator::Sequence;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8XY1`/`8XY2`/`8XY3` clear VF.
+    pub vf_reset: bool,
+    /// `FX55`/`FX65` leave I pointing past the last register.
+    pub memory_increment: bool,
+    /// `8XY6`/`8XYE` shift VY into VX instead of shifting VX in place.
+    pub shift_uses_vy: bool,
+    /// Sprites are cut off at the screen edge instead of wrapping around.
+    pub clip_sprites: bool,
+    /// `BNNN` jumps to NNN plus VX, X being the top nibble of NNN (`BXNN`),
+    /// instead of NNN plus V0.
+    pub jump_uses_vx: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence)]
+pub enum Platform {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+#[derive(Debug)]
+pub struct PlatformErr;
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Platform::Chip8.quirks()
+    }
+}
+
+impl Platform {
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks {
+                vf_reset: true,
+                memory_increment: true,
+                shift_uses_vy: true,
+                clip_sprites: true,
+                jump_uses_vx: false,
+            },
+            Platform::SuperChip => Quirks {
+                vf_reset: false,
+                memory_increment: false,
+                shift_uses_vy: false,
+                clip_sprites: true,
+                jump_uses_vx: true,
+            },
+            Platform::XoChip => Quirks {
+                vf_reset: false,
+                memory_increment: true,
+                shift_uses_vy: true,
+                clip_sprites: false,
+                jump_uses_vx: false,
+            },
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Chip8 => "chip8",
+            Platform::SuperChip => "schip",
+            Platform::XoChip => "xochip",
+        }
+    }
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = PlatformErr;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        enum_iterator::all::<Platform>()
+            .find(|platform| platform.name() == name)
+            .ok_or(PlatformErr)
+    }
+}
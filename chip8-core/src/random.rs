@@ -0,0 +1,158 @@
+//! Random number sources for `CXNN`.
+//!
+//! A source's position is saved with the machine, so a restored state draws
+//! the same numbers the original run did.
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt::Debug;
+
+/// The most bytes a source may write in [`RandomSource::save`].
+pub const RANDOM_STATE_LEN: usize = 16;
+
+pub trait RandomSource: Debug + Send {
+    fn next_byte(&mut self) -> u8;
+
+    /// Writes the source's position into `out` and returns how many bytes it
+    /// used.
+    fn save(&self, out: &mut [u8; RANDOM_STATE_LEN]) -> usize;
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), RandomErr>;
+}
+
+#[derive(Debug)]
+pub struct RandomErr;
+
+/// A [`RandomSource`] that can be cloned behind a `Box`, which is what lets
+/// `Box<dyn CloneRandomSource>` serve as a machine's source. Every `Clone`
+/// source implements it.
+#[cfg(feature = "alloc")]
+pub trait CloneRandomSource: RandomSource {
+    fn box_clone(&self) -> Box<dyn CloneRandomSource>;
+}
+
+#[cfg(feature = "alloc")]
+impl<T: RandomSource + Clone + 'static> CloneRandomSource for T {
+    fn box_clone(&self) -> Box<dyn CloneRandomSource> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Clone for Box<dyn CloneRandomSource> {
+    fn clone(&self) -> Self {
+        (**self).box_clone()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Default for Box<dyn CloneRandomSource> {
+    fn default() -> Self {
+        Box::new(SeededRandom::default())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl RandomSource for Box<dyn CloneRandomSource> {
+    fn next_byte(&mut self) -> u8 {
+        (**self).next_byte()
+    }
+
+    fn save(&self, out: &mut [u8; RANDOM_STATE_LEN]) -> usize {
+        (**self).save(out)
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), RandomErr> {
+        (**self).restore(state)
+    }
+}
+
+pub const DEFAULT_SEED: u64 = 0x5EED_C8C8_5EED_C8C8;
+
+/// xorshift64* generator.
+#[derive(Debug, Clone)]
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl Default for SeededRandom {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        // A zero state would only ever produce zeros.
+        Self {
+            state: if seed == 0 { DEFAULT_SEED } else { seed },
+        }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_byte(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    fn save(&self, out: &mut [u8; RANDOM_STATE_LEN]) -> usize {
+        out[..8].copy_from_slice(&self.state.to_be_bytes());
+        8
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), RandomErr> {
+        let state: [u8; 8] = state.try_into().map_err(|_| RandomErr)?;
+        match u64::from_be_bytes(state) {
+            0 => Err(RandomErr),
+            state => {
+                self.state = state;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Replays a fixed byte sequence, starting over when it runs out.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct ScriptedRandom {
+    bytes: Vec<u8>,
+    pos: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl ScriptedRandom {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes, pos: 0 }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl RandomSource for ScriptedRandom {
+    fn next_byte(&mut self) -> u8 {
+        if self.bytes.is_empty() {
+            return 0;
+        }
+        let byte = self.bytes[self.pos];
+        self.pos = (self.pos + 1) % self.bytes.len();
+        byte
+    }
+
+    fn save(&self, out: &mut [u8; RANDOM_STATE_LEN]) -> usize {
+        out[..4].copy_from_slice(&(self.pos as u32).to_be_bytes());
+        4
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), RandomErr> {
+        let state: [u8; 4] = state.try_into().map_err(|_| RandomErr)?;
+        let pos = u32::from_be_bytes(state) as usize;
+        if pos >= self.bytes.len().max(1) {
+            return Err(RandomErr);
+        }
+        self.pos = pos;
+        Ok(())
+    }
+}
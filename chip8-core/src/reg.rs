@@ -0,0 +1,182 @@
+use core::fmt::Display;
+
+use enum_iterator::Sequence;
+
+#[derive(Debug, Clone, Copy, PartialEq, Sequence)]
+#[repr(u16)]
+pub enum Reg {
+    V0,
+    V1,
+    V2,
+    V3,
+    V4,
+    V5,
+    V6,
+    V7,
+    V8,
+    V9,
+    VA,
+    VB,
+    VC,
+    VD,
+    VE,
+    VF,
+}
+
+impl From<u8> for Reg {
+    fn from(reg: u8) -> Self {
+        let reg = reg & 0x0F;
+        match reg {
+            0x0 => Self::V0,
+            0x1 => Self::V1,
+            0x2 => Self::V2,
+            0x3 => Self::V3,
+            0x4 => Self::V4,
+            0x5 => Self::V5,
+            0x6 => Self::V6,
+            0x7 => Self::V7,
+            0x8 => Self::V8,
+            0x9 => Self::V9,
+            0xA => Self::VA,
+            0xB => Self::VB,
+            0xC => Self::VC,
+            0xD => Self::VD,
+            0xE => Self::VE,
+            0xF => Self::VF,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Display for Reg {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "V{:X}", *self as u16)
+    }
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy)]
+pub struct RegBank {
+    V0: u8,
+    V1: u8,
+    V2: u8,
+    V3: u8,
+    V4: u8,
+    V5: u8,
+    V6: u8,
+    V7: u8,
+    V8: u8,
+    V9: u8,
+    VA: u8,
+    VB: u8,
+    VC: u8,
+    VD: u8,
+    VE: u8,
+    VF: u8,
+}
+
+impl RegBank {
+    pub fn new() -> Self {
+        Self {
+            V0: 0,
+            V1: 0,
+            V2: 0,
+            V3: 0,
+            V4: 0,
+            V5: 0,
+            V6: 0,
+            V7: 0,
+            V8: 0,
+            V9: 0,
+            VA: 0,
+            VB: 0,
+            VC: 0,
+            VD: 0,
+            VE: 0,
+            VF: 0,
+        }
+    }
+
+    fn get_reg_ref(&self, reg: Reg) -> &u8 {
+        match reg {
+            Reg::V0 => &self.V0,
+            Reg::V1 => &self.V1,
+            Reg::V2 => &self.V2,
+            Reg::V3 => &self.V3,
+            Reg::V4 => &self.V4,
+            Reg::V5 => &self.V5,
+            Reg::V6 => &self.V6,
+            Reg::V7 => &self.V7,
+            Reg::V8 => &self.V8,
+            Reg::V9 => &self.V9,
+            Reg::VA => &self.VA,
+            Reg::VB => &self.VB,
+            Reg::VC => &self.VC,
+            Reg::VD => &self.VD,
+            Reg::VE => &self.VE,
+            Reg::VF => &self.VF,
+        }
+    }
+
+    fn get_reg_ref_mut(&mut self, reg: Reg) -> &mut u8 {
+        match reg {
+            Reg::V0 => &mut self.V0,
+            Reg::V1 => &mut self.V1,
+            Reg::V2 => &mut self.V2,
+            Reg::V3 => &mut self.V3,
+            Reg::V4 => &mut self.V4,
+            Reg::V5 => &mut self.V5,
+            Reg::V6 => &mut self.V6,
+            Reg::V7 => &mut self.V7,
+            Reg::V8 => &mut self.V8,
+            Reg::V9 => &mut self.V9,
+            Reg::VA => &mut self.VA,
+            Reg::VB => &mut self.VB,
+            Reg::VC => &mut self.VC,
+            Reg::VD => &mut self.VD,
+            Reg::VE => &mut self.VE,
+            Reg::VF => &mut self.VF,
+        }
+    }
+
+    /// V0 to VF in order.
+    #[cfg(feature = "alloc")]
+    pub fn to_array(self) -> [u8; 16] {
+        [
+            self.V0, self.V1, self.V2, self.V3, self.V4, self.V5, self.V6, self.V7, self.V8,
+            self.V9, self.VA, self.VB, self.VC, self.VD, self.VE, self.VF,
+        ]
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn from_array(regs: [u8; 16]) -> Self {
+        Self {
+            V0: regs[0],
+            V1: regs[1],
+            V2: regs[2],
+            V3: regs[3],
+            V4: regs[4],
+            V5: regs[5],
+            V6: regs[6],
+            V7: regs[7],
+            V8: regs[8],
+            V9: regs[9],
+            VA: regs[10],
+            VB: regs[11],
+            VC: regs[12],
+            VD: regs[13],
+            VE: regs[14],
+            VF: regs[15],
+        }
+    }
+
+    pub fn get_value(&self, reg: Reg) -> u8 {
+        let bank_reg = self.get_reg_ref(reg);
+        *bank_reg
+    }
+
+    pub fn set_value(&mut self, reg: Reg, val: u8) {
+        let bank_reg = self.get_reg_ref_mut(reg);
+        *bank_reg = val;
+    }
+}
@@ -0,0 +1,50 @@
+use core::fmt::{self, Debug};
+
+pub const STACK_SIZE: usize = 16;
+
+#[derive(Clone)]
+pub struct Stack {
+    data: [u16; STACK_SIZE],
+    len: usize,
+}
+
+impl Debug for Stack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stack")
+            .field("data", &self.as_slice())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct StackErr;
+
+impl Stack {
+    pub fn new() -> Self {
+        Self {
+            data: [0; STACK_SIZE],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, addr: u16) -> Result<(), StackErr> {
+        if self.len == STACK_SIZE {
+            return Err(StackErr);
+        }
+        self.data[self.len] = addr;
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Result<u16, StackErr> {
+        if self.len == 0 {
+            return Err(StackErr);
+        }
+        self.len -= 1;
+        Ok(self.data[self.len])
+    }
+
+    pub fn as_slice(&self) -> &[u16] {
+        &self.data[..self.len]
+    }
+}
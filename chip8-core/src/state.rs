@@ -0,0 +1,246 @@
+//! Save state serialization.
+//!
+//! A save state is a `C8SS` magic, a big-endian `u16` format version, a
+//! big-endian `u32` payload length, the payload, and a CRC-32 of the payload.
+//! The payload ends with a byte of quirk flags, a strict-memory byte and the
+//! random source state, so a loaded state runs exactly as the saved machine
+//! would have.
+
+use alloc::vec::Vec;
+
+use enum_iterator::all;
+
+use super::key::Key;
+use super::reg::Reg;
+use super::RANDOM_STATE_LEN;
+use super::{Machine, Quirks, RandomSource, DISPLAY_HEIGHT, DISPLAY_WIDTH, MEMORY_SIZE};
+
+const MAGIC: &[u8; 4] = b"C8SS";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 10;
+const CHECKSUM_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StateErr {
+    BadMagic,
+    UnsupportedVersion(u16),
+    BadChecksum,
+    Truncated,
+    Corrupt,
+}
+
+struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateErr> {
+        if self.data.len() < len {
+            return Err(StateErr::Truncated);
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, StateErr> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, StateErr> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, StateErr> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_be_bytes(buf))
+    }
+
+    fn u64(&mut self) -> Result<u64, StateErr> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_be_bytes(buf))
+    }
+}
+
+pub(super) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+const VF_RESET: u8 = 1 << 0;
+const MEMORY_INCREMENT: u8 = 1 << 1;
+const SHIFT_USES_VY: u8 = 1 << 2;
+const CLIP_SPRITES: u8 = 1 << 3;
+const JUMP_USES_VX: u8 = 1 << 4;
+const QUIRK_FLAGS: u8 = VF_RESET | MEMORY_INCREMENT | SHIFT_USES_VY | CLIP_SPRITES | JUMP_USES_VX;
+
+fn quirk_flags(quirks: Quirks) -> u8 {
+    let mut flags = 0;
+    for (set, flag) in [
+        (quirks.vf_reset, VF_RESET),
+        (quirks.memory_increment, MEMORY_INCREMENT),
+        (quirks.shift_uses_vy, SHIFT_USES_VY),
+        (quirks.clip_sprites, CLIP_SPRITES),
+        (quirks.jump_uses_vx, JUMP_USES_VX),
+    ] {
+        if set {
+            flags |= flag;
+        }
+    }
+    flags
+}
+
+fn quirks_from_flags(flags: u8) -> Result<Quirks, StateErr> {
+    if flags & !QUIRK_FLAGS != 0 {
+        return Err(StateErr::Corrupt);
+    }
+    Ok(Quirks {
+        vf_reset: flags & VF_RESET != 0,
+        memory_increment: flags & MEMORY_INCREMENT != 0,
+        shift_uses_vy: flags & SHIFT_USES_VY != 0,
+        clip_sprites: flags & CLIP_SPRITES != 0,
+        jump_uses_vx: flags & JUMP_USES_VX != 0,
+    })
+}
+
+impl<R: RandomSource> Machine<R> {
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(MEMORY_SIZE + 512);
+        payload.extend_from_slice(&self.pc.to_be_bytes());
+        payload.extend_from_slice(&self.index.to_be_bytes());
+        payload.extend_from_slice(&self.cycles.to_be_bytes());
+        for reg in all::<Reg>() {
+            payload.push(self.reg.get_value(reg));
+        }
+        payload.push(self.delay_timer.get_value());
+        payload.push(self.sound_timer.get_value());
+
+        let mut keys: u16 = 0;
+        for key in all::<Key>() {
+            if self.key.get_value(key) {
+                keys |= 1 << u8::from(key);
+            }
+        }
+        payload.extend_from_slice(&keys.to_be_bytes());
+
+        let stack = self.stack.as_slice();
+        payload.extend_from_slice(&(stack.len() as u16).to_be_bytes());
+        for addr in stack {
+            payload.extend_from_slice(&addr.to_be_bytes());
+        }
+
+        payload.extend_from_slice(self.memory.as_slice());
+
+        for y in 0..DISPLAY_HEIGHT {
+            for x in (0..DISPLAY_WIDTH).step_by(8) {
+                let mut byte = 0;
+                for bit in 0..8 {
+                    if self.display.pixel(x + bit, y).unwrap() {
+                        byte |= 0x80 >> bit;
+                    }
+                }
+                payload.push(byte);
+            }
+        }
+
+        payload.push(quirk_flags(self.quirks));
+        payload.push(self.strict_memory as u8);
+
+        let mut random = [0; RANDOM_STATE_LEN];
+        let len = self.random.save(&mut random);
+        payload.extend_from_slice(&(len as u16).to_be_bytes());
+        payload.extend_from_slice(&random[..len]);
+
+        let mut state = Vec::with_capacity(HEADER_LEN + payload.len() + CHECKSUM_LEN);
+        state.extend_from_slice(MAGIC);
+        state.extend_from_slice(&VERSION.to_be_bytes());
+        state.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        state.extend_from_slice(&payload);
+        state.extend_from_slice(&crc32(&payload).to_be_bytes());
+        state
+    }
+}
+
+impl<R: RandomSource + Clone> Machine<R> {
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateErr> {
+        let mut reader = StateReader { data: state };
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(StateErr::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(StateErr::UnsupportedVersion(version));
+        }
+        let len = reader.u32()? as usize;
+        let payload = reader.bytes(len)?;
+        if reader.u32()? != crc32(payload) {
+            return Err(StateErr::BadChecksum);
+        }
+
+        let mut reader = StateReader { data: payload };
+        let mut mach = Self::with_random(self.quirks, self.random.clone());
+        mach.set_decode_cache(self.decode_cache());
+        mach.set_backend(self.backend());
+        mach.pc = reader.u16()?;
+        mach.index = reader.u16()?;
+        mach.cycles = reader.u64()?;
+        for reg in all::<Reg>() {
+            mach.reg.set_value(reg, reader.u8()?);
+        }
+        mach.delay_timer.set_value(reader.u8()?);
+        mach.sound_timer.set_value(reader.u8()?);
+
+        let keys = reader.u16()?;
+        for key in all::<Key>() {
+            mach.key.set_value(key, keys & (1 << u8::from(key)) != 0);
+        }
+
+        let stack_len = reader.u16()?;
+        for _ in 0..stack_len {
+            mach.stack
+                .push(reader.u16()?)
+                .map_err(|_| StateErr::Corrupt)?;
+        }
+
+        mach.memory
+            .as_mut_slice()
+            .copy_from_slice(reader.bytes(MEMORY_SIZE)?);
+
+        for y in 0..DISPLAY_HEIGHT {
+            for x in (0..DISPLAY_WIDTH).step_by(8) {
+                let byte = reader.u8()?;
+                for bit in 0..8 {
+                    let _ = mach.display.set_pixel(x + bit, y, byte & (0x80 >> bit) != 0);
+                }
+            }
+        }
+
+        mach.quirks = quirks_from_flags(reader.u8()?)?;
+        mach.strict_memory = match reader.u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(StateErr::Corrupt),
+        };
+
+        let len = reader.u16()? as usize;
+        mach.random
+            .restore(reader.bytes(len)?)
+            .map_err(|_| StateErr::Corrupt)?;
+
+        if !reader.data.is_empty() {
+            return Err(StateErr::Corrupt);
+        }
+        *self = mach;
+        Ok(())
+    }
+}
@@ -0,0 +1,1221 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use super::key::Key;
+use super::reg::Reg;
+use super::*;
+
+// The machine the frontend uses.
+type Machine = super::Machine<Box<dyn CloneRandomSource>>;
+
+fn machine_with(regs: &[(Reg, u8)]) -> Machine {
+    let mut mach = Machine::new();
+    for (reg, val) in regs {
+        mach.reg.set_value(*reg, *val);
+    }
+    mach
+}
+
+fn pixel(mach: &Machine, x: usize, y: usize) -> bool {
+    mach.display.pixel(x, y).unwrap()
+}
+
+#[test]
+fn clear_screen_clears_every_pixel() {
+    let mut mach = Machine::new();
+    mach.display.set_pixel(0, 0, true).unwrap();
+    mach.display.set_pixel(63, 31, true).unwrap();
+    mach.execute_command(Command::ClearScreen).unwrap();
+    assert!(!pixel(&mach, 0, 0));
+    assert!(!pixel(&mach, 63, 31));
+    assert_eq!(mach.pc, LOAD_OFFSET);
+}
+
+#[test]
+fn jump_sets_pc() {
+    let mut mach = Machine::new();
+    mach.execute_command(Command::Jump(0x345)).unwrap();
+    assert_eq!(mach.pc, 0x345);
+    assert!(mach.stack.as_slice().is_empty());
+}
+
+#[test]
+fn jump_with_offset_adds_the_register_and_wraps() {
+    let mut mach = machine_with(&[(Reg::V3, 0x10)]);
+    mach.execute_command(Command::JumpWithOffset(0x345, Reg::V3))
+        .unwrap();
+    assert_eq!(mach.pc, 0x355);
+    mach.execute_command(Command::JumpWithOffset(0xFF8, Reg::V3))
+        .unwrap();
+    assert_eq!(mach.pc, 0x008);
+}
+
+#[test]
+fn call_pushes_return_address() {
+    let mut mach = Machine::new();
+    mach.pc = 0x202;
+    mach.execute_command(Command::Call(0x300)).unwrap();
+    assert_eq!(mach.pc, 0x300);
+    assert_eq!(mach.stack.as_slice(), &[0x202]);
+}
+
+#[test]
+fn return_pops_return_address() {
+    let mut mach = Machine::new();
+    mach.pc = 0x302;
+    mach.stack.push(0x202).unwrap();
+    mach.execute_command(Command::Return).unwrap();
+    assert_eq!(mach.pc, 0x202);
+    assert!(mach.stack.as_slice().is_empty());
+}
+
+#[test]
+fn skip_if_reg_val() {
+    let mut mach = machine_with(&[(Reg::V3, 0x42)]);
+    mach.execute_command(Command::SkipIfRegVal(Reg::V3, 0x42))
+        .unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET + 2);
+    mach.execute_command(Command::SkipIfRegVal(Reg::V3, 0x43))
+        .unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET + 2);
+}
+
+#[test]
+fn skip_if_reg_val_not() {
+    let mut mach = machine_with(&[(Reg::V3, 0x42)]);
+    mach.execute_command(Command::SkipIfRegValNot(Reg::V3, 0x42))
+        .unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET);
+    mach.execute_command(Command::SkipIfRegValNot(Reg::V3, 0x43))
+        .unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET + 2);
+}
+
+#[test]
+fn skip_if_reg_equal() {
+    let mut mach = machine_with(&[(Reg::V1, 7), (Reg::V2, 7), (Reg::V3, 8)]);
+    mach.execute_command(Command::SkipIfRegEqual(Reg::V1, Reg::V2))
+        .unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET + 2);
+    mach.execute_command(Command::SkipIfRegEqual(Reg::V1, Reg::V3))
+        .unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET + 2);
+}
+
+#[test]
+fn skip_if_reg_not_equal() {
+    let mut mach = machine_with(&[(Reg::V1, 7), (Reg::V2, 7), (Reg::V3, 8)]);
+    mach.execute_command(Command::SkipIfRegNotEqual(Reg::V1, Reg::V2))
+        .unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET);
+    mach.execute_command(Command::SkipIfRegNotEqual(Reg::V1, Reg::V3))
+        .unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET + 2);
+}
+
+#[test]
+fn set_val() {
+    let mut mach = Machine::new();
+    mach.execute_command(Command::SetVal(Reg::VA, 0xBC))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::VA), 0xBC);
+}
+
+#[test]
+fn add_val_wraps_without_touching_vf() {
+    let mut mach = machine_with(&[(Reg::V0, 0xFF), (Reg::VF, 0x55)]);
+    mach.execute_command(Command::AddVal(Reg::V0, 0x02))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x01);
+    assert_eq!(mach.reg.get_value(Reg::VF), 0x55);
+}
+
+#[test]
+fn set_reg() {
+    let mut mach = machine_with(&[(Reg::V1, 0x12)]);
+    mach.execute_command(Command::SetReg(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x12);
+    assert_eq!(mach.reg.get_value(Reg::V1), 0x12);
+}
+
+#[test]
+fn binary_logic() {
+    let mut mach = machine_with(&[(Reg::V0, 0b1100), (Reg::V1, 0b1010)]);
+    mach.execute_command(Command::BinOR(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0b1110);
+
+    mach.reg.set_value(Reg::V0, 0b1100);
+    mach.execute_command(Command::BinAND(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0b1000);
+
+    mach.reg.set_value(Reg::V0, 0b1100);
+    mach.execute_command(Command::LogXOR(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0b0110);
+    assert_eq!(mach.reg.get_value(Reg::V1), 0b1010);
+}
+
+#[test]
+fn add_reg_sets_carry() {
+    let mut mach = machine_with(&[(Reg::V0, 0xF0), (Reg::V1, 0x20)]);
+    mach.execute_command(Command::AddReg(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x10);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+}
+
+#[test]
+fn add_reg_clears_carry() {
+    let mut mach = machine_with(&[(Reg::V0, 0x10), (Reg::V1, 0x20), (Reg::VF, 1)]);
+    mach.execute_command(Command::AddReg(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x30);
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+}
+
+#[test]
+fn sub_reg_sets_not_borrow() {
+    let mut mach = machine_with(&[(Reg::V0, 0x30), (Reg::V1, 0x10)]);
+    mach.execute_command(Command::SubReg(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x20);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+
+    mach.execute_command(Command::SubReg(Reg::V1, Reg::V0))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V1), 0xF0);
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+}
+
+#[test]
+fn sub_reg_equal_operands_do_not_borrow() {
+    let mut mach = machine_with(&[(Reg::V0, 0x30), (Reg::V1, 0x30)]);
+    mach.execute_command(Command::SubReg(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+}
+
+#[test]
+fn sub_reg_rev_sets_not_borrow() {
+    let mut mach = machine_with(&[(Reg::V0, 0x10), (Reg::V1, 0x30)]);
+    mach.execute_command(Command::SubRegRev(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x20);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+
+    mach.reg.set_value(Reg::V0, 0x40);
+    mach.execute_command(Command::SubRegRev(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0xF0);
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+}
+
+#[test]
+fn shift_right_moves_out_low_bit() {
+    let mut mach = machine_with(&[(Reg::V1, 0b0000_0101)]);
+    mach.execute_command(Command::ShiftRight(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0b0000_0010);
+    assert_eq!(mach.reg.get_value(Reg::V1), 0b0000_0101);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+
+    mach.reg.set_value(Reg::V1, 0b0000_0100);
+    mach.execute_command(Command::ShiftRight(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0b0000_0010);
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+}
+
+#[test]
+fn shift_left_moves_out_high_bit() {
+    let mut mach = machine_with(&[(Reg::V1, 0b1000_0001)]);
+    mach.execute_command(Command::ShiftLeft(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0b0000_0010);
+    assert_eq!(mach.reg.get_value(Reg::V1), 0b1000_0001);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+
+    mach.reg.set_value(Reg::V1, 0b0100_0000);
+    mach.execute_command(Command::ShiftLeft(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0b1000_0000);
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+}
+
+#[test]
+fn set_index() {
+    let mut mach = Machine::new();
+    mach.execute_command(Command::SetIndex(0x2F0)).unwrap();
+    assert_eq!(mach.index, 0x2F0);
+}
+
+#[test]
+fn display_draws_sprite_without_collision() {
+    let mut mach = machine_with(&[(Reg::V0, 2), (Reg::V1, 3), (Reg::VF, 1)]);
+    mach.memory
+        .get_mut_data(0x300, 2)
+        .unwrap()
+        .copy_from_slice(&[0b1000_0001, 0b0100_0000]);
+    mach.index = 0x300;
+    mach.execute_command(Command::Display(Reg::V0, Reg::V1, 2))
+        .unwrap();
+    assert!(pixel(&mach, 2, 3));
+    assert!(!pixel(&mach, 3, 3));
+    assert!(pixel(&mach, 9, 3));
+    assert!(pixel(&mach, 3, 4));
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+    assert_eq!(mach.index, 0x300);
+}
+
+#[test]
+fn display_reports_collision() {
+    let mut mach = machine_with(&[(Reg::V0, 0), (Reg::V1, 0)]);
+    mach.memory
+        .get_mut_data(0x300, 1)
+        .unwrap()
+        .copy_from_slice(&[0b1100_0000]);
+    mach.index = 0x300;
+    mach.display.set_pixel(1, 0, true).unwrap();
+    mach.execute_command(Command::Display(Reg::V0, Reg::V1, 1))
+        .unwrap();
+    assert!(pixel(&mach, 0, 0));
+    assert!(!pixel(&mach, 1, 0));
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+}
+
+#[test]
+fn display_wraps_start_coordinates() {
+    let mut mach = machine_with(&[(Reg::V0, 64 + 5), (Reg::V1, 32 + 6)]);
+    mach.memory
+        .get_mut_data(0x300, 1)
+        .unwrap()
+        .copy_from_slice(&[0b1000_0000]);
+    mach.index = 0x300;
+    mach.execute_command(Command::Display(Reg::V0, Reg::V1, 1))
+        .unwrap();
+    assert!(pixel(&mach, 5, 6));
+}
+
+#[test]
+fn skip_if_key() {
+    let mut mach = machine_with(&[(Reg::V2, 0xA)]);
+    mach.execute_command(Command::SkipIfKey(Reg::V2)).unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET);
+    mach.key.set_value(Key::KeyA, true);
+    mach.execute_command(Command::SkipIfKey(Reg::V2)).unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET + 2);
+}
+
+#[test]
+fn skip_if_not_key() {
+    let mut mach = machine_with(&[(Reg::V2, 0xA)]);
+    mach.execute_command(Command::SkipIfNotKey(Reg::V2))
+        .unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET + 2);
+    mach.key.set_value(Key::KeyA, true);
+    mach.execute_command(Command::SkipIfNotKey(Reg::V2))
+        .unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET + 2);
+}
+
+#[test]
+fn timers_move_to_and_from_registers() {
+    let mut mach = machine_with(&[(Reg::V4, 0x3C), (Reg::V5, 0x10)]);
+    mach.execute_command(Command::SetDelayTimerFromReg(Reg::V4))
+        .unwrap();
+    assert_eq!(mach.delay_timer.get_value(), 0x3C);
+    mach.execute_command(Command::SetSoundTimerFromReg(Reg::V5))
+        .unwrap();
+    assert_eq!(mach.sound_timer.get_value(), 0x10);
+    mach.execute_command(Command::SetRegFromDelayTimer(Reg::V6))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V6), 0x3C);
+}
+
+#[test]
+fn add_index() {
+    let mut mach = machine_with(&[(Reg::V0, 0x10)]);
+    mach.index = 0x200;
+    mach.execute_command(Command::AddIndex(Reg::V0)).unwrap();
+    assert_eq!(mach.index, 0x210);
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+}
+
+#[test]
+fn add_index_past_address_space_sets_vf() {
+    let mut mach = machine_with(&[(Reg::V0, 0x02)]);
+    mach.index = 0xFFF;
+    mach.execute_command(Command::AddIndex(Reg::V0)).unwrap();
+    assert_eq!(mach.index, 0x001);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+}
+
+// Sets VF, then loops `I += V0` with V0 = 1, which never leaves the address
+// space within a few hundred instructions.
+const ADD_INDEX_LOOP: [u8; 10] = [0x6F, 0x01, 0xA1, 0x00, 0x60, 0x01, 0xF0, 0x1E, 0x12, 0x06];
+
+#[test]
+fn add_index_keeps_a_stale_vf() {
+    for backend in [Backend::Interpreter, Backend::Threaded] {
+        let mut mach = Machine::new();
+        mach.set_backend(backend);
+        mach.load(&ADD_INDEX_LOOP).unwrap();
+        mach.run_frame(101).unwrap();
+        assert_eq!(mach.index, 0x131, "{:?}", backend);
+        assert_eq!(mach.reg.get_value(Reg::VF), 1, "{:?}", backend);
+    }
+
+    let mut batch = MachineBatch::new(2, Quirks::default(), |lane| {
+        SeededRandom::new(lane as u64 + 1)
+    });
+    batch.load(&ADD_INDEX_LOOP).unwrap();
+    let _ = batch.run_frame(&[0; 2], 101).unwrap();
+    for lane in 0..2 {
+        assert_eq!(batch.machine(lane).index(), 0x131);
+        assert_eq!(batch.regs()[lane][0xF], 1);
+    }
+}
+
+#[test]
+fn get_key_waits_for_a_key() {
+    let mut mach = machine_with(&[(Reg::V3, 0x77)]);
+    mach.pc = LOAD_OFFSET + 2;
+    mach.execute_command(Command::GetKey(Reg::V3)).unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET);
+    assert_eq!(mach.reg.get_value(Reg::V3), 0x77);
+}
+
+#[test]
+fn get_key_accepts_key_zero() {
+    let mut mach = Machine::new();
+    mach.pc = LOAD_OFFSET + 2;
+    mach.reg.set_value(Reg::V3, 0x77);
+    mach.key.set_value(Key::Key0, true);
+    mach.execute_command(Command::GetKey(Reg::V3)).unwrap();
+    assert_eq!(mach.pc, LOAD_OFFSET + 2);
+    assert_eq!(mach.reg.get_value(Reg::V3), 0);
+}
+
+#[test]
+fn font_points_index_at_glyph() {
+    let mut mach = machine_with(&[(Reg::V0, 0x1A)]);
+    mach.execute_command(Command::Font(Reg::V0)).unwrap();
+    assert_eq!(mach.index, FONT_OFFSET + 0xA * 5);
+    assert_eq!(
+        mach.memory.get_data(mach.index, 5).unwrap(),
+        &[0xF0, 0x90, 0xF0, 0x90, 0x90]
+    );
+}
+
+#[test]
+fn bcd_writes_three_digits() {
+    for (val, digits) in [
+        (0, [0, 0, 0]),
+        (7, [0, 0, 7]),
+        (42, [0, 4, 2]),
+        (255, [2, 5, 5]),
+    ] {
+        let mut mach = machine_with(&[(Reg::V9, val)]);
+        mach.index = 0x300;
+        mach.memory
+            .get_mut_data(0x300, 4)
+            .unwrap()
+            .copy_from_slice(&[0xEE; 4]);
+        mach.execute_command(Command::BCDConv(Reg::V9)).unwrap();
+        assert_eq!(
+            mach.memory.get_data(0x300, 4).unwrap(),
+            &[digits[0], digits[1], digits[2], 0xEE]
+        );
+        assert_eq!(mach.index, 0x300);
+    }
+}
+
+#[test]
+fn store_writes_registers_up_to_x() {
+    let mut mach = machine_with(&[(Reg::V0, 1), (Reg::V1, 2), (Reg::V2, 3), (Reg::V3, 4)]);
+    mach.index = 0x300;
+    mach.execute_command(Command::Store(Reg::V2)).unwrap();
+    assert_eq!(mach.memory.get_data(0x300, 4).unwrap(), &[1, 2, 3, 0]);
+    assert_eq!(mach.index, 0x300);
+}
+
+#[test]
+fn load_reads_registers_up_to_x() {
+    let mut mach = machine_with(&[(Reg::V3, 0xAA)]);
+    mach.memory
+        .get_mut_data(0x300, 4)
+        .unwrap()
+        .copy_from_slice(&[1, 2, 3, 4]);
+    mach.index = 0x300;
+    mach.execute_command(Command::Load(Reg::V2)).unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 1);
+    assert_eq!(mach.reg.get_value(Reg::V1), 2);
+    assert_eq!(mach.reg.get_value(Reg::V2), 3);
+    assert_eq!(mach.reg.get_value(Reg::V3), 0xAA);
+    assert_eq!(mach.index, 0x300);
+}
+
+#[test]
+fn store_with_index_increment_advances_index() {
+    let mut mach = machine_with(&[(Reg::V0, 1), (Reg::V1, 2)]);
+    mach.index = 0x300;
+    mach.execute_command(Command::StoreWithIndexIncrement(Reg::V1))
+        .unwrap();
+    assert_eq!(mach.memory.get_data(0x300, 3).unwrap(), &[1, 2, 0]);
+    assert_eq!(mach.index, 0x302);
+}
+
+#[test]
+fn load_with_index_increment_advances_index() {
+    let mut mach = Machine::new();
+    mach.memory
+        .get_mut_data(0x300, 2)
+        .unwrap()
+        .copy_from_slice(&[5, 6]);
+    mach.index = 0x300;
+    mach.execute_command(Command::LoadWithIndexIncrement(Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 5);
+    assert_eq!(mach.reg.get_value(Reg::V1), 6);
+    assert_eq!(mach.index, 0x302);
+}
+
+#[test]
+fn vf_reset_quirk() {
+    let mut mach = machine_with(&[(Reg::V0, 1), (Reg::V1, 2), (Reg::VF, 5)]);
+    mach.quirks.vf_reset = true;
+    mach.execute_command(Command::BinOR(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+
+    mach.reg.set_value(Reg::VF, 5);
+    mach.quirks.vf_reset = false;
+    mach.execute_command(Command::BinOR(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::VF), 5);
+}
+
+#[test]
+fn shift_source_quirk() {
+    let mut mach = machine_with(&[(Reg::V0, 0b0000_0011), (Reg::V1, 0b0000_1000)]);
+    mach.quirks.shift_uses_vy = false;
+    mach.execute_command(Command::ShiftRight(Reg::V0, Reg::V1))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0b0000_0001);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+}
+
+#[test]
+fn memory_increment_quirk_selects_decoding() {
+    let mut mach = Machine::with_quirks(Platform::Chip8.quirks());
+    mach.load(&[0xF2, 0x55, 0xF2, 0x65]).unwrap();
+    assert!(matches!(
+        mach.peek_command(0x200),
+        Ok(Command::StoreWithIndexIncrement(Reg::V2))
+    ));
+
+    let mut mach = Machine::with_quirks(Platform::SuperChip.quirks());
+    mach.load(&[0xF2, 0x55, 0xF2, 0x65]).unwrap();
+    assert!(matches!(
+        mach.peek_command(0x200),
+        Ok(Command::Store(Reg::V2))
+    ));
+    assert!(matches!(
+        mach.peek_command(0x202),
+        Ok(Command::Load(Reg::V2))
+    ));
+}
+
+#[test]
+fn jump_uses_vx_quirk_selects_the_offset_register() {
+    for (platform, pc) in [(Platform::Chip8, 0x346), (Platform::SuperChip, 0x348)] {
+        let mut mach = Machine::with_quirks(platform.quirks());
+        mach.load(&[0x60, 0x01, 0x63, 0x03, 0xB3, 0x45]).unwrap();
+        for _ in 0..3 {
+            mach.step().unwrap();
+        }
+        assert_eq!(mach.pc, pc, "{}", platform);
+    }
+}
+
+#[test]
+fn clip_sprites_quirk() {
+    let mut mach = machine_with(&[(Reg::V0, 60), (Reg::V1, 0)]);
+    mach.memory
+        .get_mut_data(0x300, 1)
+        .unwrap()
+        .copy_from_slice(&[0xFF]);
+    mach.index = 0x300;
+    mach.quirks.clip_sprites = true;
+    mach.execute_command(Command::Display(Reg::V0, Reg::V1, 1))
+        .unwrap();
+    assert!(pixel(&mach, 63, 0));
+    assert!(!pixel(&mach, 0, 0));
+
+    mach.display.clear_screen();
+    mach.quirks.clip_sprites = false;
+    mach.execute_command(Command::Display(Reg::V0, Reg::V1, 1))
+        .unwrap();
+    assert!(pixel(&mach, 0, 0));
+    assert!(pixel(&mach, 3, 0));
+    assert!(!pixel(&mach, 4, 0));
+}
+
+#[test]
+fn sprites_wrap_or_clip_at_the_bottom_edge() {
+    let mut mach = machine_with(&[(Reg::V0, 0), (Reg::V1, 30)]);
+    mach.memory
+        .get_mut_data(0x300, 3)
+        .unwrap()
+        .copy_from_slice(&[0x80, 0x40, 0x20]);
+    mach.index = 0x300;
+    mach.quirks.clip_sprites = false;
+    mach.execute_command(Command::Display(Reg::V0, Reg::V1, 3))
+        .unwrap();
+    assert!(pixel(&mach, 0, 30));
+    assert!(pixel(&mach, 1, 31));
+    assert!(pixel(&mach, 2, 0));
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+
+    mach.display.clear_screen();
+    mach.quirks.clip_sprites = true;
+    mach.execute_command(Command::Display(Reg::V0, Reg::V1, 3))
+        .unwrap();
+    assert!(pixel(&mach, 1, 31));
+    assert!(!pixel(&mach, 2, 0));
+}
+
+// Sets both timers, draws a glyph, then loops inside a subroutine.
+const STATEFUL: [u8; 18] = [
+    0x60, 0x05, 0xF0, 0x15, 0xF0, 0x18, 0xA0, 0x50, 0xD0, 0x05, 0x22, 0x0E, 0x12, 0x0C, 0x70, 0x01,
+    0x12, 0x0E,
+];
+
+fn stateful_machine() -> Machine {
+    let mut mach = Machine::with_quirks(Platform::SuperChip.quirks());
+    mach.set_strict_memory(true);
+    mach.load(&STATEFUL).unwrap();
+    for _ in 0..9 {
+        mach.step().unwrap();
+    }
+    mach.key.set_value(Key::KeyA, true);
+    mach
+}
+
+fn pixels(mach: &Machine) -> Vec<bool> {
+    (0..DISPLAY_HEIGHT)
+        .flat_map(|y| (0..DISPLAY_WIDTH).map(move |x| (x, y)))
+        .map(|(x, y)| mach.display.pixel(x, y).unwrap())
+        .collect()
+}
+
+// A state around `payload`, with a matching CRC.
+fn seal(payload: &[u8]) -> Vec<u8> {
+    let mut state = b"C8SS".to_vec();
+    state.extend_from_slice(&1u16.to_be_bytes());
+    state.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    state.extend_from_slice(payload);
+    state.extend_from_slice(&state::crc32(payload).to_be_bytes());
+    state
+}
+
+fn payload(state: &[u8]) -> &[u8] {
+    &state[10..state.len() - 4]
+}
+
+#[test]
+fn save_state_round_trips() {
+    let mut mach = stateful_machine();
+    let state = mach.save_state();
+    let mut restored = Machine::new();
+    restored.load_state(&state).unwrap();
+
+    assert_eq!(restored.save_state(), state);
+    assert_eq!(restored.quirks(), Platform::SuperChip.quirks());
+    assert!(restored.strict_memory());
+    assert_eq!(restored.pc(), mach.pc());
+    assert_eq!(restored.stack(), &[0x20C]);
+    assert_eq!(restored.delay_timer(), 5);
+    assert!(restored.key.get_value(Key::KeyA));
+    assert_eq!(pixels(&restored), pixels(&mach));
+    assert_eq!(restored.memory(), mach.memory());
+    for _ in 0..10 {
+        mach.step().unwrap();
+        restored.step().unwrap();
+    }
+    assert_eq!(restored.save_state(), mach.save_state());
+}
+
+// The end of a payload from the default seeded source: a length and its
+// 8-byte state.
+const SEEDED_STATE_LEN: usize = 2 + 8;
+
+#[test]
+fn every_quirk_survives_a_save_state() {
+    for quirk in 0..5 {
+        let quirks = Quirks {
+            vf_reset: quirk == 0,
+            memory_increment: quirk == 1,
+            shift_uses_vy: quirk == 2,
+            clip_sprites: quirk == 3,
+            jump_uses_vx: quirk == 4,
+        };
+        let state = Machine::with_quirks(quirks).save_state();
+        let mut restored = Machine::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.quirks(), quirks);
+    }
+}
+
+#[test]
+fn load_state_rejects_bad_input_and_keeps_the_machine() {
+    let state = stateful_machine().save_state();
+    let mut mach = Machine::new();
+    mach.load(&STATEFUL).unwrap();
+    let before = mach.save_state();
+
+    let mut bad_magic = state.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(mach.load_state(&bad_magic), Err(StateErr::BadMagic));
+
+    for version in [0, 2, u16::MAX] {
+        let mut state = state.clone();
+        state[4..6].copy_from_slice(&version.to_be_bytes());
+        assert_eq!(
+            mach.load_state(&state),
+            Err(StateErr::UnsupportedVersion(version))
+        );
+    }
+
+    let mut bad_crc = state.clone();
+    bad_crc[20] ^= 0x01;
+    assert_eq!(mach.load_state(&bad_crc), Err(StateErr::BadChecksum));
+
+    for len in [0, 3, 5, 9, 100, state.len() - 1] {
+        assert_eq!(mach.load_state(&state[..len]), Err(StateErr::Truncated));
+    }
+    let payload = payload(&state);
+    let short = seal(&payload[..payload.len() - 1]);
+    assert_eq!(mach.load_state(&short), Err(StateErr::Truncated));
+
+    let flags = payload.len() - SEEDED_STATE_LEN - 2;
+    let mut unknown_quirk = payload.to_vec();
+    unknown_quirk[flags] |= 0x80;
+    assert_eq!(
+        mach.load_state(&seal(&unknown_quirk)),
+        Err(StateErr::Corrupt)
+    );
+    let mut bad_strict = payload.to_vec();
+    bad_strict[flags + 1] = 2;
+    assert_eq!(mach.load_state(&seal(&bad_strict)), Err(StateErr::Corrupt));
+    let mut trailing = payload.to_vec();
+    trailing.push(0);
+    assert_eq!(mach.load_state(&seal(&trailing)), Err(StateErr::Corrupt));
+
+    assert_eq!(mach.save_state(), before);
+}
+
+#[test]
+fn return_with_empty_stack_faults() {
+    let mut mach = Machine::new();
+    assert!(mach.execute_command(Command::Return).is_err());
+}
+
+#[test]
+fn call_past_stack_depth_faults() {
+    let mut mach = Machine::new();
+    for _ in 0..stack::STACK_SIZE {
+        mach.execute_command(Command::Call(0x200)).unwrap();
+    }
+    assert!(mach.execute_command(Command::Call(0x200)).is_err());
+}
+
+#[test]
+fn fetch_past_end_of_memory_faults() {
+    let mut mach = Machine::new();
+    mach.pc = 0xFFF;
+    assert!(mach.step().is_err());
+}
+
+#[test]
+fn strict_index_accesses_past_end_of_memory_fault() {
+    let mut mach = machine_with(&[(Reg::V0, 7), (Reg::V1, 0)]);
+    mach.set_strict_memory(true);
+    mach.index = 0xFFE;
+    assert!(mach
+        .execute_command(Command::Display(Reg::V0, Reg::V1, 3))
+        .is_err());
+    assert!(mach.execute_command(Command::BCDConv(Reg::V0)).is_err());
+    assert!(mach.execute_command(Command::Store(Reg::V2)).is_err());
+    assert!(mach
+        .execute_command(Command::LoadWithIndexIncrement(Reg::V2))
+        .is_err());
+    assert_eq!(mach.index, 0xFFE);
+    assert_eq!(mach.memory.get_data(0xFFE, 2).unwrap(), &[0, 0]);
+    mach.execute_command(Command::Store(Reg::V1)).unwrap();
+}
+
+#[test]
+fn index_accesses_wrap_at_end_of_memory() {
+    let mut mach = machine_with(&[(Reg::V0, 1), (Reg::V1, 2), (Reg::V2, 3), (Reg::V3, 4)]);
+    mach.index = 0xFFE;
+    mach.execute_command(Command::StoreWithIndexIncrement(Reg::V3))
+        .unwrap();
+    assert_eq!(mach.memory.get_data(0xFFE, 2).unwrap(), &[1, 2]);
+    assert_eq!(mach.memory.get_data(0x000, 2).unwrap(), &[3, 4]);
+    assert_eq!(mach.index, 0x002);
+
+    mach.index = 0xFFF;
+    mach.execute_command(Command::Load(Reg::V1)).unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 2);
+    assert_eq!(mach.reg.get_value(Reg::V1), 3);
+
+    mach.reg.set_value(Reg::V4, 255);
+    mach.execute_command(Command::BCDConv(Reg::V4)).unwrap();
+    assert_eq!(mach.memory.get_data(0xFFF, 1).unwrap(), &[2]);
+    assert_eq!(mach.memory.get_data(0x000, 2).unwrap(), &[5, 5]);
+
+    mach.reg.set_value(Reg::V0, 0);
+    mach.reg.set_value(Reg::V1, 0);
+    mach.execute_command(Command::Display(Reg::V0, Reg::V1, 2))
+        .unwrap();
+    assert!(pixel(&mach, 6, 0));
+    assert!(pixel(&mach, 5, 1));
+    assert!(pixel(&mach, 7, 1));
+}
+
+#[test]
+fn undecodable_opcode_faults() {
+    let mut mach = Machine::new();
+    mach.load(&[0x80, 0x08]).unwrap();
+    assert!(mach.step().is_err());
+}
+
+#[test]
+fn alu_flag_wins_when_vf_is_destination() {
+    let cases = [
+        (Command::AddReg(Reg::VF, Reg::V1), 0xF0, 0x20, 1),
+        (Command::AddReg(Reg::VF, Reg::V1), 0x10, 0x20, 0),
+        (Command::SubReg(Reg::VF, Reg::V1), 0x30, 0x10, 1),
+        (Command::SubReg(Reg::VF, Reg::V1), 0x10, 0x30, 0),
+        (Command::SubRegRev(Reg::VF, Reg::V1), 0x10, 0x30, 1),
+        (Command::SubRegRev(Reg::VF, Reg::V1), 0x30, 0x10, 0),
+        (Command::ShiftLeft(Reg::VF, Reg::V1), 0x00, 0x81, 1),
+        (Command::ShiftLeft(Reg::VF, Reg::V1), 0x00, 0x41, 0),
+        (Command::ShiftRight(Reg::VF, Reg::V1), 0x00, 0x03, 1),
+        (Command::ShiftRight(Reg::VF, Reg::V1), 0x00, 0x02, 0),
+    ];
+    for (command, val_f, val_1, flag) in cases {
+        let mut mach = machine_with(&[(Reg::VF, val_f), (Reg::V1, val_1)]);
+        mach.execute_command(command).unwrap();
+        assert_eq!(mach.reg.get_value(Reg::VF), flag, "{}", command);
+    }
+}
+
+#[test]
+fn alu_reads_vf_source_before_writing_flag() {
+    let mut mach = machine_with(&[(Reg::V0, 0x10), (Reg::VF, 0x05)]);
+    mach.execute_command(Command::AddReg(Reg::V0, Reg::VF))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x15);
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+
+    let mut mach = machine_with(&[(Reg::V0, 0x10), (Reg::VF, 0x05)]);
+    mach.execute_command(Command::SubReg(Reg::V0, Reg::VF))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x0B);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+
+    let mut mach = machine_with(&[(Reg::V0, 0x01), (Reg::VF, 0x05)]);
+    mach.execute_command(Command::SubRegRev(Reg::V0, Reg::VF))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x04);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+
+    let mut mach = machine_with(&[(Reg::VF, 0x81)]);
+    mach.execute_command(Command::ShiftLeft(Reg::V0, Reg::VF))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x02);
+    assert_eq!(mach.reg.get_value(Reg::VF), 1);
+
+    let mut mach = machine_with(&[(Reg::VF, 0x02)]);
+    mach.execute_command(Command::ShiftRight(Reg::V0, Reg::VF))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x01);
+    assert_eq!(mach.reg.get_value(Reg::VF), 0);
+}
+
+#[test]
+fn random_masks_source_byte() {
+    let mut mach = Machine::new();
+    mach.set_random(Box::new(ScriptedRandom::new(vec![0xAB, 0x3C])));
+    mach.execute_command(Command::Random(Reg::V0, 0x0F))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x0B);
+    mach.execute_command(Command::Random(Reg::V0, 0xF0))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0x30);
+    mach.execute_command(Command::Random(Reg::V0, 0xFF))
+        .unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 0xAB);
+}
+
+#[test]
+fn seeded_random_is_reproducible() {
+    let draw = |seed| {
+        let mut random = SeededRandom::new(seed);
+        (0..32).map(|_| random.next_byte()).collect::<Vec<u8>>()
+    };
+    assert_eq!(draw(1), draw(1));
+    assert_ne!(draw(1), draw(2));
+    assert!(draw(0).iter().any(|byte| *byte != 0));
+}
+
+#[test]
+fn save_state_restores_random_position() {
+    let mut mach = Machine::new();
+    mach.load(&[0xC0, 0xFF, 0x12, 0x00]).unwrap();
+    mach.step().unwrap();
+    let state = mach.save_state();
+    mach.step().unwrap();
+    mach.step().unwrap();
+    let expected = mach.reg.get_value(Reg::V0);
+
+    mach.load_state(&state).unwrap();
+    mach.step().unwrap();
+    mach.step().unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), expected);
+}
+
+// Runs 0x20C once, overwrites it with FX55, then runs it again.
+const SELF_MODIFYING: [u8; 16] = [
+    0x22, 0x0C, 0x60, 0x72, 0x61, 0x05, 0xA2, 0x0C, 0xF1, 0x55, 0x22, 0x0C, 0x72, 0x01, 0x00, 0xEE,
+];
+
+#[test]
+fn decode_cache_sees_stored_code() {
+    for enabled in [true, false] {
+        let mut mach = Machine::new();
+        mach.set_decode_cache(enabled);
+        mach.load(&SELF_MODIFYING).unwrap();
+        for _ in 0..9 {
+            mach.step().unwrap();
+        }
+        assert_eq!(mach.reg.get_value(Reg::V2), 6);
+    }
+}
+
+#[test]
+fn decode_cache_sees_bcd_code() {
+    let mut mach = machine_with(&[(Reg::V0, 234)]);
+    // Calls ADD V1, 0x01 at 0x208, then lets FX33 turn it into ADD V1, 0x02.
+    mach.load(&[
+        0xA2, 0x09, 0x22, 0x08, 0xF0, 0x33, 0x12, 0x08, 0x71, 0x01, 0x00, 0xEE,
+    ])
+    .unwrap();
+    for _ in 0..7 {
+        mach.step().unwrap();
+    }
+    assert_eq!(mach.reg.get_value(Reg::V1), 3);
+}
+
+#[test]
+fn decode_cache_sees_reload() {
+    let mut mach = Machine::new();
+    mach.load(&[0x60, 0x01]).unwrap();
+    mach.step().unwrap();
+    mach.pc = LOAD_OFFSET;
+    mach.load(&[0x60, 0x02]).unwrap();
+    mach.step().unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 2);
+}
+
+#[test]
+fn decode_cache_sees_written_memory() {
+    let mut mach = Machine::new();
+    mach.load(&[0x60, 0x01]).unwrap();
+    mach.step().unwrap();
+    mach.pc = LOAD_OFFSET;
+    mach.write_memory(LOAD_OFFSET + 1, &[0x03]).unwrap();
+    mach.step().unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 3);
+    assert!(mach.write_memory(0xFFF, &[0, 0]).is_err());
+}
+
+#[test]
+fn threaded_backend_sees_self_modifying_code() {
+    // Each pass adds the immediate of `V1 += 0` to V1, then stores the pass
+    // count over that immediate.
+    let rom = [0xA2, 0x03, 0x71, 0x00, 0x70, 0x01, 0xF0, 0x55, 0x12, 0x00];
+    let mut reference = Machine::new();
+    let mut threaded = Machine::new();
+    threaded.set_backend(Backend::Threaded);
+    for mach in [&mut reference, &mut threaded] {
+        mach.load(&rom).unwrap();
+        mach.run_frame(40).unwrap();
+    }
+    assert_eq!(threaded.reg.get_value(Reg::V1), (0..8).sum());
+    assert_eq!(
+        threaded.reg.get_value(Reg::V1),
+        reference.reg.get_value(Reg::V1)
+    );
+}
+
+#[test]
+fn threaded_backend_stops_mid_block_at_the_frame_budget() {
+    let mut mach = Machine::new();
+    mach.set_backend(Backend::Threaded);
+    mach.load(&[0x70, 0x01].repeat(16)).unwrap();
+    mach.run_frame(10).unwrap();
+    assert_eq!(mach.reg.get_value(Reg::V0), 10);
+    assert_eq!(mach.pc, LOAD_OFFSET + 20);
+    assert_eq!(mach.cycles, 10);
+}
+
+#[test]
+fn threaded_backend_faults_like_the_interpreter() {
+    let mut mach = Machine::new();
+    mach.set_backend(Backend::Threaded);
+    mach.load(&[0x60, 0x01, 0x00, 0x00]).unwrap();
+    assert!(mach.run_frame(10).is_err());
+    assert_eq!(mach.pc, LOAD_OFFSET + 4);
+    assert_eq!(mach.cycles, 1);
+}
+
+#[test]
+fn batch_lanes_round_trip_through_machines() {
+    let mut mach = machine_with(&[(Reg::V3, 0x42)]);
+    mach.load(&[0x12, 0x00]).unwrap();
+    mach.set_key(Key::Key7, true);
+    mach.run_frame(3).unwrap();
+    let batch = MachineBatch::from_machine(&mach, 3, |_| mach.random.clone());
+    assert_eq!(batch.len(), 3);
+    assert_eq!(batch.machine(2).save_state(), mach.save_state());
+}
+
+#[test]
+fn batch_fault_stops_only_its_lane() {
+    let mut batch = MachineBatch::new(3, Quirks::default(), |lane| {
+        SeededRandom::new(lane as u64 + 1)
+    });
+    batch.load(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+    batch.load_lane(1, &[0x70, 0x01, 0x00, 0x00]).unwrap();
+    let faults: Vec<bool> = batch
+        .run_frame(&[0; 3], 10)
+        .unwrap()
+        .map(|observation| observation.fault)
+        .collect();
+    assert_eq!(faults, [false, true, false]);
+    assert_eq!(batch.cycles(), [10, 1, 10]);
+    assert_eq!(batch.pcs()[1], LOAD_OFFSET + 4);
+    let _ = batch.run_frame(&[0; 3], 10).unwrap();
+    assert_eq!(batch.cycles(), [20, 1, 20]);
+}
+
+#[test]
+fn batch_lanes_see_their_own_keys() {
+    let mut batch = MachineBatch::new(3, Quirks::default(), |lane| {
+        SeededRandom::new(lane as u64 + 1)
+    });
+    batch.load(&[0xF0, 0x0A, 0x12, 0x02]).unwrap();
+    let _ = batch.run_frame(&[0, 1 << 0xA, 1 << 3 | 1 << 9], 4).unwrap();
+    let v0: Vec<u8> = batch.regs().iter().map(|regs| regs[0]).collect();
+    assert_eq!(v0, [0, 0xA, 3]);
+    assert_eq!(batch.pcs(), [LOAD_OFFSET, LOAD_OFFSET + 2, LOAD_OFFSET + 2]);
+    assert!(batch.run_frame(&[0; 2], 1).is_err());
+}
+
+#[test]
+fn batch_lanes_draw_from_their_own_random_sources() {
+    let rom = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF, 0xC3, 0xFF];
+    let mut batch = MachineBatch::new(4, Quirks::default(), |lane| {
+        SeededRandom::new(100 + lane as u64)
+    });
+    batch.load(&rom).unwrap();
+    let _ = batch.run_frame(&[0; 4], 4).unwrap();
+    for lane in 0..4 {
+        let mut mach = Machine::new();
+        mach.set_random(Box::new(SeededRandom::new(100 + lane as u64)));
+        mach.load(&rom).unwrap();
+        mach.run_frame(4).unwrap();
+        assert_eq!(batch.regs()[lane][..4], mach.reg.to_array()[..4]);
+    }
+    assert_ne!(batch.regs()[0][..4], batch.regs()[1][..4]);
+}
+
+#[test]
+fn batch_chunks_cover_every_lane_once() {
+    let mut batch = MachineBatch::new(7, Quirks::default(), |lane| {
+        SeededRandom::new(lane as u64 + 1)
+    });
+    let lanes: Vec<_> = batch.chunks_mut(3).map(|chunk| chunk.lanes()).collect();
+    assert_eq!(lanes, [0..3, 3..6, 6..7]);
+}
+
+#[test]
+fn display_rows_are_packed_msb_first() {
+    let mut display = MachDisplay::<64, 32>::new();
+    display.set_pixel(0, 1, true).unwrap();
+    display.set_pixel(63, 1, true).unwrap();
+    assert_eq!(display.rows()[1], 0x8000_0000_0000_0001);
+    assert!(display.set_pixel(64, 0, true).is_err());
+}
+
+#[test]
+#[allow(deprecated)]
+fn pixel_accessors_by_reference_still_work() {
+    let mut display = MachDisplay::<64, 32>::new();
+    *display.get_pixel_mut(3, 4).unwrap() = true;
+    assert!(*display.get_pixel(3, 4).unwrap());
+    assert!(display.pixel(3, 4).unwrap());
+    let mut pixel = display.get_pixel_mut(3, 4).unwrap();
+    *pixel = !*pixel;
+    drop(pixel);
+    assert!(!display.pixel(3, 4).unwrap());
+    assert!(display.get_pixel(64, 0).is_err());
+    assert!(display.get_pixel_mut(0, 32).is_err());
+}
+
+#[cfg(feature = "jit")]
+mod jit {
+    use super::*;
+
+    fn run(rom: &[u8], backend: Backend, instructions: usize) -> Machine {
+        let mut mach = Machine::new();
+        mach.set_backend(backend);
+        mach.load(rom).unwrap();
+        mach.run_frame(instructions).unwrap();
+        mach
+    }
+
+    #[test]
+    fn jit_compiles_hot_loops() {
+        // V0 counts up, V1 accumulates it with carry into VF, I steps by V0.
+        let rom = [0x70, 0x01, 0x81, 0x04, 0xF0, 0x1E, 0x12, 0x00];
+        let reference = run(&rom, Backend::Interpreter, 1000);
+        let jit = run(&rom, Backend::Jit, 1000);
+        assert_eq!(jit.backend(), Backend::Jit);
+        assert!(jit.jit.is_compiled(LOAD_OFFSET));
+        assert_eq!(jit.reg.to_array(), reference.reg.to_array());
+        assert_eq!(
+            (jit.index, jit.pc, jit.cycles),
+            (reference.index, reference.pc, 1000)
+        );
+    }
+
+    #[test]
+    fn jit_add_index_keeps_a_stale_vf() {
+        let jit = run(&ADD_INDEX_LOOP, Backend::Jit, 101);
+        assert!(jit.jit.is_compiled(LOAD_OFFSET + 6));
+        assert_eq!(jit.index, 0x131);
+        assert_eq!(jit.reg.get_value(Reg::VF), 1);
+    }
+
+    #[test]
+    fn jit_leaves_self_modifying_code_to_the_interpreter() {
+        // The loop from `threaded_backend_sees_self_modifying_code`.
+        let rom = [0xA2, 0x03, 0x71, 0x00, 0x70, 0x01, 0xF0, 0x55, 0x12, 0x00];
+        let jit = run(&rom, Backend::Jit, 200);
+        assert!(!jit.jit.is_compiled(LOAD_OFFSET + 2));
+        assert_eq!(
+            jit.reg.get_value(Reg::V1),
+            (0..40u8).fold(0, u8::wrapping_add)
+        );
+    }
+
+    #[test]
+    fn jit_clone_and_reload_start_cold() {
+        let rom = [0x70, 0x01, 0x12, 0x00];
+        let mut jit = run(&rom, Backend::Jit, 100);
+        assert!(!jit.clone().jit.is_compiled(LOAD_OFFSET));
+        assert_eq!(jit.clone().backend(), Backend::Jit);
+        jit.load(&rom).unwrap();
+        assert!(!jit.jit.is_compiled(LOAD_OFFSET));
+    }
+}
+
+#[cfg(feature = "embedded-graphics")]
+mod graphics {
+    use embedded_graphics::{geometry::Point, mock_display::MockDisplay, pixelcolor::BinaryColor};
+
+    use super::*;
+
+    type Screen = MachDisplay<DISPLAY_WIDTH, DISPLAY_HEIGHT>;
+    type Renderer = DisplayRenderer<DISPLAY_WIDTH, DISPLAY_HEIGHT>;
+
+    fn screen_with_sprite() -> Screen {
+        let mut screen = Screen::new();
+        screen.draw(&[0b1100_0000, 0b0100_0000], 62, 1, false);
+        screen
+    }
+
+    #[test]
+    fn renderer_draws_the_screen_at_an_offset() {
+        let mut target = MockDisplay::new();
+        let mut renderer = Renderer::new().with_offset(Point::new(0, 16));
+        assert_eq!(renderer.render(&screen_with_sprite(), &mut target), Ok(32));
+        for (x, y, lit) in [
+            (62, 17, true),
+            (63, 17, true),
+            (62, 18, false),
+            (63, 18, true),
+        ] {
+            let color = if lit {
+                BinaryColor::On
+            } else {
+                BinaryColor::Off
+            };
+            assert_eq!(target.get_pixel(Point::new(x, y)), Some(color));
+        }
+        assert_eq!(target.get_pixel(Point::new(0, 15)), None);
+        assert_eq!(target.get_pixel(Point::new(0, 16)), Some(BinaryColor::Off));
+        assert_eq!(target.get_pixel(Point::new(0, 48)), None);
+    }
+
+    #[test]
+    fn renderer_scales_each_pixel_to_a_block() {
+        let mut screen = Screen::new();
+        screen.set_pixel(1, 1, true).unwrap();
+        let mut target = MockDisplay::new();
+        target.set_allow_out_of_bounds_drawing(true);
+        let mut renderer = Renderer::new().with_scale(3);
+        assert_eq!(renderer.bounding_box().size.width, 192);
+        renderer.render(&screen, &mut target).unwrap();
+        for y in 0..9 {
+            for x in 0..9 {
+                let lit = (3..6).contains(&x) && (3..6).contains(&y);
+                let color = if lit {
+                    BinaryColor::On
+                } else {
+                    BinaryColor::Off
+                };
+                assert_eq!(target.get_pixel(Point::new(x, y)), Some(color));
+            }
+        }
+    }
+
+    #[test]
+    fn dirty_tracking_only_pushes_changed_rows() {
+        let mut screen = screen_with_sprite();
+        let mut renderer = Renderer::new().with_dirty_tracking(true);
+        // MockDisplay panics when a pixel is drawn twice.
+        let mut target = MockDisplay::new();
+        assert_eq!(renderer.render(&screen, &mut target), Ok(32));
+        let mut target = MockDisplay::new();
+        assert_eq!(renderer.render(&screen, &mut target), Ok(0));
+
+        screen.set_pixel(5, 20, true).unwrap();
+        screen.draw(&[0b1000_0000], 63, 2, false);
+        assert_eq!(renderer.render(&screen, &mut target), Ok(2));
+        assert_eq!(target.get_pixel(Point::new(5, 20)), Some(BinaryColor::On));
+        assert_eq!(target.get_pixel(Point::new(63, 2)), Some(BinaryColor::Off));
+        assert_eq!(target.get_pixel(Point::new(0, 0)), None);
+
+        renderer.invalidate();
+        let mut target = MockDisplay::new();
+        assert_eq!(renderer.render(&screen, &mut target), Ok(32));
+    }
+}
@@ -0,0 +1,434 @@
+//! Threaded-code backend.
+//!
+//! A basic block runs from the address execution entered at up to and
+//! including the next instruction that moves PC other than by falling through
+//! (jumps, calls, returns, skips and `FX0A`) or that writes memory. Each
+//! instruction is translated once into a handler function pointer and its
+//! pre-extracted operands, so running a block is a loop of indirect calls with
+//! no fetch, decode or dispatch `match`.
+//!
+//! Blocks are looked up by their first address and their handlers live in one
+//! arena. A write into a block's bytes drops it; because writes end a block,
+//! the running block is never changed under it. Instructions the translator
+//! refuses fall back to [`Machine::step`], which reports the same fault the
+//! reference interpreter does.
+//!
+//! The arena and tables are allocated when the backend is selected, so running
+//! never allocates; a full arena is flushed and refilled.
+
+use alloc::{vec, vec::Vec};
+use core::fmt::{self, Debug};
+
+use super::{exec, Command, Machine, MachineErr, RandomSource, Reg, MEMORY_SIZE};
+
+/// How [`Machine::run_frame`] executes instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Fetch, decode and execute one instruction at a time.
+    #[default]
+    Interpreter,
+    /// Run cached basic blocks of pre-resolved handlers.
+    Threaded,
+    /// Compile hot basic blocks to x86-64 code.
+    #[cfg(feature = "jit")]
+    Jit,
+}
+
+// Long blocks are split so invalidation only scans a bounded window.
+const MAX_BLOCK_LEN: usize = 32;
+const ARENA_LEN: usize = 16 * 1024;
+
+#[derive(Clone, Copy)]
+struct Args {
+    x: Reg,
+    y: Reg,
+    nn: u8,
+    nnn: u16,
+}
+
+type Handler<R> = fn(&mut Machine<R>, Args) -> Result<(), MachineErr>;
+
+struct Op<R: RandomSource> {
+    handler: Handler<R>,
+    args: Args,
+}
+
+// Derived impls would needlessly require `R: Clone`.
+impl<R: RandomSource> Clone for Op<R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R: RandomSource> Copy for Op<R> {}
+
+#[derive(Clone, Copy)]
+struct Block {
+    first: usize,
+    len: usize,
+}
+
+pub struct BlockCache<R: RandomSource> {
+    // Indexed by a block's first address; all three are empty while the
+    // interpreter backend is selected.
+    blocks: Vec<Option<Block>>,
+    // Bytes that belonged to some block since the last flush.
+    code: Vec<bool>,
+    ops: Vec<Op<R>>,
+}
+
+impl<R: RandomSource> Clone for BlockCache<R> {
+    fn clone(&self) -> Self {
+        let mut ops = Vec::with_capacity(self.ops.capacity());
+        ops.extend_from_slice(&self.ops);
+        Self {
+            blocks: self.blocks.clone(),
+            code: self.code.clone(),
+            ops,
+        }
+    }
+}
+
+impl<R: RandomSource> Debug for BlockCache<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockCache")
+            .field("backend", &self.backend())
+            .field("blocks", &self.blocks.iter().flatten().count())
+            .field("ops", &self.ops.len())
+            .finish()
+    }
+}
+
+impl<R: RandomSource> Default for BlockCache<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: RandomSource> BlockCache<R> {
+    pub fn new() -> Self {
+        Self {
+            blocks: Vec::new(),
+            code: Vec::new(),
+            ops: Vec::new(),
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        if self.blocks.is_empty() {
+            Backend::Interpreter
+        } else {
+            Backend::Threaded
+        }
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        *self = Self::new();
+        if backend == Backend::Threaded {
+            self.blocks = vec![None; MEMORY_SIZE];
+            self.code = vec![false; MEMORY_SIZE];
+            self.ops = Vec::with_capacity(ARENA_LEN);
+        }
+    }
+
+    fn get(&self, addr: u16) -> Option<Block> {
+        self.blocks.get(addr as usize).copied().flatten()
+    }
+
+    fn flush(&mut self) {
+        self.blocks.fill(None);
+        self.code.fill(false);
+        self.ops.clear();
+    }
+
+    /// Drops every block that overlaps the `len` bytes at `addr`.
+    pub fn invalidate(&mut self, addr: usize, len: usize) {
+        let end = (addr + len).min(self.code.len());
+        for addr in addr..end {
+            if !self.code[addr] {
+                continue;
+            }
+            for start in addr.saturating_sub(MAX_BLOCK_LEN * 2 - 1)..=addr {
+                if self.blocks[start].is_some_and(|block| start + block.len * 2 > addr) {
+                    self.blocks[start] = None;
+                }
+            }
+        }
+    }
+}
+
+impl<R: RandomSource> Machine<R> {
+    pub fn backend(&self) -> Backend {
+        #[cfg(feature = "jit")]
+        if self.jit.is_enabled() {
+            return Backend::Jit;
+        }
+        self.threaded.backend()
+    }
+
+    /// Selects how [`Machine::run_frame`] executes. All backends behave
+    /// identically; [`Machine::step`] always uses the interpreter. The JIT
+    /// needs executable memory; if it cannot be mapped, the machine keeps
+    /// interpreting and [`Machine::backend`] says so.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.threaded.set_backend(backend);
+        #[cfg(feature = "jit")]
+        self.jit.set_enabled(backend == Backend::Jit);
+    }
+
+    pub(crate) fn run_threaded(&mut self, instructions: usize) -> Result<(), MachineErr> {
+        let mut left = instructions;
+        while left > 0 {
+            let Some(block) = self
+                .threaded
+                .get(self.pc)
+                .or_else(|| self.translate(self.pc))
+            else {
+                self.step()?;
+                left -= 1;
+                continue;
+            };
+            let len = block.len.min(left);
+            for op in block.first..block.first + len {
+                let op = self.threaded.ops[op];
+                self.increment_pc();
+                (op.handler)(self, op.args)?;
+                self.cycles += 1;
+            }
+            left -= len;
+        }
+        Ok(())
+    }
+
+    fn translate(&mut self, pc: u16) -> Option<Block> {
+        if self.threaded.ops.len() + MAX_BLOCK_LEN > ARENA_LEN {
+            self.threaded.flush();
+        }
+        let first = self.threaded.ops.len();
+        let mut addr = pc;
+        while self.threaded.ops.len() - first < MAX_BLOCK_LEN {
+            let Some(command) = self
+                .peek_opcode(addr)
+                .ok()
+                .and_then(|opcode| self.decode_command(opcode).ok())
+            else {
+                break;
+            };
+            let Some((handler, args, ends_block)) = compile(command) else {
+                break;
+            };
+            self.threaded.ops.push(Op { handler, args });
+            addr = addr.wrapping_add(2);
+            if ends_block {
+                break;
+            }
+        }
+        let block = Block {
+            first,
+            len: self.threaded.ops.len() - first,
+        };
+        if block.len == 0 {
+            return None;
+        }
+        self.threaded.code[pc as usize..addr as usize].fill(true);
+        self.threaded.blocks[pc as usize] = Some(block);
+        Some(block)
+    }
+}
+
+fn args(x: Reg, y: Reg, nn: u8, nnn: u16) -> Args {
+    Args { x, y, nn, nnn }
+}
+
+// Returns the handler, its operands and whether the block ends after it, or
+// `None` for commands the interpreter faults on.
+fn compile<R: RandomSource>(command: Command) -> Option<(Handler<R>, Args, bool)> {
+    use Reg::V0;
+
+    Some(match command {
+        Command::ClearScreen => (clear_screen, args(V0, V0, 0, 0), false),
+        Command::Jump(addr) => (jump, args(V0, V0, 0, addr), true),
+        Command::JumpWithOffset(addr, x) => (jump_with_offset, args(x, V0, 0, addr), true),
+        Command::Call(addr) => (call, args(V0, V0, 0, addr), true),
+        Command::Return => (ret, args(V0, V0, 0, 0), true),
+        Command::SkipIfRegVal(x, nn) => (skip_if_val, args(x, V0, nn, 0), true),
+        Command::SkipIfRegValNot(x, nn) => (skip_if_not_val, args(x, V0, nn, 0), true),
+        Command::SkipIfRegEqual(x, y) => (skip_if_equal, args(x, y, 0, 0), true),
+        Command::SkipIfRegNotEqual(x, y) => (skip_if_not_equal, args(x, y, 0, 0), true),
+        Command::SetVal(x, nn) => (set_val, args(x, V0, nn, 0), false),
+        Command::AddVal(x, nn) => (add_val, args(x, V0, nn, 0), false),
+        Command::SetReg(x, y) => (set_reg, args(x, y, 0, 0), false),
+        Command::BinOR(x, y) => (or, args(x, y, 0, 0), false),
+        Command::BinAND(x, y) => (and, args(x, y, 0, 0), false),
+        Command::LogXOR(x, y) => (xor, args(x, y, 0, 0), false),
+        Command::AddReg(x, y) => (add_reg, args(x, y, 0, 0), false),
+        Command::SubReg(x, y) => (sub_reg, args(x, y, 0, 0), false),
+        Command::SubRegRev(x, y) => (sub_reg_rev, args(x, y, 0, 0), false),
+        Command::ShiftLeft(x, y) => (shift_left, args(x, y, 0, 0), false),
+        Command::ShiftRight(x, y) => (shift_right, args(x, y, 0, 0), false),
+        Command::SetIndex(addr) => (set_index, args(V0, V0, 0, addr), false),
+        Command::Random(x, nn) => (random, args(x, V0, nn, 0), false),
+        Command::Display(x, y, n) => (display, args(x, y, n, 0), false),
+        Command::SkipIfKey(x) => (skip_if_key, args(x, V0, 0, 0), true),
+        Command::SkipIfNotKey(x) => (skip_if_not_key, args(x, V0, 0, 0), true),
+        Command::SetRegFromDelayTimer(x) => (get_delay_timer, args(x, V0, 0, 0), false),
+        Command::SetDelayTimerFromReg(x) => (set_delay_timer, args(x, V0, 0, 0), false),
+        Command::SetSoundTimerFromReg(x) => (set_sound_timer, args(x, V0, 0, 0), false),
+        Command::AddIndex(x) => (add_index, args(x, V0, 0, 0), false),
+        Command::GetKey(x) => (get_key, args(x, V0, 0, 0), true),
+        Command::Font(x) => (font, args(x, V0, 0, 0), false),
+        Command::BCDConv(x) => (bcd, args(x, V0, 0, 0), true),
+        Command::Store(x) => (store, args(x, V0, 0, 0), true),
+        Command::Load(x) => (load, args(x, V0, 0, 0), false),
+        Command::StoreWithIndexIncrement(x) => (store_increment, args(x, V0, 0, 0), true),
+        Command::LoadWithIndexIncrement(x) => (load_increment, args(x, V0, 0, 0), false),
+        Command::ExecuteMachineRoutine(_) | Command::Skip => return None,
+    })
+}
+
+// Each handler runs its instruction's function in `exec`; PC has already
+// moved past the instruction.
+
+fn clear_screen<R: RandomSource>(mach: &mut Machine<R>, _: Args) -> Result<(), MachineErr> {
+    exec::clear_screen(mach)
+}
+
+fn jump<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::jump(mach, args.nnn)
+}
+
+fn jump_with_offset<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::jump_with_offset(mach, args.nnn, args.x)
+}
+
+fn call<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::call(mach, args.nnn)
+}
+
+fn ret<R: RandomSource>(mach: &mut Machine<R>, _: Args) -> Result<(), MachineErr> {
+    exec::ret(mach)
+}
+
+fn skip_if_val<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::skip_if_val(mach, args.x, args.nn)
+}
+
+fn skip_if_not_val<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::skip_if_not_val(mach, args.x, args.nn)
+}
+
+fn skip_if_equal<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::skip_if_equal(mach, args.x, args.y)
+}
+
+fn skip_if_not_equal<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::skip_if_not_equal(mach, args.x, args.y)
+}
+
+fn skip_if_key<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::skip_if_key(mach, args.x)
+}
+
+fn skip_if_not_key<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::skip_if_not_key(mach, args.x)
+}
+
+fn set_val<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::set_val(mach, args.x, args.nn)
+}
+
+fn add_val<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::add_val(mach, args.x, args.nn)
+}
+
+fn set_reg<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::set_reg(mach, args.x, args.y)
+}
+
+fn or<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::or(mach, args.x, args.y)
+}
+
+fn and<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::and(mach, args.x, args.y)
+}
+
+fn xor<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::xor(mach, args.x, args.y)
+}
+
+fn add_reg<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::add_reg(mach, args.x, args.y)
+}
+
+fn sub_reg<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::sub_reg(mach, args.x, args.y)
+}
+
+fn sub_reg_rev<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::sub_reg_rev(mach, args.x, args.y)
+}
+
+fn shift_left<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::shift_left(mach, args.x, args.y)
+}
+
+fn shift_right<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::shift_right(mach, args.x, args.y)
+}
+
+fn set_index<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::set_index(mach, args.nnn)
+}
+
+fn random<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::random(mach, args.x, args.nn)
+}
+
+fn display<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::display(mach, args.x, args.y, args.nn)
+}
+
+fn get_delay_timer<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::get_delay_timer(mach, args.x)
+}
+
+fn set_delay_timer<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::set_delay_timer(mach, args.x)
+}
+
+fn set_sound_timer<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::set_sound_timer(mach, args.x)
+}
+
+fn add_index<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::add_index(mach, args.x)
+}
+
+fn get_key<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::get_key(mach, args.x)
+}
+
+fn font<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::font(mach, args.x)
+}
+
+fn bcd<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::bcd(mach, args.x)
+}
+
+fn store<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::store(mach, args.x)
+}
+
+fn load<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::load(mach, args.x)
+}
+
+fn store_increment<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::store_increment(mach, args.x)
+}
+
+fn load_increment<R: RandomSource>(mach: &mut Machine<R>, args: Args) -> Result<(), MachineErr> {
+    exec::load_increment(mach, args.x)
+}
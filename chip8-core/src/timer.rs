@@ -13,7 +13,6 @@ impl Timer {
         self.val = self.val.wrapping_add(1);
     }
 
-    #[allow(dead_code)]
     pub fn decrement(&mut self) {
         self.val = self.val.wrapping_sub(1);
     }
@@ -0,0 +1,14 @@
+#![no_main]
+
+use chip8emu::machine::mach::{Command, RawCommand};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for word in data.chunks_exact(2) {
+        let raw = RawCommand(u16::from_be_bytes([word[0], word[1]]));
+        let command: Result<Command, _> = raw.try_into();
+        if let Ok(command) = command {
+            let _ = command.to_string();
+        }
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use chip8emu::machine::Machine;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut mach = Machine::new();
+    if mach.load_state(data).is_ok() {
+        let _ = mach.step();
+    }
+});
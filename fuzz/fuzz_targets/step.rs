@@ -0,0 +1,73 @@
+#![no_main]
+
+//! Input layout: one platform byte, sixteen big-endian keypad masks (one per
+//! frame, repeated), then the memory image loaded at 0x200.
+
+use chip8emu::machine::{Backend, Key, Machine, Platform, INSTRUCTIONS_PER_FRAME};
+use libfuzzer_sys::fuzz_target;
+
+const FRAMES: usize = 64;
+const KEY_FRAMES: usize = 16;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 1 + KEY_FRAMES * 2 {
+        return;
+    }
+    let (header, image) = data.split_at(1 + KEY_FRAMES * 2);
+    let platform = [Platform::Chip8, Platform::SuperChip, Platform::XoChip][header[0] as usize % 3];
+    let masks: Vec<u16> = header[1..]
+        .chunks_exact(2)
+        .map(|mask| u16::from_be_bytes([mask[0], mask[1]]))
+        .collect();
+
+    // The uncached, threaded and (on x86-64 Linux) JIT twins check that
+    // neither the decode cache nor the other backends ever change behaviour.
+    let mut mach = Machine::with_quirks(platform.quirks());
+    let mut uncached = Machine::with_quirks(platform.quirks());
+    uncached.set_decode_cache(false);
+    let mut threaded = Machine::with_quirks(platform.quirks());
+    threaded.set_backend(Backend::Threaded);
+    let mut twins = vec![uncached, threaded];
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    {
+        let mut jit = Machine::with_quirks(platform.quirks());
+        jit.set_backend(Backend::Jit);
+        twins.push(jit);
+    }
+    if mach.load(image).is_err() {
+        return;
+    }
+    for twin in &mut twins {
+        twin.load(image).unwrap();
+    }
+    for frame in 0..FRAMES {
+        let mask = masks[frame % KEY_FRAMES];
+        for key in 0..16 {
+            let pressed = mask & (1 << key) != 0;
+            mach.set_key(Key::from(key), pressed);
+            for twin in &mut twins {
+                twin.set_key(Key::from(key), pressed);
+            }
+        }
+        let result = mach.run_frame(INSTRUCTIONS_PER_FRAME);
+        for twin in &mut twins {
+            assert_eq!(
+                result.is_ok(),
+                twin.run_frame(INSTRUCTIONS_PER_FRAME).is_ok(),
+                "{:?}",
+                twin.backend()
+            );
+        }
+        if result.is_err() {
+            break;
+        }
+    }
+
+    let state = mach.save_state();
+    for twin in &twins {
+        assert_eq!(twin.save_state(), state, "{:?}", twin.backend());
+    }
+    let mut restored = Machine::with_quirks(platform.quirks());
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+});
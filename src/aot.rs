@@ -0,0 +1,398 @@
+//! Ahead-of-time recompilation of a ROM to Rust.
+//!
+//! `Program::discover` finds the code by recursive traversal from 0x200: it
+//! follows jump and call targets, the return address of every call and both
+//! outcomes of every skip. It stops at `00EE`, at `BNNN`, whose target is only
+//! known at run time, and at anything that does not decode or lies outside the
+//! ROM. The code is then cut into basic blocks, each ending at a control
+//! transfer, at a store to memory, or where another block begins.
+//!
+//! `Program::emit` writes a Rust module with one function per block, each
+//! driving a `chip8emu::machine::Machine` directly, plus a `run_frame` that
+//! dispatches on PC. The module embeds the ROM. A block only runs while its
+//! bytes in memory still match the ROM and the frame has room for all of its
+//! instructions; otherwise, and at addresses without a block, `run_frame`
+//! falls back to `Machine::step`. Indirect jumps, self-modifying code and code
+//! copied outside the ROM therefore behave exactly as under the interpreter.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use crate::machine::{Command, Machine, Platform, Reg};
+
+const ROM_START: u16 = 0x200;
+const MAX_BLOCK_LEN: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    rom: Vec<u8>,
+    platform: Platform,
+    blocks: BTreeMap<u16, Vec<(u16, Command)>>,
+}
+
+#[derive(Debug)]
+pub struct AotErr;
+
+impl Program {
+    pub fn discover(rom: &[u8], platform: Platform) -> Result<Self, AotErr> {
+        let mut mach = Machine::with_quirks(platform.quirks());
+        mach.load(rom).map_err(|_| AotErr)?;
+        let end = ROM_START as usize + rom.len();
+        let mut code = BTreeMap::new();
+        let mut leaders = BTreeSet::from([ROM_START]);
+        let mut pending = vec![ROM_START];
+        while let Some(addr) = pending.pop() {
+            if code.contains_key(&addr) || addr < ROM_START || addr as usize + 2 > end {
+                continue;
+            }
+            let Ok(command) = mach.peek_command(addr) else {
+                continue;
+            };
+            code.insert(addr, command);
+            let next = addr + 2;
+            let targets = match command {
+                Command::Jump(target) => vec![target],
+                Command::Call(target) => vec![target, next],
+                Command::SkipIfRegVal(..)
+                | Command::SkipIfRegValNot(..)
+                | Command::SkipIfRegEqual(..)
+                | Command::SkipIfRegNotEqual(..)
+                | Command::SkipIfKey(_)
+                | Command::SkipIfNotKey(_) => vec![next, next + 2],
+                command if ends_flow(command) => vec![],
+                command if ends_block(command) => vec![next],
+                _ => {
+                    pending.push(next);
+                    continue;
+                }
+            };
+            leaders.extend(targets.iter().copied());
+            pending.extend(targets);
+        }
+        let mut blocks = BTreeMap::new();
+        let mut starts: Vec<u16> = leaders.iter().rev().copied().collect();
+        while let Some(start) = starts.pop() {
+            if blocks.contains_key(&start) || !code.contains_key(&start) {
+                continue;
+            }
+            let mut block = Vec::new();
+            let mut addr = start;
+            while let Some(&command) = code.get(&addr) {
+                block.push((addr, command));
+                addr += 2;
+                if ends_block(command) || leaders.contains(&addr) {
+                    break;
+                }
+                if block.len() == MAX_BLOCK_LEN {
+                    starts.push(addr);
+                    break;
+                }
+            }
+            blocks.insert(start, block);
+        }
+        Ok(Self {
+            rom: rom.to_vec(),
+            platform,
+            blocks,
+        })
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn instruction_count(&self) -> usize {
+        self.blocks.values().map(Vec::len).sum()
+    }
+
+    pub fn emit(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "//! Generated by `chip8emu --recompile`; do not edit.").unwrap();
+        writeln!(out, "//!").unwrap();
+        writeln!(
+            out,
+            "//! {} blocks, {} instructions, platform `{}`.",
+            self.block_count(),
+            self.instruction_count(),
+            self.platform
+        )
+        .unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "#![allow(dead_code, unused_imports)]").unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "use chip8emu::machine::{{mach::Reg::*, Command, Key, Machine, MachineErr, Platform}};"
+        )
+        .unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "pub const PLATFORM: Platform = Platform::{:?};",
+            self.platform
+        )
+        .unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "pub static ROM: [u8; {}] = [", self.rom.len()).unwrap();
+        for row in self.rom.chunks(16) {
+            let bytes: Vec<String> = row.iter().map(|byte| format!("0x{:02x},", byte)).collect();
+            writeln!(out, "    {}", bytes.join(" ")).unwrap();
+        }
+        writeln!(out, "];").unwrap();
+        writeln!(out).unwrap();
+        out.push_str(concat!(
+            "/// A machine for `PLATFORM` with the ROM loaded.\n",
+            "pub fn machine() -> Machine {\n",
+            "    let mut mach = Machine::with_quirks(PLATFORM.quirks());\n",
+            "    mach.load(&ROM).unwrap();\n",
+            "    mach\n",
+            "}\n",
+            "\n",
+            "/// Runs `instructions` instructions and ticks the timers, like\n",
+            "/// `Machine::run_frame`.\n",
+            "pub fn run_frame(mach: &mut Machine, instructions: usize) -> Result<(), MachineErr> {\n",
+            "    let mut left = instructions;\n",
+            "    while left > 0 {\n",
+            "        left -= match mach.pc() {\n",
+        ));
+        for (&start, block) in &self.blocks {
+            let len = block.len();
+            let end = start as usize + 2 * len;
+            writeln!(
+                out,
+                "            0x{:03x} if left >= {} && unchanged(mach, 0x{:03x}, 0x{:03x}) => {{",
+                start, len, start, end
+            )
+            .unwrap();
+            writeln!(out, "                block_{:03x}(mach)?;", start).unwrap();
+            writeln!(out, "                {}", len).unwrap();
+            writeln!(out, "            }}").unwrap();
+        }
+        out.push_str(concat!(
+            "            _ => {\n",
+            "                mach.step()?;\n",
+            "                1\n",
+            "            }\n",
+            "        };\n",
+            "    }\n",
+            "    mach.tick_timers();\n",
+            "    Ok(())\n",
+            "}\n",
+            "\n",
+            "fn unchanged(mach: &Machine, start: usize, end: usize) -> bool {\n",
+            "    mach.memory()[start..end] == ROM[start - 0x200..end - 0x200]\n",
+            "}\n",
+        ));
+        for (&start, block) in &self.blocks {
+            writeln!(out).unwrap();
+            self.emit_block(&mut out, start, block);
+        }
+        out
+    }
+
+    fn emit_block(&self, out: &mut String, start: u16, block: &[(u16, Command)]) {
+        writeln!(
+            out,
+            "fn block_{:03x}(mach: &mut Machine) -> Result<(), MachineErr> {{",
+            start
+        )
+        .unwrap();
+        // PC and the cycle count are only brought up to date before a
+        // fallback to `Machine::execute`, which can fault, and at the end.
+        let mut retired = 0;
+        let mut pc = start;
+        let mut last_native = false;
+        for &(addr, command) in block {
+            writeln!(out, "    // 0x{:03x}: {}", addr, command).unwrap();
+            if let Some(body) = self.native(command) {
+                for line in body {
+                    writeln!(out, "    {}", line).unwrap();
+                }
+                retired += 1;
+                last_native = true;
+                continue;
+            }
+            if let Some(lines) = branch(addr, command) {
+                retired += 1;
+                sync(out, &mut retired, None);
+                for line in lines {
+                    writeln!(out, "    {}", line).unwrap();
+                }
+                writeln!(out, "    Ok(())").unwrap();
+                writeln!(out, "}}").unwrap();
+                return;
+            }
+            sync(out, &mut retired, (pc != addr).then_some(addr));
+            writeln!(out, "    mach.execute(Command::{:?})?;", command).unwrap();
+            pc = addr + 2;
+            last_native = false;
+        }
+        // After a fallback the machine already holds the right PC, even when
+        // the instruction was a call or return.
+        if last_native {
+            let next = block.last().map_or(start, |&(addr, _)| addr + 2);
+            sync(out, &mut retired, Some(next));
+        }
+        writeln!(out, "    Ok(())").unwrap();
+        writeln!(out, "}}").unwrap();
+    }
+
+    /// Straight-line code for the instructions that cannot fault.
+    fn native(&self, command: Command) -> Option<Vec<String>> {
+        let quirks = self.platform.quirks();
+        let reg = |reg: Reg| format!("{:?}", reg);
+        let logic = |x: Reg, y: Reg, op: &str| {
+            let mut lines = vec![format!(
+                "mach.set_reg({}, mach.reg({}) {} mach.reg({}));",
+                reg(x),
+                reg(y),
+                op,
+                reg(x)
+            )];
+            if quirks.vf_reset {
+                lines.push("mach.set_reg(VF, 0);".to_string());
+            }
+            lines
+        };
+        let flagged = |x: Reg, result: String, flag: &str| {
+            vec![
+                "{".to_string(),
+                format!("    let (result, overflow) = {};", result),
+                format!("    mach.set_reg({}, result);", reg(x)),
+                format!("    mach.set_reg(VF, {});", flag),
+                "}".to_string(),
+            ]
+        };
+        let shift = |x: Reg, y: Reg, result: &str, flag: &str| {
+            let source = if quirks.shift_uses_vy { y } else { x };
+            vec![
+                "{".to_string(),
+                format!("    let val = mach.reg({});", reg(source)),
+                format!("    mach.set_reg({}, {});", reg(x), result),
+                format!("    mach.set_reg(VF, {});", flag),
+                "}".to_string(),
+            ]
+        };
+        Some(match command {
+            Command::SetVal(x, val) => vec![format!("mach.set_reg({}, 0x{:02x});", reg(x), val)],
+            Command::AddVal(x, val) => vec![format!(
+                "mach.set_reg({}, mach.reg({}).wrapping_add(0x{:02x}));",
+                reg(x),
+                reg(x),
+                val
+            )],
+            Command::SetReg(x, y) => {
+                vec![format!("mach.set_reg({}, mach.reg({}));", reg(x), reg(y))]
+            }
+            Command::BinOR(x, y) => logic(x, y, "|"),
+            Command::BinAND(x, y) => logic(x, y, "&"),
+            Command::LogXOR(x, y) => logic(x, y, "^"),
+            Command::AddReg(x, y) => flagged(
+                x,
+                format!("mach.reg({}).overflowing_add(mach.reg({}))", reg(x), reg(y)),
+                "overflow as u8",
+            ),
+            Command::SubReg(x, y) => flagged(
+                x,
+                format!("mach.reg({}).overflowing_sub(mach.reg({}))", reg(x), reg(y)),
+                "!overflow as u8",
+            ),
+            Command::SubRegRev(x, y) => flagged(
+                x,
+                format!("mach.reg({}).overflowing_sub(mach.reg({}))", reg(y), reg(x)),
+                "!overflow as u8",
+            ),
+            Command::ShiftLeft(x, y) => shift(x, y, "val << 1", "val >> 7"),
+            Command::ShiftRight(x, y) => shift(x, y, "val >> 1", "val & 0x01"),
+            Command::SetIndex(addr) => vec![format!("mach.set_index(0x{:03x});", addr)],
+            Command::AddIndex(x) => vec![
+                "{".to_string(),
+                format!(
+                    "    let index = mach.index().wrapping_add(mach.reg({}) as u16);",
+                    reg(x)
+                ),
+                "    if index & 0xF000 != 0 {".to_string(),
+                "        mach.set_reg(VF, 1);".to_string(),
+                "    }".to_string(),
+                "    mach.set_index(index & 0x0FFF);".to_string(),
+                "}".to_string(),
+            ],
+            Command::SetRegFromDelayTimer(x) => {
+                vec![format!("mach.set_reg({}, mach.delay_timer());", reg(x))]
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// Native code for jumps and skips, which end their block.
+fn branch(addr: u16, command: Command) -> Option<Vec<String>> {
+    let reg = |reg: Reg| format!("{:?}", reg);
+    let condition = match command {
+        Command::Jump(target) => return Some(vec![format!("mach.set_pc(0x{:03x});", target)]),
+        Command::SkipIfRegVal(x, val) => format!("mach.reg({}) == 0x{:02x}", reg(x), val),
+        Command::SkipIfRegValNot(x, val) => format!("mach.reg({}) != 0x{:02x}", reg(x), val),
+        Command::SkipIfRegEqual(x, y) => format!("mach.reg({}) == mach.reg({})", reg(x), reg(y)),
+        Command::SkipIfRegNotEqual(x, y) => {
+            format!("mach.reg({}) != mach.reg({})", reg(x), reg(y))
+        }
+        Command::SkipIfKey(x) => format!("mach.key(Key::from(mach.reg({})))", reg(x)),
+        Command::SkipIfNotKey(x) => format!("!mach.key(Key::from(mach.reg({})))", reg(x)),
+        _ => return None,
+    };
+    Some(vec![
+        format!("if {} {{", condition),
+        format!("    mach.set_pc(0x{:03x});", addr + 4),
+        "} else {".to_string(),
+        format!("    mach.set_pc(0x{:03x});", addr + 2),
+        "}".to_string(),
+    ])
+}
+
+fn sync(out: &mut String, retired: &mut u64, pc: Option<u16>) {
+    if let Some(pc) = pc {
+        writeln!(out, "    mach.set_pc(0x{:03x});", pc).unwrap();
+    }
+    if *retired > 0 {
+        writeln!(out, "    mach.retire({});", retired).unwrap();
+        *retired = 0;
+    }
+}
+
+/// Instructions after which execution never falls through to the next one.
+fn ends_flow(command: Command) -> bool {
+    matches!(
+        command,
+        Command::Jump(_)
+            | Command::Return
+            | Command::JumpWithOffset(..)
+            | Command::ExecuteMachineRoutine(_)
+            | Command::Skip
+    )
+}
+
+/// Instructions that end a block: control transfers, `FX0A`, which repeats
+/// until a key is down, and stores, which may rewrite the code that follows.
+fn ends_block(command: Command) -> bool {
+    ends_flow(command)
+        || matches!(
+            command,
+            Command::Call(_)
+                | Command::SkipIfRegVal(..)
+                | Command::SkipIfRegValNot(..)
+                | Command::SkipIfRegEqual(..)
+                | Command::SkipIfRegNotEqual(..)
+                | Command::SkipIfKey(_)
+                | Command::SkipIfNotKey(_)
+                | Command::GetKey(_)
+                | Command::BCDConv(_)
+                | Command::Store(_)
+                | Command::StoreWithIndexIncrement(_)
+        )
+}
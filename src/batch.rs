@@ -0,0 +1,80 @@
+//! Multi-threaded executor for `MachineBatch`.
+//!
+//! The batch is cut into one run of lanes per thread with
+//! `MachineBatch::chunks_mut`, and each run advances on its own scoped thread.
+//! Lanes share no state, so every thread count gives the same result as
+//! `MachineBatch::run_frame`.
+
+use std::{num::NonZeroUsize, thread};
+
+use crate::machine::{mach, BatchErr, Observation, RandomSource};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Executor {
+    threads: usize,
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Executor {
+    /// One thread per available core.
+    pub fn new() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        }
+    }
+
+    pub fn with_threads(self, threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Like `MachineBatch::run_frame`, with the lanes spread over the threads.
+    pub fn run_frame<'a, R: RandomSource>(
+        &self,
+        batch: &'a mut mach::MachineBatch<R>,
+        keys: &[u16],
+        instructions: usize,
+    ) -> Result<impl ExactSizeIterator<Item = Observation<'a>>, BatchErr> {
+        self.run_frames(batch, keys, instructions, 1)?;
+        Ok(batch.observations())
+    }
+
+    /// Runs `frames` frames with the keypads held, letting each thread run
+    /// all of them without waiting for the others in between.
+    pub fn run_frames<R: RandomSource>(
+        &self,
+        batch: &mut mach::MachineBatch<R>,
+        keys: &[u16],
+        instructions: usize,
+        frames: u64,
+    ) -> Result<(), BatchErr> {
+        if keys.len() != batch.len() {
+            return Err(mach::BatchErr);
+        }
+        let lanes = batch.len().div_ceil(self.threads).max(1);
+        thread::scope(|scope| {
+            let workers: Vec<_> = batch
+                .chunks_mut(lanes)
+                .zip(keys.chunks(lanes))
+                .map(|(mut chunk, keys)| {
+                    scope.spawn(move || {
+                        (0..frames).try_for_each(|_| chunk.run_frame(keys, instructions))
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .try_for_each(|worker| worker.join().unwrap())
+        })
+    }
+}
@@ -0,0 +1,244 @@
+//! Code and data coverage of machine memory.
+//!
+//! Every byte carries a set of flags: executed as code, read as sprite data
+//! by `DXYN`, read by `FX65`, or written by `FX33`/`FX55`. The coverage file
+//! starts with a `chip8-coverage 1 <rom>` line, where `<rom>` is the
+//! [`fnv1a`] hash of the ROM in 16 hex digits, followed by one line per
+//! 64-byte row that has any flags set: the row address in hex, a space, then
+//! one hex digit of flags per byte. Files merge by OR-ing the flags, and only
+//! when they cover the same ROM.
+
+use std::{
+    io::{self, Write},
+    ops::Range,
+};
+
+use crate::machine::{Command, Machine, Reg, MEMORY_SIZE};
+use crate::probe::Probe;
+
+const HEADER: &str = "chip8-coverage 1";
+const ROW_LEN: usize = 64;
+const ROM_START: usize = 0x200;
+
+/// 64-bit FNV-1a hash, used to tell ROMs apart.
+pub fn fnv1a(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    rom: Option<u64>,
+    flags: Vec<u8>,
+    pending_pc: Option<usize>,
+    pending_access: Option<(Range<usize>, u8)>,
+}
+
+#[derive(Debug)]
+pub struct CoverageErr;
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coverage {
+    pub const CODE: u8 = 0b0001;
+    pub const SPRITE: u8 = 0b0010;
+    pub const READ: u8 = 0b0100;
+    pub const WRITE: u8 = 0b1000;
+
+    pub fn new() -> Self {
+        Self {
+            rom: None,
+            flags: vec![0; MEMORY_SIZE],
+            pending_pc: None,
+            pending_access: None,
+        }
+    }
+
+    /// Ties the map to a ROM, so it only merges with maps of the same ROM.
+    pub fn with_rom(mut self, rom: &[u8]) -> Self {
+        self.rom = Some(fnv1a(rom));
+        self
+    }
+
+    pub fn rom(&self) -> Option<u64> {
+        self.rom
+    }
+
+    pub fn flags(&self, addr: u16) -> u8 {
+        self.flags.get(addr as usize).copied().unwrap_or(0)
+    }
+
+    fn mark(&mut self, range: Range<usize>, flag: u8) {
+        for addr in range {
+            self.flags[addr % MEMORY_SIZE] |= flag;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Coverage) -> Result<(), CoverageErr> {
+        if self.rom != other.rom {
+            return Err(CoverageErr);
+        }
+        for (flags, other) in self.flags.iter_mut().zip(&other.flags) {
+            *flags |= other;
+        }
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, CoverageErr> {
+        let mut lines = text.lines();
+        let header = lines.next().unwrap_or_default().trim();
+        let rom = match header.strip_prefix(HEADER) {
+            Some("") => None,
+            Some(rom) => {
+                let rom = rom.strip_prefix(' ').ok_or(CoverageErr)?;
+                Some(u64::from_str_radix(rom, 16).map_err(|_| CoverageErr)?)
+            }
+            None => return Err(CoverageErr),
+        };
+        let mut coverage = Self::new();
+        coverage.rom = rom;
+        for line in lines {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (addr, digits) = line.split_once(' ').ok_or(CoverageErr)?;
+            let addr = usize::from_str_radix(addr, 16).map_err(|_| CoverageErr)?;
+            for (i, digit) in digits.chars().enumerate() {
+                let flags = digit.to_digit(16).ok_or(CoverageErr)? as u8;
+                *coverage.flags.get_mut(addr + i).ok_or(CoverageErr)? |= flags;
+            }
+        }
+        Ok(coverage)
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        match self.rom {
+            Some(rom) => writeln!(out, "{} {:016x}", HEADER, rom)?,
+            None => writeln!(out, "{}", HEADER)?,
+        }
+        for (row, flags) in self.flags.chunks(ROW_LEN).enumerate() {
+            if flags.iter().all(|flags| *flags == 0) {
+                continue;
+            }
+            let digits: String = flags.iter().map(|flags| format!("{:x}", flags)).collect();
+            writeln!(out, "{:03x} {}", row * ROW_LEN, digits)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_listing<W: Write>(&self, rom: &[u8], out: &mut W) -> io::Result<()> {
+        let mut mach = Machine::new();
+        if mach.load(rom).is_err() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "ROM too large"));
+        }
+        let rom_range = ROM_START..ROM_START + rom.len();
+
+        let count = |flag: u8| {
+            self.flags[rom_range.clone()]
+                .iter()
+                .filter(|f| *f & flag != 0)
+                .count()
+        };
+        let untouched = self.flags[rom_range.clone()]
+            .iter()
+            .filter(|f| **f == 0)
+            .count();
+        writeln!(out, "; {} ROM bytes", rom.len())?;
+        writeln!(out, "; code      {:>5}", count(Self::CODE))?;
+        writeln!(out, "; sprite    {:>5}", count(Self::SPRITE))?;
+        writeln!(out, "; read      {:>5}", count(Self::READ))?;
+        writeln!(out, "; written   {:>5}", count(Self::WRITE))?;
+        writeln!(out, "; untouched {:>5}", untouched)?;
+        writeln!(out)?;
+
+        let mut addr = rom_range.start;
+        while addr < rom_range.end {
+            let flags = self.flags[addr];
+            let is_code = flags & Self::CODE != 0 && addr + 1 < rom_range.end;
+            let (bytes, text) = match mach.peek_command(addr as u16) {
+                Ok(command) if is_code => (
+                    format!(
+                        "{:02X} {:02X}",
+                        rom[addr - ROM_START],
+                        rom[addr + 1 - ROM_START]
+                    ),
+                    command.to_string(),
+                ),
+                _ => {
+                    let byte = rom[addr - ROM_START];
+                    (format!("{:02X}", byte), format!("DB {:#010b}", byte))
+                }
+            };
+            writeln!(
+                out,
+                "{:#06x}  {:<5}  {:<20} ; {}",
+                addr,
+                bytes,
+                text,
+                describe(flags)
+            )?;
+            addr += if is_code { 2 } else { 1 };
+        }
+        Ok(())
+    }
+}
+
+fn describe(flags: u8) -> String {
+    let names = [
+        (Coverage::CODE, "code"),
+        (Coverage::SPRITE, "sprite"),
+        (Coverage::READ, "read"),
+        (Coverage::WRITE, "write"),
+    ];
+    let kinds: Vec<&str> = names
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if kinds.is_empty() {
+        "untouched".to_string()
+    } else {
+        kinds.join(", ")
+    }
+}
+
+impl Probe for Coverage {
+    fn before_step(&mut self, mach: &Machine) {
+        let pc = mach.pc() as usize;
+        let index = mach.index() as usize;
+        self.pending_pc = Some(pc);
+        self.pending_access = match mach.peek_command(mach.pc()) {
+            Ok(Command::Display(_, _, rows)) => Some((index..index + rows as usize, Self::SPRITE)),
+            Ok(Command::BCDConv(_)) => Some((index..index + 3, Self::WRITE)),
+            Ok(Command::Store(reg_x)) | Ok(Command::StoreWithIndexIncrement(reg_x)) => {
+                Some((index..index + reg_count(reg_x), Self::WRITE))
+            }
+            Ok(Command::Load(reg_x)) | Ok(Command::LoadWithIndexIncrement(reg_x)) => {
+                Some((index..index + reg_count(reg_x), Self::READ))
+            }
+            _ => None,
+        };
+    }
+
+    fn after_step(&mut self, _mach: &Machine, _command: Command) {
+        if let Some(pc) = self.pending_pc.take() {
+            self.mark(pc..pc + 2, Self::CODE);
+        }
+        if let Some((range, flag)) = self.pending_access.take() {
+            self.mark(range, flag);
+        }
+    }
+}
+
+fn reg_count(reg_x: Reg) -> usize {
+    reg_x as usize + 1
+}
@@ -0,0 +1,551 @@
+//! Debug Adapter Protocol server, so editors can launch and debug ROMs.
+//!
+//! The `launch` request takes `program` (ROM path), an optional `sourceMap`
+//! (see [`SourceMap`]), `stopOnEntry` and `platform` (`chip8`, `schip` or
+//! `xochip`, as with `--platform`; `chip8` by default).
+
+mod protocol;
+mod source_map;
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, TryRecvError},
+    thread,
+};
+
+use serde_json::{json, Value};
+
+use crate::machine::{Machine, Platform, Reg, INSTRUCTIONS_PER_FRAME, MEMORY_SIZE};
+use crate::rewind::Rewind;
+
+pub use self::source_map::{SourceMap, SourceMapErr};
+use self::source_map::parse_addr;
+
+const THREAD_ID: u64 = 1;
+const STEPS_PER_SLICE: usize = 10_000;
+const REWIND_SECONDS: u32 = 10;
+const MAX_DISASSEMBLY: i64 = MEMORY_SIZE as i64 / 2;
+
+const REGISTERS_REF: u64 = 1;
+const SPECIAL_REF: u64 = 2;
+const STACK_REF: u64 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RunMode {
+    Stopped,
+    Continue,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+    StepBack,
+    ReverseContinue,
+}
+
+struct Session<W: Write> {
+    out: W,
+    seq: u64,
+    mach: Machine,
+    rewind: Rewind,
+    source_map: SourceMap,
+    line_breakpoints: HashMap<PathBuf, Vec<u16>>,
+    instruction_breakpoints: Vec<u16>,
+    stop_on_entry: bool,
+    mode: RunMode,
+    finished: bool,
+}
+
+pub fn serve_stdio() -> io::Result<()> {
+    serve(io::stdin(), io::stdout())
+}
+
+pub fn serve_tcp(port: u16) -> io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    let (stream, _) = listener.accept()?;
+    serve(stream.try_clone()?, stream)
+}
+
+pub fn serve<R, W>(input: R, output: W) -> io::Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write,
+{
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut reader = BufReader::new(input);
+        while let Ok(Some(message)) = protocol::read_message(&mut reader) {
+            if tx.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session::new(output);
+    while !session.finished {
+        let message = if session.is_running() {
+            match rx.try_recv() {
+                Ok(message) => Some(message),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match rx.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            }
+        };
+        if let Some(message) = message {
+            session.handle(&message)?;
+        }
+        if session.is_running() {
+            session.run_slice()?;
+        }
+    }
+    Ok(())
+}
+
+impl<W: Write> Session<W> {
+    fn new(out: W) -> Self {
+        Self {
+            out,
+            seq: 0,
+            mach: Machine::new(),
+            rewind: Rewind::new(REWIND_SECONDS),
+            source_map: SourceMap::new(),
+            line_breakpoints: HashMap::new(),
+            instruction_breakpoints: Vec::new(),
+            stop_on_entry: false,
+            mode: RunMode::Stopped,
+            finished: false,
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.mode != RunMode::Stopped
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        protocol::write_message(&mut self.out, &message)
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    fn respond_err(&mut self, request: &Value, message: &str) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+
+    fn stop(&mut self, reason: &str, description: Option<String>) -> io::Result<()> {
+        self.mode = RunMode::Stopped;
+        self.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": description,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        )
+    }
+
+    fn is_breakpoint(&self, addr: u16) -> bool {
+        self.instruction_breakpoints.contains(&addr)
+            || self
+                .line_breakpoints
+                .values()
+                .any(|addrs| addrs.contains(&addr))
+    }
+
+    fn run_slice(&mut self) -> io::Result<()> {
+        if matches!(self.mode, RunMode::StepBack | RunMode::ReverseContinue) {
+            return self.reverse_slice();
+        }
+        for _ in 0..STEPS_PER_SLICE {
+            let pc = self.mach.pc();
+            if self.mach.step().is_err() {
+                let description = format!("invalid instruction at {:#06x}", pc);
+                self.event("output", json!({ "output": format!("{}\n", description) }))?;
+                return self.stop("exception", Some(description));
+            }
+            if self.mach.cycles().is_multiple_of(INSTRUCTIONS_PER_FRAME as u64) {
+                self.mach.tick_timers();
+                self.rewind.push(&self.mach);
+            }
+
+            let depth = self.mach.stack().len();
+            let reason = if self.is_breakpoint(self.mach.pc()) {
+                Some("breakpoint")
+            } else {
+                match self.mode {
+                    RunMode::StepIn => Some("step"),
+                    RunMode::StepOver(from) if depth <= from => Some("step"),
+                    RunMode::StepOut(from) if depth < from => Some("step"),
+                    _ => None,
+                }
+            };
+            if let Some(reason) = reason {
+                return self.stop(reason, None);
+            }
+        }
+        Ok(())
+    }
+
+    fn reverse_slice(&mut self) -> io::Result<()> {
+        for _ in 0..STEPS_PER_SLICE {
+            if !self.rewind.step_back_instruction(&mut self.mach) {
+                return self.stop("step", Some("reached the start of the rewind buffer".into()));
+            }
+            if self.mode == RunMode::StepBack {
+                return self.stop("step", None);
+            }
+            if self.is_breakpoint(self.mach.pc()) {
+                return self.stop("breakpoint", None);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, request: &Value) -> io::Result<()> {
+        if request["type"] != "request" {
+            return Ok(());
+        }
+        let args = &request["arguments"];
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => self.respond(
+                request,
+                json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsDisassembleRequest": true,
+                    "supportsReadMemoryRequest": true,
+                    "supportsInstructionBreakpoints": true,
+                    "supportsSteppingGranularity": true,
+                    "supportsStepBack": true,
+                    "supportsTerminateRequest": true,
+                }),
+            ),
+            "launch" => match self.launch(args) {
+                Ok(()) => {
+                    self.respond(request, json!({}))?;
+                    self.event("initialized", json!({}))
+                }
+                Err(message) => self.respond_err(request, &message),
+            },
+            "setBreakpoints" => {
+                let body = self.set_breakpoints(args);
+                self.respond(request, body)
+            }
+            "setInstructionBreakpoints" => {
+                let body = self.set_instruction_breakpoints(args);
+                self.respond(request, body)
+            }
+            "setExceptionBreakpoints" => self.respond(request, json!({ "breakpoints": [] })),
+            "configurationDone" => {
+                self.respond(request, json!({}))?;
+                if self.stop_on_entry {
+                    self.stop("entry", None)
+                } else {
+                    self.mode = RunMode::Continue;
+                    Ok(())
+                }
+            }
+            "threads" => self.respond(
+                request,
+                json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] }),
+            ),
+            "stackTrace" => {
+                let body = self.stack_trace();
+                self.respond(request, body)
+            }
+            "scopes" => self.respond(
+                request,
+                json!({
+                    "scopes": [
+                        { "name": "Registers", "variablesReference": REGISTERS_REF, "expensive": false },
+                        { "name": "Index & Timers", "variablesReference": SPECIAL_REF, "expensive": false },
+                        { "name": "Stack", "variablesReference": STACK_REF, "expensive": false },
+                    ]
+                }),
+            ),
+            "variables" => {
+                let body = self.variables(args["variablesReference"].as_u64().unwrap_or(0));
+                self.respond(request, body)
+            }
+            "readMemory" => match self.read_memory(args) {
+                Some(body) => self.respond(request, body),
+                None => self.respond_err(request, "invalid memory reference"),
+            },
+            "disassemble" => match self.disassemble(args) {
+                Some(body) => self.respond(request, body),
+                None => self.respond_err(request, "invalid memory reference"),
+            },
+            "continue" => {
+                self.mode = RunMode::Continue;
+                self.respond(request, json!({ "allThreadsContinued": true }))
+            }
+            "next" => {
+                self.mode = RunMode::StepOver(self.mach.stack().len());
+                self.respond(request, json!({}))
+            }
+            "stepIn" => {
+                self.mode = RunMode::StepIn;
+                self.respond(request, json!({}))
+            }
+            "stepOut" => {
+                let depth = self.mach.stack().len();
+                self.mode = if depth == 0 {
+                    RunMode::Continue
+                } else {
+                    RunMode::StepOut(depth)
+                };
+                self.respond(request, json!({}))
+            }
+            "stepBack" => {
+                self.mode = RunMode::StepBack;
+                self.respond(request, json!({}))
+            }
+            "reverseContinue" => {
+                self.mode = RunMode::ReverseContinue;
+                self.respond(request, json!({}))
+            }
+            "pause" => {
+                self.respond(request, json!({}))?;
+                if self.is_running() {
+                    self.stop("pause", None)?;
+                }
+                Ok(())
+            }
+            "terminate" => {
+                self.respond(request, json!({}))?;
+                self.finished = true;
+                self.event("terminated", json!({}))
+            }
+            "disconnect" => {
+                self.finished = true;
+                self.respond(request, json!({}))
+            }
+            command => self.respond_err(request, &format!("unsupported request '{}'", command)),
+        }
+    }
+
+    fn launch(&mut self, args: &Value) -> Result<(), String> {
+        let program = args["program"]
+            .as_str()
+            .ok_or("launch requires a 'program' path")?;
+        let platform = match args["platform"].as_str() {
+            Some(name) => name
+                .parse()
+                .map_err(|_| format!("unknown platform '{}'", name))?,
+            None => Platform::Chip8,
+        };
+        let rom = fs::read(program).map_err(|err| format!("cannot read {}: {}", program, err))?;
+        let mut mach = Machine::with_quirks(platform.quirks());
+        mach.load(&rom)
+            .map_err(|_| format!("{} does not fit in memory", program))?;
+
+        if let Some(path) = args["sourceMap"].as_str() {
+            self.source_map = SourceMap::load(Path::new(path))
+                .map_err(|_| format!("invalid source map {}", path))?;
+        }
+        self.mach = mach;
+        self.rewind.clear();
+        self.rewind.push(&self.mach);
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+        Ok(())
+    }
+
+    fn set_breakpoints(&mut self, args: &Value) -> Value {
+        let path = PathBuf::from(args["source"]["path"].as_str().unwrap_or_default());
+        let mut addrs = Vec::new();
+        let mut breakpoints = Vec::new();
+        for bp in args["breakpoints"].as_array().into_iter().flatten() {
+            let line = bp["line"].as_u64().unwrap_or(0);
+            match self.source_map.lookup_line(&path, line) {
+                Some(addr) => {
+                    addrs.push(addr);
+                    breakpoints.push(json!({
+                        "verified": true,
+                        "line": line,
+                        "instructionReference": format!("{:#06x}", addr),
+                    }));
+                }
+                None => breakpoints.push(json!({
+                    "verified": false,
+                    "line": line,
+                    "message": "no code at this line",
+                })),
+            }
+        }
+        self.line_breakpoints.insert(path, addrs);
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn set_instruction_breakpoints(&mut self, args: &Value) -> Value {
+        self.instruction_breakpoints.clear();
+        let mut breakpoints = Vec::new();
+        for bp in args["breakpoints"].as_array().into_iter().flatten() {
+            let addr = bp["instructionReference"]
+                .as_str()
+                .and_then(parse_addr)
+                .map(|addr| addr as i64 + bp["offset"].as_i64().unwrap_or(0))
+                .and_then(|addr| u16::try_from(addr).ok());
+            match addr {
+                Some(addr) => {
+                    self.instruction_breakpoints.push(addr);
+                    breakpoints.push(json!({
+                        "verified": true,
+                        "instructionReference": format!("{:#06x}", addr),
+                    }));
+                }
+                None => breakpoints.push(json!({ "verified": false })),
+            }
+        }
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn frame(&self, id: usize, addr: u16) -> Value {
+        let name = match self.mach.peek_command(addr) {
+            Ok(command) => format!("{:#06x}: {}", addr, command),
+            Err(_) => format!("{:#06x}", addr),
+        };
+        let mut frame = json!({
+            "id": id,
+            "name": name,
+            "line": 0,
+            "column": 0,
+            "instructionPointerReference": format!("{:#06x}", addr),
+        });
+        if let Some((path, line)) = self.source_map.lookup_addr(addr) {
+            frame["source"] = json!({ "path": path });
+            frame["line"] = json!(line);
+            frame["column"] = json!(1);
+        }
+        frame
+    }
+
+    fn stack_trace(&self) -> Value {
+        let mut frames = vec![self.frame(0, self.mach.pc())];
+        for (id, ret) in self.mach.stack().iter().rev().enumerate() {
+            frames.push(self.frame(id + 1, ret.wrapping_sub(2)));
+        }
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    fn variables(&self, reference: u64) -> Value {
+        let variables: Vec<Value> = match reference {
+            REGISTERS_REF => enum_iterator::all::<Reg>()
+                .map(|reg| variable(&reg.to_string(), format!("{:#04x}", self.mach.reg(reg))))
+                .collect(),
+            SPECIAL_REF => {
+                let mut index = variable("I", format!("{:#06x}", self.mach.index()));
+                index["memoryReference"] = json!(format!("{:#06x}", self.mach.index()));
+                vec![
+                    variable("PC", format!("{:#06x}", self.mach.pc())),
+                    index,
+                    variable("DT", format!("{:#04x}", self.mach.delay_timer())),
+                    variable("ST", format!("{:#04x}", self.mach.sound_timer())),
+                ]
+            }
+            STACK_REF => self
+                .mach
+                .stack()
+                .iter()
+                .enumerate()
+                .map(|(i, addr)| variable(&format!("[{}]", i), format!("{:#06x}", addr)))
+                .collect(),
+            _ => Vec::new(),
+        };
+        json!({ "variables": variables })
+    }
+
+    fn read_memory(&self, args: &Value) -> Option<Value> {
+        let base = parse_addr(args["memoryReference"].as_str()?)? as i64;
+        let start = base.checked_add(args["offset"].as_i64().unwrap_or(0))?;
+        let count = args["count"].as_u64().unwrap_or(0).min(MEMORY_SIZE as u64) as i64;
+        let memory = self.mach.memory();
+        let lo = start.clamp(0, memory.len() as i64) as usize;
+        let hi = start.saturating_add(count).clamp(0, memory.len() as i64) as usize;
+        let data = &memory[lo..hi.max(lo)];
+        Some(json!({
+            "address": format!("{:#06x}", start.max(0)),
+            "data": protocol::encode_base64(data),
+            "unreadableBytes": count - data.len() as i64,
+        }))
+    }
+
+    fn disassemble(&self, args: &Value) -> Option<Value> {
+        let base = parse_addr(args["memoryReference"].as_str()?)? as i64;
+        let start = args["instructionOffset"]
+            .as_i64()
+            .unwrap_or(0)
+            .checked_mul(2)?
+            .checked_add(args["offset"].as_i64().unwrap_or(0))?
+            .checked_add(base)?;
+        // Clients ask for a window around an address; more than a memory's
+        // worth of instructions is never useful.
+        let count = args["instructionCount"]
+            .as_i64()
+            .unwrap_or(0)
+            .clamp(0, MAX_DISASSEMBLY);
+        let memory = self.mach.memory();
+
+        let mut instructions = Vec::new();
+        for i in 0..count {
+            let addr = start.checked_add(i * 2)?;
+            if addr < 0 || addr + 2 > memory.len() as i64 {
+                instructions.push(json!({
+                    "address": format!("{:#06x}", addr.max(0)),
+                    "instruction": "??",
+                    "presentationHint": "invalid",
+                }));
+                continue;
+            }
+            let addr = addr as u16;
+            let bytes = &memory[addr as usize..addr as usize + 2];
+            let text = match self.mach.peek_command(addr) {
+                Ok(command) => command.to_string(),
+                Err(_) => format!("DW {:#06x}", u16::from_be_bytes([bytes[0], bytes[1]])),
+            };
+            let mut instruction = json!({
+                "address": format!("{:#06x}", addr),
+                "instructionBytes": format!("{:02X} {:02X}", bytes[0], bytes[1]),
+                "instruction": text,
+            });
+            if let Some((path, line)) = self.source_map.lookup_addr(addr) {
+                instruction["location"] = json!({ "path": path });
+                instruction["line"] = json!(line);
+            }
+            instructions.push(instruction);
+        }
+        Some(json!({ "instructions": instructions }))
+    }
+}
+
+fn variable(name: &str, value: String) -> Value {
+    json!({ "name": name, "value": value, "variablesReference": 0 })
+}
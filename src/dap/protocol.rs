@@ -0,0 +1,76 @@
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+const CONTENT_LENGTH: &str = "Content-Length:";
+// Longer bodies are refused instead of allocated.
+const MAX_CONTENT_LENGTH: usize = 16 * 1024 * 1024;
+
+/// Reads the next message. Bodies that are not JSON are logged to stderr and
+/// skipped.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    loop {
+        let Some(len) = read_header(reader)? else {
+            return Ok(None);
+        };
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body)?;
+        match serde_json::from_slice(&body) {
+            Ok(message) => return Ok(Some(message)),
+            Err(err) => eprintln!("dap: skipping a message that is not JSON: {}", err),
+        }
+    }
+}
+
+// Returns the Content-Length of the next message, or `None` at end of input.
+fn read_header<R: BufRead>(reader: &mut R) -> io::Result<Option<usize>> {
+    let mut content_length: Option<usize> = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                return Ok(content_length);
+            }
+            continue;
+        }
+        if let Some(len) = line.strip_prefix(CONTENT_LENGTH) {
+            let len = len
+                .trim()
+                .parse()
+                .ok()
+                .filter(|len| *len <= MAX_CONTENT_LENGTH)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad Content-Length"))?;
+            content_length = Some(len);
+        }
+    }
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "{} {}\r\n\r\n{}", CONTENT_LENGTH, body.len(), body)?;
+    writer.flush()
+}
+
+pub fn encode_base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let sextet = (triple >> (18 - 6 * i)) & 0x3F;
+                encoded.push(TABLE[sextet as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,83 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone)]
+struct Entry {
+    addr: u16,
+    path: PathBuf,
+    line: u64,
+}
+
+/// Maps ROM addresses to lines of the source the ROM was assembled from.
+///
+/// The file is plain text with one entry per line, `<address> <path>:<line>`,
+/// e.g. `0x200 game.8o:12`. Blank lines and lines starting with `#` are
+/// ignored. Relative paths are resolved against the directory of the map.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+pub struct SourceMapErr;
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SourceMapErr> {
+        let text = fs::read_to_string(path).map_err(|_| SourceMapErr)?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        Self::parse(&text, base)
+    }
+
+    pub fn parse(text: &str, base: &Path) -> Result<Self, SourceMapErr> {
+        let mut entries = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (addr, location) = line.split_once(char::is_whitespace).ok_or(SourceMapErr)?;
+            let (path, line) = location.trim().rsplit_once(':').ok_or(SourceMapErr)?;
+            entries.push(Entry {
+                addr: parse_addr(addr).ok_or(SourceMapErr)?,
+                path: base.join(path),
+                line: line.parse().map_err(|_| SourceMapErr)?,
+            });
+        }
+        entries.sort_by_key(|entry| entry.addr);
+        Ok(Self { entries })
+    }
+
+    pub fn lookup_addr(&self, addr: u16) -> Option<(&Path, u64)> {
+        self.entries
+            .iter()
+            .find(|entry| entry.addr == addr)
+            .map(|entry| (entry.path.as_path(), entry.line))
+    }
+
+    pub fn lookup_line(&self, path: &Path, line: u64) -> Option<u16> {
+        self.entries
+            .iter()
+            .find(|entry| entry.line == line && same_file(&entry.path, path))
+            .map(|entry| entry.addr)
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    a == b || a.ends_with(b) || b.ends_with(a)
+}
+
+pub fn parse_addr(text: &str) -> Option<u16> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
@@ -0,0 +1,483 @@
+//! Differential execution.
+//!
+//! [`Lockstep`] runs two machines on the same ROM and input one instruction
+//! at a time and stops after the first instruction that leaves their PC,
+//! registers, index, stack, memory or framebuffer different. The right machine
+//! mirrors the left one's keypad, so input only has to be fed to the left.
+//!
+//! [`TraceLockstep`] checks a machine against a reference trace written by
+//! [`Tracer`] instead, with the trace on the right. A trace only records the
+//! PC, opcode and changed registers, so only those are compared, and it has to
+//! start at the first instruction (no `--trace-range`).
+
+use std::{collections::VecDeque, fmt, io};
+
+use enum_iterator::all;
+
+use crate::machine::{Command, Key, Machine, MachineErr, Reg, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::probe;
+use crate::trace::Tracer;
+
+const DEFAULT_CONTEXT: usize = 8;
+
+/// Keeps the last `max` lines written to it.
+#[derive(Debug)]
+struct Context {
+    lines: VecDeque<String>,
+    partial: Vec<u8>,
+    max: usize,
+}
+
+impl Context {
+    fn new(max: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(max),
+            partial: Vec::new(),
+            max,
+        }
+    }
+}
+
+impl io::Write for Context {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for byte in buf {
+            if *byte != b'\n' {
+                self.partial.push(*byte);
+                continue;
+            }
+            if self.max > 0 {
+                if self.lines.len() == self.max {
+                    self.lines.pop_front();
+                }
+                let line = String::from_utf8_lossy(&self.partial).into_owned();
+                self.lines.push_back(line);
+            }
+            self.partial.clear();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Instruction {
+    pub cycle: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub command: Command,
+}
+
+/// A difference between the two sides, left value first.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    Pc(u16, u16),
+    Opcode(u16, u16),
+    Reg(Reg, u8, u8),
+    Index(u16, u16),
+    Stack(Vec<u16>, Vec<u16>),
+    /// The first differing address and the number of differing bytes.
+    Memory {
+        addr: u16,
+        left: u8,
+        right: u8,
+        count: usize,
+    },
+    /// The first differing pixel and the number of differing pixels.
+    Display {
+        x: usize,
+        y: usize,
+        count: usize,
+    },
+    /// Which side faulted.
+    Fault(bool, bool),
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Pc(left, right) => write!(f, "PC {:04X} | {:04X}", left, right),
+            Field::Opcode(left, right) => write!(f, "opcode {:04X} | {:04X}", left, right),
+            Field::Reg(reg, left, right) => write!(f, "{} {:02X} | {:02X}", reg, left, right),
+            Field::Index(left, right) => write!(f, "I {:04X} | {:04X}", left, right),
+            Field::Stack(left, right) => write!(f, "stack {:04X?} | {:04X?}", left, right),
+            Field::Memory {
+                addr,
+                left,
+                right,
+                count,
+            } => write!(
+                f,
+                "memory {:04X} {:02X} | {:02X} ({} bytes differ)",
+                addr, left, right, count
+            ),
+            Field::Display { x, y, count } => {
+                write!(f, "display ({}, {}) ({} pixels differ)", x, y, count)
+            }
+            Field::Fault(left, right) => write!(f, "fault {} | {}", left, right),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// The instruction after which the sides differ, or `None` if they
+    /// already differed before the first one.
+    pub instruction: Option<Instruction>,
+    pub fields: Vec<Field>,
+    /// The last trace lines of the left side, oldest first.
+    pub context: Vec<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.instruction {
+            Some(ins) => writeln!(
+                f,
+                "diverged after {} {:04X} {:04X} {}",
+                ins.cycle, ins.pc, ins.opcode, ins.command
+            )?,
+            None => writeln!(f, "diverged before the first instruction")?,
+        }
+        for field in &self.fields {
+            writeln!(f, "  {}", field)?;
+        }
+        if !self.context.is_empty() {
+            writeln!(f, "context:")?;
+            for line in &self.context {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum DiffErr {
+    Parse(usize),
+    Diverged(Divergence),
+    /// Both sides faulted on the same instruction.
+    Fault(MachineErr),
+}
+
+fn compare(left: &Machine, right: &Machine) -> Vec<Field> {
+    let mut fields = Vec::new();
+    if left.pc() != right.pc() {
+        fields.push(Field::Pc(left.pc(), right.pc()));
+    }
+    for reg in all::<Reg>() {
+        if left.reg(reg) != right.reg(reg) {
+            fields.push(Field::Reg(reg, left.reg(reg), right.reg(reg)));
+        }
+    }
+    if left.index() != right.index() {
+        fields.push(Field::Index(left.index(), right.index()));
+    }
+    if left.stack() != right.stack() {
+        fields.push(Field::Stack(left.stack().to_vec(), right.stack().to_vec()));
+    }
+    let mut memory = left
+        .memory()
+        .iter()
+        .zip(right.memory())
+        .enumerate()
+        .filter(|(_, (left, right))| left != right);
+    if let Some((addr, (l, r))) = memory.next() {
+        fields.push(Field::Memory {
+            addr: addr as u16,
+            left: *l,
+            right: *r,
+            count: memory.count() + 1,
+        });
+    }
+    let mut pixels = (0..DISPLAY_HEIGHT)
+        .flat_map(|y| (0..DISPLAY_WIDTH).map(move |x| (x, y)))
+        .filter(|(x, y)| left.display().pixel(*x, *y).ok() != right.display().pixel(*x, *y).ok());
+    if let Some((x, y)) = pixels.next() {
+        fields.push(Field::Display {
+            x,
+            y,
+            count: pixels.count() + 1,
+        });
+    }
+    fields
+}
+
+fn diverged(
+    instruction: Option<Instruction>,
+    fields: Vec<Field>,
+    tracer: &Tracer<Context>,
+) -> DiffErr {
+    DiffErr::Diverged(Divergence {
+        instruction,
+        fields,
+        context: tracer.get_ref().lines.iter().cloned().collect(),
+    })
+}
+
+/// Runs two machines side by side.
+#[derive(Debug)]
+pub struct Lockstep {
+    left: Machine,
+    right: Machine,
+    tracer: Tracer<Context>,
+}
+
+impl Lockstep {
+    pub fn new(left: Machine, right: Machine) -> Self {
+        Self {
+            left,
+            right,
+            tracer: Tracer::new(Context::new(DEFAULT_CONTEXT)),
+        }
+    }
+
+    /// Sets how many trace lines of context a divergence report carries.
+    pub fn with_context(mut self, lines: usize) -> Self {
+        self.tracer = Tracer::new(Context::new(lines));
+        self
+    }
+
+    pub fn left(&self) -> &Machine {
+        &self.left
+    }
+
+    pub fn right(&self) -> &Machine {
+        &self.right
+    }
+
+    /// The left machine, for feeding input.
+    pub fn left_mut(&mut self) -> &mut Machine {
+        &mut self.left
+    }
+
+    pub fn step(&mut self) -> Result<Command, DiffErr> {
+        for key in all::<Key>() {
+            self.right.set_key(key, self.left.key(key));
+        }
+        let cycle = self.left.cycles();
+        let pc = self.left.pc();
+        let opcode = self.left.peek_opcode(pc).map_err(DiffErr::Fault)?;
+        let left = probe::step(&mut self.left, &mut [&mut self.tracer]);
+        let right = self.right.step();
+        let (command, fields) = match (left, right) {
+            (Ok(command), Ok(_)) => (command, compare(&self.left, &self.right)),
+            (Err(err), Err(_)) => return Err(DiffErr::Fault(err)),
+            (Ok(command), Err(_)) => (command, vec![Field::Fault(false, true)]),
+            (Err(_), Ok(command)) => (command, vec![Field::Fault(true, false)]),
+        };
+        if fields.is_empty() {
+            return Ok(command);
+        }
+        let instruction = Instruction {
+            cycle,
+            pc,
+            opcode,
+            command,
+        };
+        Err(diverged(Some(instruction), fields, &self.tracer))
+    }
+
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), DiffErr> {
+        for _ in 0..instructions {
+            self.step()?;
+        }
+        self.tick_timers();
+        Ok(())
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.left.tick_timers();
+        self.right.tick_timers();
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    pc: u16,
+    opcode: u16,
+    regs: Vec<(Reg, u8)>,
+    index: Option<u16>,
+}
+
+/// A parsed execution trace.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    entries: Vec<Entry>,
+}
+
+impl Reference {
+    pub fn parse(text: &str) -> Result<Self, DiffErr> {
+        let mut entries = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = Self::parse_line(line).ok_or(DiffErr::Parse(i + 1))?;
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    fn parse_line(line: &str) -> Option<Entry> {
+        let (head, changes) = line.split_once(" ;")?;
+        let mut words = head.split_whitespace();
+        words.next()?.parse::<u64>().ok()?;
+        let pc = u16::from_str_radix(words.next()?, 16).ok()?;
+        let opcode = u16::from_str_radix(words.next()?, 16).ok()?;
+        let mut entry = Entry {
+            pc,
+            opcode,
+            regs: Vec::new(),
+            index: None,
+        };
+        for change in changes.split_whitespace() {
+            match change.split_once('=')? {
+                ("I", val) => entry.index = Some(u16::from_str_radix(val, 16).ok()?),
+                (reg, val) => {
+                    let reg = u8::from_str_radix(reg.strip_prefix('V')?, 16).ok()?;
+                    let val = u8::from_str_radix(val, 16).ok()?;
+                    entry.regs.push((Reg::from(reg), val));
+                }
+            }
+        }
+        Some(entry)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Runs a machine against a reference trace.
+#[derive(Debug)]
+pub struct TraceLockstep {
+    mach: Machine,
+    reference: Reference,
+    next: usize,
+    last: Option<Instruction>,
+    tracer: Tracer<Context>,
+}
+
+impl TraceLockstep {
+    pub fn new(mach: Machine, reference: Reference) -> Self {
+        Self {
+            mach,
+            reference,
+            next: 0,
+            last: None,
+            tracer: Tracer::new(Context::new(DEFAULT_CONTEXT)),
+        }
+    }
+
+    /// Sets how many trace lines of context a divergence report carries.
+    pub fn with_context(mut self, lines: usize) -> Self {
+        self.tracer = Tracer::new(Context::new(lines));
+        self
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.mach
+    }
+
+    /// The machine, for feeding input.
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.mach
+    }
+
+    /// Whether every instruction in the reference has been checked.
+    pub fn is_finished(&self) -> bool {
+        self.next == self.reference.entries.len()
+    }
+
+    /// Executes one instruction. Past the end of the reference the machine
+    /// runs unchecked.
+    pub fn step(&mut self) -> Result<Command, DiffErr> {
+        let Some(entry) = self.reference.entries.get(self.next) else {
+            return self.mach.step().map_err(DiffErr::Fault);
+        };
+        let cycle = self.mach.cycles();
+        let pc = self.mach.pc();
+        let opcode = self.mach.peek_opcode(pc).map_err(DiffErr::Fault)?;
+        if pc != entry.pc {
+            return Err(diverged(
+                self.last,
+                vec![Field::Pc(pc, entry.pc)],
+                &self.tracer,
+            ));
+        }
+        if opcode != entry.opcode {
+            return Err(diverged(
+                self.last,
+                vec![Field::Opcode(opcode, entry.opcode)],
+                &self.tracer,
+            ));
+        }
+
+        let before: Vec<u8> = all::<Reg>().map(|reg| self.mach.reg(reg)).collect();
+        let index = self.mach.index();
+        let command = match probe::step(&mut self.mach, &mut [&mut self.tracer]) {
+            Ok(command) => command,
+            Err(err) => {
+                let command = self
+                    .mach
+                    .peek_command(pc)
+                    .map_err(|_| DiffErr::Fault(err))?;
+                let instruction = Instruction {
+                    cycle,
+                    pc,
+                    opcode,
+                    command,
+                };
+                let fields = vec![Field::Fault(true, false)];
+                return Err(diverged(Some(instruction), fields, &self.tracer));
+            }
+        };
+        let instruction = Instruction {
+            cycle,
+            pc,
+            opcode,
+            command,
+        };
+
+        let mut fields = Vec::new();
+        for (reg, before) in all::<Reg>().zip(before) {
+            let expected = entry
+                .regs
+                .iter()
+                .find(|(changed, _)| *changed == reg)
+                .map_or(before, |(_, val)| *val);
+            if self.mach.reg(reg) != expected {
+                fields.push(Field::Reg(reg, self.mach.reg(reg), expected));
+            }
+        }
+        let expected = entry.index.unwrap_or(index);
+        if self.mach.index() != expected {
+            fields.push(Field::Index(self.mach.index(), expected));
+        }
+        if !fields.is_empty() {
+            return Err(diverged(Some(instruction), fields, &self.tracer));
+        }
+        self.last = Some(instruction);
+        self.next += 1;
+        Ok(command)
+    }
+
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), DiffErr> {
+        for _ in 0..instructions {
+            self.step()?;
+        }
+        self.tick_timers();
+        Ok(())
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.mach.tick_timers();
+    }
+}
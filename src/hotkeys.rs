@@ -0,0 +1,205 @@
+//! Save-slot hotkeys for the terminal frontend.
+//!
+//! When stdin is a terminal, the frontend switches it out of line mode so it
+//! sees function keys as they are pressed. F1-F9 save to slots 1-9 and
+//! Shift+F1-F9 load from them; everything else is collected into lines and
+//! handled as typed commands, as it is when stdin is a pipe. Ctrl+C quits.
+//!
+//! `Keyboard` turns the raw bytes into [`Input`]s. It understands the xterm,
+//! VT220 and Linux console encodings of F1-F9, and xterm's Shift+F1-F9.
+
+const ESC: u8 = 0x1B;
+const CTRL_C: u8 = 0x03;
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7F;
+
+/// The longest escape sequence kept; longer ones are dropped unread.
+const MAX_ESCAPE_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hotkey {
+    Save(u8),
+    Load(u8),
+}
+
+impl Hotkey {
+    /// The typed command the hotkey stands for.
+    pub fn command(self) -> String {
+        match self {
+            Hotkey::Save(slot) => format!("save {}", slot),
+            Hotkey::Load(slot) => format!("load {}", slot),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    /// A finished line of typed text.
+    Line(String),
+    Hotkey(Hotkey),
+    /// Text to write back so the user sees what they type.
+    Echo(String),
+    Interrupt,
+}
+
+#[derive(Debug, Default)]
+pub struct Keyboard {
+    line: String,
+    escape: Option<Vec<u8>>,
+}
+
+impl Keyboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, byte: u8) -> Option<Input> {
+        if let Some(escape) = self.escape.as_mut() {
+            escape.push(byte);
+            if !escape_is_complete(escape) {
+                if escape.len() >= MAX_ESCAPE_LEN {
+                    self.escape = None;
+                }
+                return None;
+            }
+            let escape = self.escape.take().unwrap();
+            return function_key(&escape).map(Input::Hotkey);
+        }
+        match byte {
+            ESC => {
+                self.escape = Some(Vec::new());
+                None
+            }
+            CTRL_C => Some(Input::Interrupt),
+            b'\r' | b'\n' => Some(Input::Line(std::mem::take(&mut self.line))),
+            BACKSPACE | DELETE => self
+                .line
+                .pop()
+                .map(|_| Input::Echo("\u{8} \u{8}".to_string())),
+            byte if byte.is_ascii_graphic() || byte == b' ' => {
+                self.line.push(byte as char);
+                Some(Input::Echo((byte as char).to_string()))
+            }
+            _ => None,
+        }
+    }
+}
+
+// `escape` is what followed ESC: `O` or `[` and then the sequence's
+// parameters and final byte, or `[[` and a letter on the Linux console.
+fn escape_is_complete(escape: &[u8]) -> bool {
+    match escape {
+        [] => false,
+        [b'O', rest @ ..] | [b'[', b'[', rest @ ..] => {
+            rest.last().is_some_and(u8::is_ascii_alphabetic)
+        }
+        [b'[', rest @ ..] => rest.last().is_some_and(|byte| (0x40..=0x7E).contains(byte)),
+        _ => true,
+    }
+}
+
+fn function_key(escape: &[u8]) -> Option<Hotkey> {
+    let (number, shift) = match escape {
+        // xterm F1-F4, and Shift+F1-F4 as `ESC O 2 P` on some terminals.
+        [b'O', key @ b'P'..=b'S'] => (key - b'P' + 1, false),
+        [b'O', b'2', key @ b'P'..=b'S'] => (key - b'P' + 1, true),
+        // Linux console F1-F5.
+        [b'[', b'[', key @ b'A'..=b'E'] => (key - b'A' + 1, false),
+        // xterm Shift+F1-F4.
+        [b'[', b'1', b';', b'2', key @ b'P'..=b'S'] => (key - b'P' + 1, true),
+        // F1-F10 as `ESC [ <code> ~`, with `;2` for Shift.
+        [b'[', params @ .., b'~'] => {
+            let text = std::str::from_utf8(params).ok()?;
+            let (code, modifier) = match text.split_once(';') {
+                Some((code, modifier)) => (code, Some(modifier)),
+                None => (text, None),
+            };
+            let number = match code.parse::<u8>().ok()? {
+                code @ 11..=15 => code - 10,
+                code @ 17..=21 => code - 11,
+                _ => return None,
+            };
+            match modifier {
+                None => (number, false),
+                Some("2") => (number, true),
+                Some(_) => return None,
+            }
+        }
+        _ => return None,
+    };
+    match (number, shift) {
+        (1..=9, false) => Some(Hotkey::Save(number)),
+        (1..=9, true) => Some(Hotkey::Load(number)),
+        _ => None,
+    }
+}
+
+/// A reminder of the hotkeys for the start of a run.
+pub const HELP: &str = "F1-F9 save to slots 1-9, Shift+F1-F9 load from them, Ctrl+C quits";
+
+/// Puts stdin into character-at-a-time mode without echo for as long as it
+/// lives, and restores the previous mode when dropped.
+#[cfg(unix)]
+pub struct RawTerminal {
+    fd: i32,
+    saved: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawTerminal {
+    /// `None` when stdin is not a terminal.
+    pub fn stdin() -> Option<Self> {
+        use std::os::fd::AsRawFd;
+
+        let fd = std::io::stdin().as_raw_fd();
+        // SAFETY: `termios` is plain old data, and tcgetattr fills it in or
+        // fails without touching anything else.
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return None;
+        }
+        let mut raw = saved;
+        // Ctrl+C arrives as a byte so the frontend can quit and restore the
+        // terminal on its way out; output processing stays on so lines still
+        // end with a carriage return.
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: `raw` is a valid termios copied from the terminal's own.
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            return None;
+        }
+        Some(Self { fd, saved })
+    }
+}
+
+#[cfg(unix)]
+impl std::fmt::Debug for RawTerminal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawTerminal")
+            .field("fd", &self.fd)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read in `stdin`.
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSANOW, &self.saved);
+        }
+    }
+}
+
+/// Elsewhere stdin always stays in line mode.
+#[cfg(not(unix))]
+#[derive(Debug)]
+pub struct RawTerminal;
+
+#[cfg(not(unix))]
+impl RawTerminal {
+    pub fn stdin() -> Option<Self> {
+        None
+    }
+}
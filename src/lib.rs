@@ -1 +1,13 @@
+pub mod aot;
+pub mod batch;
+pub mod coverage;
+pub mod dap;
+pub mod diff;
+pub mod hotkeys;
 pub mod machine;
+pub mod movie;
+pub mod probe;
+pub mod profile;
+pub mod rewind;
+pub mod script;
+pub mod trace;
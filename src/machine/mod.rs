@@ -1,4 +1,12 @@
-pub mod mach;
+pub use chip8_core as mach;
 
-pub type Machine = mach::Machine;
-pub type MachineErr = mach::MachineErr;
+pub use mach::{
+    Backend, BatchErr, CloneRandomSource, Command, Key, MachineErr, Observation, Platform,
+    PlatformErr, Quirks, RandomErr, RandomSource, Reg, Row, ScriptedRandom, SeededRandom, StateErr,
+    DISPLAY_HEIGHT, DISPLAY_WIDTH, FRAMES_PER_SECOND, INSTRUCTIONS_PER_FRAME, MEMORY_SIZE,
+};
+
+pub type Machine = mach::Machine<Box<dyn CloneRandomSource>>;
+pub type MachDisplay = mach::MachDisplay<{ mach::DISPLAY_WIDTH }, { mach::DISPLAY_HEIGHT }>;
+pub type MachineBatch = mach::MachineBatch<Box<dyn CloneRandomSource>>;
+pub type BatchChunk<'a> = mach::BatchChunk<'a, Box<dyn CloneRandomSource>>;
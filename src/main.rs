@@ -1,38 +1,783 @@
-use chip8emu::machine::Machine;
+use chip8emu::{
+    aot::Program,
+    coverage::Coverage,
+    dap,
+    diff::{DiffErr, Lockstep, Reference, TraceLockstep},
+    hotkeys::{self, Input, Keyboard, RawTerminal},
+    machine::{
+        Command, Key, Machine, Platform, SeededRandom, FRAMES_PER_SECOND, INSTRUCTIONS_PER_FRAME,
+    },
+    movie::{Movie, Player},
+    probe::{self, Probe},
+    profile::Profiler,
+    rewind::Rewind,
+    script::{Driver, Script},
+    trace::Tracer,
+};
 use std::{
-    fs::File,
-    io::{BufReader, Read},
+    env,
+    fmt::{Debug, Display},
+    fs::{self, File},
+    io::{self, BufRead, BufWriter, Read, Write},
+    ops::RangeInclusive,
     path::{Path, PathBuf},
+    process,
+    str::FromStr,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
 };
 
 #[derive(Debug)]
 struct Emulation {
-    reader: BufReader<File>,
+    path: PathBuf,
     mach: Machine,
+    tracer: Option<Tracer<BufWriter<File>>>,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    rom: Vec<u8>,
+    commands: Receiver<String>,
+    terminal: Option<RawTerminal>,
+    instructions_per_frame: usize,
+    frames: Option<u64>,
+    frame: u64,
+    seed: u64,
+    start_state: Option<Vec<u8>>,
+    record: bool,
+    recording: Option<Movie>,
+    player: Option<Player>,
+    script: Option<Driver>,
+    rewind: Rewind,
+    rewinding: bool,
+    paced: bool,
+    quit: bool,
+}
+
+const SAVE_SLOTS: u8 = 10;
+
+const REWIND_SECONDS: u32 = 10;
+
+const MOVIE_CHECK_INTERVAL: u64 = FRAMES_PER_SECOND as u64;
+
+const DIFF_FRAMES: u64 = 60 * FRAMES_PER_SECOND as u64;
+
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / FRAMES_PER_SECOND as u64);
+
+#[derive(Debug, Default)]
+struct Options {
+    rom: Option<PathBuf>,
+    dap: Option<Option<u16>>,
+    trace: Option<PathBuf>,
+    trace_range: Option<RangeInclusive<u16>>,
+    trace_limit: Option<u64>,
+    profile: Option<PathBuf>,
+    coverage: Option<PathBuf>,
+    coverage_listing: Option<PathBuf>,
+    ips: Option<usize>,
+    frames: Option<u64>,
+    platform: Option<Platform>,
+    strict_memory: bool,
+    seed: Option<u64>,
+    state: Option<PathBuf>,
+    record: Option<PathBuf>,
+    play: Option<PathBuf>,
+    verify: bool,
+    script: Option<PathBuf>,
+    diff: Option<Platform>,
+    diff_trace: Option<PathBuf>,
+    recompile: Option<PathBuf>,
+    rewind: Option<u32>,
 }
 
 impl Emulation {
-    pub fn new(path: &Path) -> Self {
-        let file = File::open(path).unwrap();
-        let reader = BufReader::new(file);
-        Self {
-            reader: reader,
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let rom = fs::read(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
             mach: Machine::new(),
+            tracer: None,
+            profiler: None,
+            coverage: None,
+            rom,
+            // Replaced by `listen` when the run starts.
+            commands: mpsc::channel().1,
+            terminal: None,
+            instructions_per_frame: INSTRUCTIONS_PER_FRAME,
+            frames: None,
+            frame: 0,
+            seed: 0,
+            start_state: None,
+            record: false,
+            recording: None,
+            player: None,
+            script: None,
+            rewind: Rewind::new(REWIND_SECONDS),
+            rewinding: false,
+            paced: true,
+            quit: false,
+        })
+    }
+
+    /// Starts reading commands from stdin, and hotkeys too when it is a
+    /// terminal.
+    fn listen(&mut self) {
+        let (tx, commands) = mpsc::channel();
+        self.commands = commands;
+        self.terminal = RawTerminal::stdin();
+        if self.terminal.is_some() {
+            eprintln!("{}", hotkeys::HELP);
+            thread::spawn(move || read_keys(tx));
+        } else {
+            thread::spawn(move || {
+                for line in io::stdin().lock().lines().map_while(Result::ok) {
+                    if tx.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    /// The save state file for a slot number, next to the ROM.
+    fn slot_path(&self, slot: &str) -> Option<PathBuf> {
+        let slot: u8 = slot.parse().ok().filter(|slot| *slot < SAVE_SLOTS)?;
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".ss{}", slot));
+        Some(path.into())
+    }
+
+    fn handle_key(&mut self, key: &str, pressed: bool) {
+        if self.player.is_some() {
+            eprintln!("Input is ignored while a movie plays");
+            return;
         }
+        let key = match u8::from_str_radix(key, 16) {
+            Ok(key) if key < 16 => Key::from(key),
+            _ => {
+                eprintln!("Unknown key '{}', expected 0-F", key);
+                return;
+            }
+        };
+        if self.mach.key(key) == pressed {
+            return;
+        }
+        self.mach.set_key(key, pressed);
+        if let Some(movie) = self.recording.as_mut() {
+            movie.record_key(self.frame, key, pressed);
+        }
+    }
+
+    /// Restores the snapshot taken one frame earlier and shows its screen.
+    fn step_back(&mut self) -> bool {
+        if !self.rewind.step_back(&mut self.mach) {
+            eprintln!("Reached the start of the rewind buffer");
+            return false;
+        }
+        self.frame = self.frame.saturating_sub(1);
+        print!("{}", self.mach.display());
+        true
     }
 
-    pub fn start_emulation(&mut self) -> std::io::Result<()> {
-        let mut buf: Vec<u8> = Vec::new();
-        self.reader.read_to_end(&mut buf)?;
-        self.mach.load(&buf).unwrap();
-        loop {
-            self.mach.step();
+    fn handle_command(&mut self, line: &str) {
+        let line = match line.trim() {
+            "quit" => {
+                self.quit = true;
+                return;
+            }
+            "back" => "back 1",
+            line => line,
+        };
+        let movie = self.recording.is_some() || self.player.is_some();
+        match line.split_once(' ') {
+            Some(("back", _)) | Some(("press" | "release", "rewind")) if movie => {
+                eprintln!("Cannot rewind while a movie is recording or playing")
+            }
+            Some(("back", frames)) => match frames.trim().parse::<u32>() {
+                Ok(frames) => {
+                    for _ in 0..frames {
+                        if !self.step_back() {
+                            break;
+                        }
+                    }
+                }
+                Err(_) => eprintln!("Invalid frame count '{}'", frames.trim()),
+            },
+            Some(("press", key)) if key.trim() == "rewind" => self.rewinding = true,
+            Some(("release", key)) if key.trim() == "rewind" => self.rewinding = false,
+            Some(("press", key)) => self.handle_key(key.trim(), true),
+            Some(("release", key)) => self.handle_key(key.trim(), false),
+            Some(("load", _)) if movie => {
+                eprintln!("Cannot load a state while a movie is recording or playing")
+            }
+            Some(("save", slot)) => match self.slot_path(slot.trim()) {
+                Some(path) => match fs::write(&path, self.mach.save_state()) {
+                    Ok(()) => eprintln!("Saved state to {}", path.display()),
+                    Err(err) => eprintln!("Cannot write {}: {}", path.display(), err),
+                },
+                None => unknown_slot(slot),
+            },
+            Some(("load", slot)) => match self.slot_path(slot.trim()) {
+                Some(path) => match fs::read(&path).map(|state| self.mach.load_state(&state)) {
+                    Ok(Ok(())) => {
+                        self.rewind.clear();
+                        self.rewind.push(&self.mach);
+                        eprintln!("Loaded state from {}", path.display())
+                    }
+                    Ok(Err(err)) => eprintln!("Cannot load {}: {:?}", path.display(), err),
+                    Err(err) => eprintln!("Cannot read {}: {}", path.display(), err),
+                },
+                None => unknown_slot(slot),
+            },
+            _ => eprintln!(
+                "Unknown command '{}', expected 'save <slot>', 'load <slot>', \
+                 'press <key>', 'release <key>', 'back [frames]' or 'quit'",
+                line
+            ),
         }
     }
+
+    fn run_frame(&mut self) -> io::Result<()> {
+        let mut probes: Vec<&mut dyn Probe> = Vec::new();
+        if let Some(tracer) = self.tracer.as_mut() {
+            probes.push(tracer);
+        }
+        if let Some(profiler) = self.profiler.as_mut() {
+            probes.push(profiler);
+        }
+        if let Some(coverage) = self.coverage.as_mut() {
+            probes.push(coverage);
+        }
+        for _ in 0..self.instructions_per_frame {
+            if let Some(script) = self.script.as_mut() {
+                script.before_step(&mut self.mach);
+            }
+            match probe::step(&mut self.mach, &mut probes) {
+                Ok(Command::Display(..)) | Ok(Command::ClearScreen) => {
+                    print!("{}", self.mach.display())
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid instruction at {:#06x}", self.mach.pc()),
+                    ))
+                }
+            }
+        }
+        self.mach.tick_timers();
+        for probe in probes.iter_mut() {
+            probe.end_frame(&self.mach);
+        }
+        if let Some(script) = self.script.as_mut() {
+            script.end_frame().map_err(invalid_data)?;
+        }
+        Ok(())
+    }
+
+    fn start_movie(&mut self) -> io::Result<()> {
+        if let Some(player) = self.player.as_ref() {
+            self.mach = player.movie().machine(&self.rom).map_err(invalid_data)?;
+            self.frames = Some(player.movie().frames);
+            return Ok(());
+        }
+        self.mach.set_random(Box::new(SeededRandom::new(self.seed)));
+        if let Some(state) = self.start_state.as_ref() {
+            self.mach.load_state(state).map_err(invalid_data)?;
+        }
+        if self.record {
+            let mut movie = Movie::new(&self.rom, &self.mach, self.seed);
+            if let Some(state) = self.start_state.clone() {
+                movie = movie.with_start_state(state);
+            }
+            self.recording = Some(movie);
+        }
+        Ok(())
+    }
+
+    pub fn start_emulation(&mut self) -> io::Result<()> {
+        self.mach.load(&self.rom).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "ROM does not fit in memory")
+        })?;
+        self.start_movie()?;
+        self.listen();
+        self.rewind.push(&self.mach);
+        let mut next_frame = Instant::now();
+        while !self.quit && self.frames.is_none_or(|frames| self.frame < frames) {
+            // Rewound frames are paced too, so rewinding plays back at speed.
+            if self.paced {
+                next_frame = wait_for_frame(next_frame);
+            }
+            if let Ok(line) = self.commands.try_recv() {
+                self.handle_command(&line);
+            }
+            if self.rewinding {
+                self.rewinding = self.step_back();
+                continue;
+            }
+            if let Some(player) = self.player.as_mut() {
+                player.before_frame(self.frame, &mut self.mach);
+            }
+            self.run_frame()?;
+            if let Some(player) = self.player.as_mut() {
+                player
+                    .after_frame(self.frame, &self.mach)
+                    .map_err(invalid_data)?;
+            }
+            if let Some(movie) = self.recording.as_mut() {
+                if (self.frame + 1).is_multiple_of(MOVIE_CHECK_INTERVAL) {
+                    movie.record_check(self.frame, &self.mach);
+                }
+            }
+            self.rewind.push(&self.mach);
+            self.frame += 1;
+        }
+        if let Some(movie) = self.recording.as_mut() {
+            movie.frames = movie.frames.max(self.frame);
+        }
+        Ok(())
+    }
+}
+
+// Sleeps until `deadline` and returns the next frame's. After a frame that
+// overran, the next one is due a full frame from now rather than at once.
+fn wait_for_frame(deadline: Instant) -> Instant {
+    let now = Instant::now();
+    if now < deadline {
+        thread::sleep(deadline - now);
+        deadline + FRAME_TIME
+    } else {
+        now + FRAME_TIME
+    }
+}
+
+// Reads stdin a key at a time, sending hotkeys as the commands they stand
+// for and everything else as typed lines.
+fn read_keys(tx: mpsc::Sender<String>) {
+    let mut keyboard = Keyboard::new();
+    for byte in io::stdin().lock().bytes().map_while(Result::ok) {
+        let command = match keyboard.feed(byte) {
+            Some(Input::Line(line)) => {
+                eprintln!();
+                line
+            }
+            Some(Input::Hotkey(hotkey)) => hotkey.command(),
+            Some(Input::Echo(text)) => {
+                eprint!("{}", text);
+                continue;
+            }
+            Some(Input::Interrupt) => "quit".to_string(),
+            None => continue,
+        };
+        if tx.send(command).is_err() {
+            break;
+        }
+    }
+}
+
+fn unknown_slot(slot: &str) {
+    eprintln!(
+        "Unknown slot '{}', expected 0-{}",
+        slot.trim(),
+        SAVE_SLOTS - 1
+    );
+}
+
+// Writes an output file through `write`, reporting instead of panicking when
+// it cannot be written. Returns whether it was written.
+fn write_output<F>(path: &Path, write: F) -> bool
+where
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let result = File::create(path).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        write(&mut out)?;
+        out.flush()
+    });
+    if let Err(err) = &result {
+        eprintln!("Cannot write {}: {}", path.display(), err);
+    }
+    result.is_ok()
+}
+
+fn invalid_data<E: Debug>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err))
+}
+
+/// Reports a bad command line or input file and exits.
+fn fail(message: impl Display) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn parse_hex(text: &str) -> Result<u16, String> {
+    u16::from_str_radix(text.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("Invalid hex address '{}'", text))
+}
+
+fn parse_value<T: FromStr>(text: &str, what: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("Invalid {} '{}'", what, text))
+}
+
+fn parse_platform(name: &str) -> Result<Platform, String> {
+    name.parse().map_err(|_| {
+        format!(
+            "Unknown platform '{}', expected chip8, schip or xochip",
+            name
+        )
+    })
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dap" => {
+                let port = args.next_if(|arg| !arg.starts_with("--"));
+                options.dap = Some(
+                    port.map(|port| parse_value(&port, "DAP port"))
+                        .transpose()?,
+                );
+            }
+            "--trace" => options.trace = Some(args.next().ok_or("--trace needs a file")?.into()),
+            "--trace-range" => {
+                let range = args.next().ok_or("--trace-range needs <start>-<end>")?;
+                let (start, end) = range
+                    .split_once('-')
+                    .ok_or_else(|| format!("Invalid trace range '{}'", range))?;
+                options.trace_range = Some(parse_hex(start)?..=parse_hex(end)?);
+            }
+            "--trace-limit" => {
+                let limit = args.next().ok_or("--trace-limit needs a line count")?;
+                options.trace_limit = Some(parse_value(&limit, "trace limit")?);
+            }
+            "--profile" => {
+                options.profile = Some(args.next().ok_or("--profile needs a file")?.into())
+            }
+            "--coverage" => {
+                options.coverage = Some(args.next().ok_or("--coverage needs a file")?.into())
+            }
+            "--coverage-listing" => {
+                let path = args.next().ok_or("--coverage-listing needs a file")?;
+                options.coverage_listing = Some(path.into());
+            }
+            "--ips" => {
+                let ips = args.next().ok_or("--ips needs an instruction rate")?;
+                options.ips = Some(parse_value(&ips, "instruction rate")?);
+            }
+            "--frames" => {
+                let frames = args.next().ok_or("--frames needs a frame count")?;
+                options.frames = Some(parse_value(&frames, "frame count")?);
+            }
+            "--platform" => {
+                let platform = args
+                    .next()
+                    .ok_or("--platform needs chip8, schip or xochip")?;
+                options.platform = Some(parse_platform(&platform)?);
+            }
+            "--strict-memory" => options.strict_memory = true,
+            "--seed" => {
+                let seed = args.next().ok_or("--seed needs a number")?;
+                options.seed = Some(parse_value(&seed, "seed")?);
+            }
+            "--state" => options.state = Some(args.next().ok_or("--state needs a file")?.into()),
+            "--record" => options.record = Some(args.next().ok_or("--record needs a file")?.into()),
+            "--play" => options.play = Some(args.next().ok_or("--play needs a file")?.into()),
+            "--verify" => options.verify = true,
+            "--diff" => {
+                let platform = args.next().ok_or("--diff needs chip8, schip or xochip")?;
+                options.diff = Some(parse_platform(&platform)?);
+            }
+            "--diff-trace" => {
+                options.diff_trace = Some(args.next().ok_or("--diff-trace needs a file")?.into())
+            }
+            "--script" => options.script = Some(args.next().ok_or("--script needs a file")?.into()),
+            "--rewind" => {
+                let seconds = args.next().ok_or("--rewind needs a number of seconds")?;
+                options.rewind = Some(parse_value(&seconds, "rewind length")?);
+            }
+            "--recompile" => {
+                options.recompile = Some(args.next().ok_or("--recompile needs a file")?.into())
+            }
+            _ => options.rom = Some(arg.into()),
+        }
+    }
+    Ok(options)
+}
+
+#[derive(Debug)]
+enum Diff {
+    Platform(Box<Lockstep>),
+    Trace(Box<TraceLockstep>),
+}
+
+impl Diff {
+    fn machine_mut(&mut self) -> &mut Machine {
+        match self {
+            Diff::Platform(lockstep) => lockstep.left_mut(),
+            Diff::Trace(lockstep) => lockstep.machine_mut(),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        match self {
+            Diff::Platform(_) => false,
+            Diff::Trace(lockstep) => lockstep.is_finished(),
+        }
+    }
+
+    fn step(&mut self) -> Result<Command, DiffErr> {
+        match self {
+            Diff::Platform(lockstep) => lockstep.step(),
+            Diff::Trace(lockstep) => lockstep.step(),
+        }
+    }
+
+    fn tick_timers(&mut self) {
+        match self {
+            Diff::Platform(lockstep) => lockstep.tick_timers(),
+            Diff::Trace(lockstep) => lockstep.tick_timers(),
+        }
+    }
+}
+
+/// Runs the ROM headlessly against another platform or a reference trace and
+/// reports the first divergence.
+fn run_diff(options: &Options, rom: &Path) -> io::Result<()> {
+    let rom = fs::read(rom)?;
+    let machine = |platform: Platform| -> io::Result<Machine> {
+        let mut mach = Machine::with_quirks(platform.quirks());
+        mach.set_strict_memory(options.strict_memory);
+        mach.set_random(Box::new(SeededRandom::new(options.seed.unwrap_or(0))));
+        mach.load(&rom).map_err(invalid_data)?;
+        Ok(mach)
+    };
+    let left = machine(options.platform.unwrap_or(Platform::Chip8))?;
+    let mut diff = match (&options.diff_trace, options.diff) {
+        (Some(path), _) => {
+            let reference = Reference::parse(&fs::read_to_string(path)?).map_err(invalid_data)?;
+            Diff::Trace(Box::new(TraceLockstep::new(left, reference)))
+        }
+        (None, Some(platform)) => Diff::Platform(Box::new(Lockstep::new(left, machine(platform)?))),
+        (None, None) => return Ok(()),
+    };
+    let mut script = match &options.script {
+        Some(path) => Some(Driver::new(
+            Script::parse(&fs::read_to_string(path)?).map_err(invalid_data)?,
+        )),
+        None => None,
+    };
+    let instructions = options.ips.map_or(INSTRUCTIONS_PER_FRAME, |ips| {
+        (ips / FRAMES_PER_SECOND as usize).max(1)
+    });
+    let frames = options.frames.unwrap_or(DIFF_FRAMES);
+    for frame in 0..frames {
+        for _ in 0..instructions {
+            if diff.is_finished() {
+                println!("reference trace matched");
+                return Ok(());
+            }
+            if let Some(script) = script.as_mut() {
+                script.before_step(diff.machine_mut());
+            }
+            match diff.step() {
+                Ok(_) => {}
+                Err(DiffErr::Diverged(divergence)) => {
+                    print!("frame {}: {}", frame, divergence);
+                    process::exit(1);
+                }
+                Err(err) => return Err(invalid_data(err)),
+            }
+        }
+        diff.tick_timers();
+        if let Some(script) = script.as_mut() {
+            script.end_frame().map_err(invalid_data)?;
+        }
+    }
+    println!("no divergence in {} frames", frames);
+    Ok(())
 }
 
 fn main() {
-    let file = PathBuf::from("test.ch8");
-    let mut emulation = Emulation::new(file.as_path());
-    emulation.start_emulation().unwrap();
+    let options = parse_options().unwrap_or_else(|err| fail(err));
+    if let Some(port) = options.dap {
+        let result = match port {
+            Some(port) => dap::serve_tcp(port),
+            None => dap::serve_stdio(),
+        };
+        if let Err(err) = result {
+            fail(format_args!("Debug adapter stopped: {}", err));
+        }
+        return;
+    }
+    if let Some(out) = &options.recompile {
+        let rom = options
+            .rom
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("test.ch8"));
+        let rom = match fs::read(&rom) {
+            Ok(rom) => rom,
+            Err(err) => {
+                eprintln!("Cannot open {}: {}", rom.display(), err);
+                process::exit(1);
+            }
+        };
+        let program = Program::discover(&rom, options.platform.unwrap_or(Platform::Chip8))
+            .unwrap_or_else(|_| fail("ROM does not fit in memory"));
+        if !write_output(out, |file| file.write_all(program.emit().as_bytes())) {
+            process::exit(1);
+        }
+        println!(
+            "{} blocks, {} instructions",
+            program.block_count(),
+            program.instruction_count()
+        );
+        return;
+    }
+    if options.diff.is_some() || options.diff_trace.is_some() {
+        let rom = options
+            .rom
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("test.ch8"));
+        if let Err(err) = run_diff(&options, &rom) {
+            fail(format_args!("{}: {}", rom.display(), err));
+        }
+        return;
+    }
+
+    let file = options.rom.unwrap_or_else(|| PathBuf::from("test.ch8"));
+    let mut emulation = match Emulation::new(file.as_path()) {
+        Ok(emulation) => emulation,
+        Err(err) => {
+            eprintln!("Cannot open {}: {}", file.display(), err);
+            process::exit(1);
+        }
+    };
+    if let Some(platform) = options.platform {
+        emulation.mach = Machine::with_quirks(platform.quirks());
+    }
+    emulation.mach.set_strict_memory(options.strict_memory);
+    emulation.seed = options.seed.unwrap_or(0);
+    if let Some(path) = options.state {
+        let state = fs::read(&path)
+            .unwrap_or_else(|err| fail(format_args!("Cannot read {}: {}", path.display(), err)));
+        emulation.start_state = Some(state);
+    }
+    emulation.record = options.record.is_some();
+    if let Some(path) = options.play {
+        let text = fs::read_to_string(&path)
+            .unwrap_or_else(|err| fail(format_args!("Cannot read {}: {}", path.display(), err)));
+        let movie = Movie::parse(&text).unwrap_or_else(|err| {
+            fail(format_args!(
+                "{} is not a valid movie: {:?}",
+                path.display(),
+                err
+            ))
+        });
+        emulation.player = Some(Player::new(movie).with_verify(options.verify));
+    }
+    if let Some(path) = options.script {
+        // Scripts can press keys mid-frame, which a movie cannot represent.
+        if emulation.record || emulation.player.is_some() {
+            eprintln!("--script cannot be combined with --record or --play");
+            process::exit(1);
+        }
+        let text = fs::read_to_string(&path)
+            .unwrap_or_else(|err| fail(format_args!("Cannot read {}: {}", path.display(), err)));
+        let script = Script::parse(&text).unwrap_or_else(|err| {
+            fail(format_args!(
+                "{} is not a valid script: {:?}",
+                path.display(),
+                err
+            ))
+        });
+        emulation.script = Some(Driver::new(script));
+    }
+    if let Some(path) = options.trace {
+        let file = match File::create(&path) {
+            Ok(file) => file,
+            Err(err) => {
+                eprintln!("Cannot write {}: {}", path.display(), err);
+                process::exit(1);
+            }
+        };
+        let mut tracer = Tracer::new(BufWriter::new(file));
+        if let Some(range) = options.trace_range {
+            tracer = tracer.with_range(range);
+        }
+        if let Some(limit) = options.trace_limit {
+            tracer = tracer.with_max_lines(limit);
+        }
+        emulation.tracer = Some(tracer);
+    }
+    if options.profile.is_some() {
+        emulation.profiler = Some(Profiler::new());
+    }
+    if options.coverage.is_some() || options.coverage_listing.is_some() {
+        emulation.coverage = Some(Coverage::new().with_rom(&emulation.rom));
+    }
+    if let Some(ips) = options.ips {
+        emulation.instructions_per_frame = (ips / FRAMES_PER_SECOND as usize).max(1);
+    }
+    emulation.frames = options.frames;
+    // A run with a frame count is headless and goes as fast as it can.
+    emulation.paced = options.frames.is_none();
+    if let Some(seconds) = options.rewind {
+        emulation.rewind = Rewind::new(seconds);
+    }
+    let result = emulation.start_emulation();
+    // Back to line mode before anything below can exit.
+    emulation.terminal = None;
+
+    // Every output is attempted even after one fails, so a bad path for one
+    // does not lose the others.
+    let mut written = true;
+    if let (Some(path), Some(movie)) = (options.record, emulation.recording.as_ref()) {
+        written &= write_output(&path, |out| movie.write(out));
+    }
+
+    if let Some(tracer) = emulation.tracer.take() {
+        if let Err(err) = tracer.finish() {
+            eprintln!("Trace is incomplete: {}", err);
+            process::exit(1);
+        }
+    }
+
+    if let (Some(path), Some(profiler)) = (options.profile, emulation.profiler.as_ref()) {
+        written &= write_output(&path, |out| profiler.write_report(&emulation.mach, out));
+        let mut folded = path.into_os_string();
+        folded.push(".folded");
+        written &= write_output(Path::new(&folded), |out| profiler.write_collapsed(out));
+    }
+    if let Some(coverage) = emulation.coverage.as_mut() {
+        if let Some(path) = options.coverage {
+            match fs::read_to_string(&path) {
+                Ok(text) => match Coverage::parse(&text).and_then(|old| coverage.merge(&old)) {
+                    Ok(()) => written &= write_output(&path, |out| coverage.write(out)),
+                    Err(_) => eprintln!(
+                        "{} is not a coverage file for this ROM; left it unchanged",
+                        path.display()
+                    ),
+                },
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    written &= write_output(&path, |out| coverage.write(out));
+                }
+                Err(err) => {
+                    eprintln!("Cannot read {}: {}; left it unchanged", path.display(), err);
+                    written = false;
+                }
+            }
+        }
+        if let Some(path) = options.coverage_listing {
+            written &= write_output(&path, |out| coverage.write_listing(&emulation.rom, out));
+        }
+    }
+    if let Err(err) = result {
+        eprintln!("{}: {}", file.display(), err);
+        process::exit(1);
+    }
+    if !written {
+        process::exit(1);
+    }
 }
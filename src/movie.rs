@@ -0,0 +1,318 @@
+//! Input movies.
+//!
+//! A movie is a text file. The header pins down everything a run depends on
+//! besides input:
+//!
+//! ```text
+//! chip8-movie 1
+//! rom 9c1f2a3b4d5e6f70
+//! quirks 1 1 1 1 0
+//! strict-memory 0
+//! seed 6694462003813472456
+//! state <hex save state, optional>
+//! frames 1800
+//! ```
+//!
+//! followed by one event per line, in frame order: `<frame> press <key>`,
+//! `<frame> release <key>` or `<frame> check <screen hash>`. Key events take
+//! effect before the frame runs; checks compare the screen after it.
+//!
+//! The quirk flags are `vf_reset`, `memory_increment`, `shift_uses_vy`,
+//! `clip_sprites` and `jump_uses_vx`.
+
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+use crate::coverage::fnv1a;
+use crate::machine::{Key, Machine, Quirks, SeededRandom, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+const HEADER: &str = "chip8-movie 1";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Press(Key),
+    Release(Key),
+    Check(u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Movie {
+    pub rom_hash: u64,
+    pub quirks: Quirks,
+    pub strict_memory: bool,
+    pub seed: u64,
+    pub start_state: Option<Vec<u8>>,
+    pub frames: u64,
+    events: Vec<(u64, Event)>,
+}
+
+#[derive(Debug)]
+pub enum MovieErr {
+    Parse(usize),
+    RomMismatch,
+    BadState,
+    Desync {
+        frame: u64,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+pub fn screen_hash(mach: &Machine) -> u64 {
+    let display = mach.display();
+    let mut pixels = Vec::with_capacity(DISPLAY_WIDTH * DISPLAY_HEIGHT);
+    for y in 0..DISPLAY_HEIGHT {
+        for x in 0..DISPLAY_WIDTH {
+            pixels.push(matches!(display.pixel(x, y), Ok(true)) as u8);
+        }
+    }
+    fnv1a(&pixels)
+}
+
+fn parse_bool(text: &str) -> Option<bool> {
+    match text {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+fn parse_hex_bytes(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl Movie {
+    pub fn new(rom: &[u8], mach: &Machine, seed: u64) -> Self {
+        Self {
+            rom_hash: fnv1a(rom),
+            quirks: mach.quirks(),
+            strict_memory: mach.strict_memory(),
+            seed,
+            start_state: None,
+            frames: 0,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_start_state(mut self, state: Vec<u8>) -> Self {
+        self.start_state = Some(state);
+        self
+    }
+
+    pub fn events(&self) -> &[(u64, Event)] {
+        &self.events
+    }
+
+    /// Records a key transition taking effect before `frame` runs.
+    pub fn record_key(&mut self, frame: u64, key: Key, pressed: bool) {
+        let event = if pressed {
+            Event::Press(key)
+        } else {
+            Event::Release(key)
+        };
+        self.events.push((frame, event));
+        self.frames = self.frames.max(frame + 1);
+    }
+
+    /// Records the screen after `frame` has run as a checkpoint.
+    pub fn record_check(&mut self, frame: u64, mach: &Machine) {
+        self.events.push((frame, Event::Check(screen_hash(mach))));
+        self.frames = self.frames.max(frame + 1);
+    }
+
+    /// Builds the machine the movie was recorded on, with `rom` loaded.
+    pub fn machine(&self, rom: &[u8]) -> Result<Machine, MovieErr> {
+        if fnv1a(rom) != self.rom_hash {
+            return Err(MovieErr::RomMismatch);
+        }
+        let mut mach = Machine::with_quirks(self.quirks);
+        mach.set_strict_memory(self.strict_memory);
+        mach.set_random(Box::new(SeededRandom::new(self.seed)));
+        mach.load(rom).map_err(|_| MovieErr::RomMismatch)?;
+        if let Some(state) = &self.start_state {
+            mach.load_state(state).map_err(|_| MovieErr::BadState)?;
+        }
+        Ok(mach)
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let quirks = self.quirks;
+        writeln!(out, "{}", HEADER)?;
+        writeln!(out, "rom {:016x}", self.rom_hash)?;
+        writeln!(
+            out,
+            "quirks {} {} {} {} {}",
+            quirks.vf_reset as u8,
+            quirks.memory_increment as u8,
+            quirks.shift_uses_vy as u8,
+            quirks.clip_sprites as u8,
+            quirks.jump_uses_vx as u8
+        )?;
+        writeln!(out, "strict-memory {}", self.strict_memory as u8)?;
+        writeln!(out, "seed {}", self.seed)?;
+        if let Some(state) = &self.start_state {
+            let mut hex = String::with_capacity(state.len() * 2);
+            for byte in state {
+                let _ = write!(hex, "{:02x}", byte);
+            }
+            writeln!(out, "state {}", hex)?;
+        }
+        writeln!(out, "frames {}", self.frames)?;
+        for (frame, event) in &self.events {
+            match event {
+                Event::Press(key) => writeln!(out, "{} press {:x}", frame, u8::from(*key))?,
+                Event::Release(key) => writeln!(out, "{} release {:x}", frame, u8::from(*key))?,
+                Event::Check(hash) => writeln!(out, "{} check {:016x}", frame, hash)?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, MovieErr> {
+        let mut lines = text.lines().enumerate();
+        if lines.next().map(|(_, line)| line.trim()) != Some(HEADER) {
+            return Err(MovieErr::Parse(1));
+        }
+        let mut movie = Movie {
+            rom_hash: 0,
+            quirks: Quirks::default(),
+            strict_memory: false,
+            seed: 0,
+            start_state: None,
+            frames: 0,
+            events: Vec::new(),
+        };
+        for (i, line) in lines {
+            let err = || MovieErr::Parse(i + 1);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                ["rom", hash] => {
+                    movie.rom_hash = u64::from_str_radix(hash, 16).map_err(|_| err())?
+                }
+                ["quirks", vf_reset, memory_increment, shift_uses_vy, clip_sprites, jump_uses_vx] => {
+                    movie.quirks = Quirks {
+                        vf_reset: parse_bool(vf_reset).ok_or_else(err)?,
+                        memory_increment: parse_bool(memory_increment).ok_or_else(err)?,
+                        shift_uses_vy: parse_bool(shift_uses_vy).ok_or_else(err)?,
+                        clip_sprites: parse_bool(clip_sprites).ok_or_else(err)?,
+                        jump_uses_vx: parse_bool(jump_uses_vx).ok_or_else(err)?,
+                    }
+                }
+                ["strict-memory", strict] => {
+                    movie.strict_memory = parse_bool(strict).ok_or_else(err)?
+                }
+                ["seed", seed] => movie.seed = seed.parse().map_err(|_| err())?,
+                ["state", hex] => movie.start_state = Some(parse_hex_bytes(hex).ok_or_else(err)?),
+                ["frames", frames] => movie.frames = frames.parse().map_err(|_| err())?,
+                [frame, kind, arg] => {
+                    let frame: u64 = frame.parse().map_err(|_| err())?;
+                    let key = || {
+                        u8::from_str_radix(arg, 16)
+                            .ok()
+                            .filter(|key| *key < 16)
+                            .map(Key::from)
+                            .ok_or_else(err)
+                    };
+                    let event = match *kind {
+                        "press" => Event::Press(key()?),
+                        "release" => Event::Release(key()?),
+                        "check" => Event::Check(u64::from_str_radix(arg, 16).map_err(|_| err())?),
+                        _ => return Err(err()),
+                    };
+                    // Events are in frame order, and within a frame the key
+                    // events come before the checks.
+                    match movie.events.last() {
+                        Some((last, _)) if *last > frame => return Err(err()),
+                        Some((last, Event::Check(_)))
+                            if *last == frame && !matches!(event, Event::Check(_)) =>
+                        {
+                            return Err(err())
+                        }
+                        _ => {}
+                    }
+                    movie.events.push((frame, event));
+                }
+                _ => return Err(err()),
+            }
+        }
+        Ok(movie)
+    }
+}
+
+/// Feeds a movie's key events into a machine frame by frame.
+#[derive(Debug, Clone)]
+pub struct Player {
+    movie: Movie,
+    next: usize,
+    verify: bool,
+}
+
+impl Player {
+    pub fn new(movie: Movie) -> Self {
+        Self {
+            movie,
+            next: 0,
+            verify: false,
+        }
+    }
+
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    pub fn is_finished(&self, frame: u64) -> bool {
+        frame >= self.movie.frames && self.next == self.movie.events.len()
+    }
+
+    /// Applies the key events for `frame`. Call before running the frame.
+    pub fn before_frame(&mut self, frame: u64, mach: &mut Machine) {
+        while let Some((at, event)) = self.movie.events.get(self.next) {
+            if *at != frame {
+                break;
+            }
+            match event {
+                Event::Press(key) => mach.set_key(*key, true),
+                Event::Release(key) => mach.set_key(*key, false),
+                Event::Check(_) => break,
+            }
+            self.next += 1;
+        }
+    }
+
+    /// Verifies the checkpoints for `frame` when verification is on. Call
+    /// after running the frame.
+    pub fn after_frame(&mut self, frame: u64, mach: &Machine) -> Result<(), MovieErr> {
+        while let Some((at, event)) = self.movie.events.get(self.next) {
+            if *at != frame {
+                break;
+            }
+            let Event::Check(expected) = event else {
+                break;
+            };
+            let actual = screen_hash(mach);
+            if self.verify && actual != *expected {
+                return Err(MovieErr::Desync {
+                    frame,
+                    expected: *expected,
+                    actual,
+                });
+            }
+            self.next += 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+use crate::machine::{Command, Machine, MachineErr};
+
+pub trait Probe {
+    fn before_step(&mut self, _mach: &Machine) {}
+
+    fn after_step(&mut self, _mach: &Machine, _command: Command) {}
+
+    fn end_frame(&mut self, _mach: &Machine) {}
+}
+
+pub fn step(mach: &mut Machine, probes: &mut [&mut dyn Probe]) -> Result<Command, MachineErr> {
+    for probe in probes.iter_mut() {
+        probe.before_step(mach);
+    }
+    let command = mach.step()?;
+    for probe in probes.iter_mut() {
+        probe.after_step(mach, command);
+    }
+    Ok(command)
+}
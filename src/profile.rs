@@ -0,0 +1,185 @@
+//! Execution profiler.
+//!
+//! Subroutines are inferred from the machine stack: when it grows, the new
+//! PC is the entry of a subroutine, and when it shrinks that subroutine has
+//! returned. Every executed instruction is charged to the subroutines on the
+//! stack at the time. Collapsed stacks are written in the folded format read
+//! by `flamegraph.pl` and `inferno-flamegraph`.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use crate::machine::{Command, Machine, MEMORY_SIZE};
+use crate::probe::Probe;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Subroutine {
+    calls: u64,
+    inclusive: u64,
+    exclusive: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    hits: Vec<u64>,
+    calls: Vec<u16>,
+    subroutines: HashMap<u16, Subroutine>,
+    stacks: HashMap<Vec<u16>, u64>,
+    frames: Vec<u64>,
+    frame_instructions: u64,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn subroutine_name(addr: u16) -> String {
+    format!("sub_{:03x}", addr)
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            hits: vec![0; MEMORY_SIZE],
+            calls: Vec::new(),
+            subroutines: HashMap::new(),
+            stacks: HashMap::new(),
+            frames: Vec::new(),
+            frame_instructions: 0,
+        }
+    }
+
+    pub fn instructions(&self) -> u64 {
+        self.hits.iter().sum()
+    }
+
+    pub fn hits(&self, addr: u16) -> u64 {
+        self.hits.get(addr as usize).copied().unwrap_or(0)
+    }
+
+    pub fn write_report<W: Write>(&self, mach: &Machine, out: &mut W) -> io::Result<()> {
+        let total = self.instructions().max(1);
+        let percent = |count: u64| count as f64 * 100.0 / total as f64;
+
+        writeln!(out, "Hot spots ({} instructions)", self.instructions())?;
+        writeln!(out, "{:>8} {:>12} {:>7}  instruction", "addr", "count", "%")?;
+        let mut hot: Vec<(usize, u64)> = self
+            .hits
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for (addr, count) in hot {
+            let instruction = match mach.peek_command(addr as u16) {
+                Ok(command) => command.to_string(),
+                Err(_) => "??".to_string(),
+            };
+            writeln!(
+                out,
+                "{:>#8x} {:>12} {:>6.2}%  {}",
+                addr,
+                count,
+                percent(count),
+                instruction
+            )?;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "Subroutines")?;
+        writeln!(
+            out,
+            "{:>8} {:>8} {:>12} {:>7} {:>12} {:>7}",
+            "addr", "calls", "inclusive", "%", "exclusive", "%"
+        )?;
+        let mut subroutines: Vec<(&u16, &Subroutine)> = self.subroutines.iter().collect();
+        subroutines.sort_by(|a, b| b.1.inclusive.cmp(&a.1.inclusive).then(a.0.cmp(b.0)));
+        for (addr, sub) in subroutines {
+            writeln!(
+                out,
+                "{:>#8x} {:>8} {:>12} {:>6.2}% {:>12} {:>6.2}%",
+                addr,
+                sub.calls,
+                sub.inclusive,
+                percent(sub.inclusive),
+                sub.exclusive,
+                percent(sub.exclusive)
+            )?;
+        }
+
+        writeln!(out)?;
+        match (self.frames.iter().min(), self.frames.iter().max()) {
+            (Some(min), Some(max)) => {
+                let avg = self.frames.iter().sum::<u64>() as f64 / self.frames.len() as f64;
+                writeln!(
+                    out,
+                    "Instructions per frame over {} frames: min {}, avg {:.1}, max {}",
+                    self.frames.len(),
+                    min,
+                    avg,
+                    max
+                )
+            }
+            _ => writeln!(out, "Instructions per frame: no complete frames"),
+        }
+    }
+
+    pub fn write_collapsed<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut stacks: Vec<(&Vec<u16>, &u64)> = self.stacks.iter().collect();
+        stacks.sort();
+        for (stack, count) in stacks {
+            let mut line = String::from("main");
+            for addr in stack {
+                line.push(';');
+                line.push_str(&subroutine_name(*addr));
+            }
+            writeln!(out, "{} {}", line, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl Probe for Profiler {
+    fn before_step(&mut self, mach: &Machine) {
+        if let Some(hits) = self.hits.get_mut(mach.pc() as usize) {
+            *hits += 1;
+        }
+        self.frame_instructions += 1;
+
+        for (i, addr) in self.calls.iter().enumerate() {
+            if self.calls[..i].contains(addr) {
+                continue;
+            }
+            self.subroutines.entry(*addr).or_default().inclusive += 1;
+        }
+        if let Some(addr) = self.calls.last() {
+            self.subroutines.entry(*addr).or_default().exclusive += 1;
+        }
+        match self.stacks.get_mut(&self.calls) {
+            Some(count) => *count += 1,
+            None => {
+                self.stacks.insert(self.calls.clone(), 1);
+            }
+        }
+    }
+
+    fn after_step(&mut self, mach: &Machine, _command: Command) {
+        let depth = mach.stack().len();
+        self.calls.truncate(depth);
+        if self.calls.len() < depth {
+            let entry = mach.pc();
+            self.calls.resize(depth, entry);
+            self.subroutines.entry(entry).or_default().calls += 1;
+        }
+    }
+
+    fn end_frame(&mut self, _mach: &Machine) {
+        self.frames.push(self.frame_instructions);
+        self.frame_instructions = 0;
+    }
+}
@@ -0,0 +1,164 @@
+//! Rewind buffer of per-frame save states.
+//!
+//! Only the newest snapshot is kept in full. Each older snapshot is stored as
+//! the runs of bytes where it differs from the snapshot after it, so a frame
+//! that touched a few registers costs a few bytes.
+
+use std::collections::VecDeque;
+
+use crate::machine::{Machine, FRAMES_PER_SECOND};
+
+#[derive(Debug, Clone)]
+pub struct Rewind {
+    capacity: usize,
+    newest: Option<(u64, Vec<u8>)>,
+    older: VecDeque<(u64, Vec<u8>)>,
+}
+
+fn push_u32(out: &mut Vec<u8>, val: usize) {
+    out.extend_from_slice(&(val as u32).to_be_bytes());
+}
+
+fn read_u32(data: &[u8], at: usize) -> usize {
+    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]) as usize
+}
+
+// Encodes `old` against `new` as its length followed by `(skip, len, xor bytes)` runs.
+fn diff(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let byte_at = |data: &[u8], i: usize| data.get(i).copied().unwrap_or(0);
+    let len = old.len().max(new.len());
+    let mut delta = Vec::new();
+    push_u32(&mut delta, old.len());
+
+    let mut last = 0;
+    let mut i = 0;
+    while i < len {
+        if byte_at(old, i) == byte_at(new, i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < len && byte_at(old, i) != byte_at(new, i) {
+            i += 1;
+        }
+        push_u32(&mut delta, start - last);
+        push_u32(&mut delta, i - start);
+        delta.extend((start..i).map(|j| byte_at(old, j) ^ byte_at(new, j)));
+        last = i;
+    }
+    delta
+}
+
+fn apply(new: &[u8], delta: &[u8]) -> Vec<u8> {
+    let old_len = read_u32(delta, 0);
+    let mut old = new.to_vec();
+    old.resize(old_len.max(new.len()), 0);
+
+    let mut pos = 0;
+    let mut at = 4;
+    while at < delta.len() {
+        pos += read_u32(delta, at);
+        let len = read_u32(delta, at + 4);
+        at += 8;
+        for (byte, xor) in old[pos..pos + len].iter_mut().zip(&delta[at..at + len]) {
+            *byte ^= xor;
+        }
+        pos += len;
+        at += len;
+    }
+    old.truncate(old_len);
+    old
+}
+
+impl Rewind {
+    pub fn new(seconds: u32) -> Self {
+        Self::with_frames((seconds as usize).saturating_mul(FRAMES_PER_SECOND as usize))
+    }
+
+    pub fn with_frames(frames: usize) -> Self {
+        Self {
+            capacity: frames.max(1),
+            newest: None,
+            older: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.older.len() + self.newest.is_some() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.older.clear();
+    }
+
+    pub fn push(&mut self, mach: &Machine) {
+        let state = mach.save_state();
+        if let Some((cycles, newest)) = self.newest.take() {
+            self.older.push_back((cycles, diff(&newest, &state)));
+        }
+        self.newest = Some((mach.cycles(), state));
+        while self.older.len() >= self.capacity {
+            self.older.pop_front();
+        }
+    }
+
+    fn pop_newest(&mut self) -> bool {
+        let Some((cycles, delta)) = self.older.pop_back() else {
+            return false;
+        };
+        if let Some(newest) = self.newest.as_mut() {
+            newest.1 = apply(&newest.1, &delta);
+            newest.0 = cycles;
+        }
+        true
+    }
+
+    fn restore(&self, mach: &mut Machine) -> bool {
+        match self.newest.as_ref() {
+            Some((_, state)) => mach.load_state(state).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn step_back(&mut self, mach: &mut Machine) -> bool {
+        let mid_frame = self
+            .newest
+            .as_ref()
+            .is_some_and(|(cycles, _)| mach.cycles() > *cycles);
+        (mid_frame || self.pop_newest()) && self.restore(mach)
+    }
+
+    pub fn step_back_instruction(&mut self, mach: &mut Machine) -> bool {
+        let Some(target) = mach.cycles().checked_sub(1) else {
+            return false;
+        };
+        let oldest = match self.older.front() {
+            Some((cycles, _)) => *cycles,
+            None => match self.newest.as_ref() {
+                Some((cycles, _)) => *cycles,
+                None => return false,
+            },
+        };
+        if oldest > target {
+            return false;
+        }
+
+        while self.newest.as_ref().is_some_and(|(cycles, _)| *cycles > target) {
+            self.pop_newest();
+        }
+        if !self.restore(mach) {
+            return false;
+        }
+        while mach.cycles() < target {
+            if mach.step().is_err() {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,222 @@
+//! Scripted keypad input.
+//!
+//! A script is a list of commands separated by newlines or `;`, with `#`
+//! starting a comment:
+//!
+//! ```text
+//! wait 30; press 5 for 3; release all
+//! wait-until pc=0x2A4 within 600
+//! press A
+//! ```
+//!
+//! - `wait <frames>` lets that many frames end.
+//! - `press <key>` and `release <key>` change one key; `release all` lets go
+//!   of every key. `press <key> for <frames>` holds the key for that many
+//!   frames and then releases it.
+//! - `wait-until <target>=<value> [within <frames>]` runs until the condition
+//!   holds before an instruction executes, or fails after the frame limit
+//!   (default 3600). Targets are `pc`, `i`, `dt`, `st` and `v0`-`vf`.
+//!
+//! Numbers are decimal, or hex with a `0x` prefix; keys are a single hex digit.
+
+use crate::machine::{Key, Machine, MachineErr, Reg};
+
+const DEFAULT_WITHIN: u64 = 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Pc,
+    Index,
+    DelayTimer,
+    SoundTimer,
+    Reg(Reg),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    Wait(u64),
+    Press(Key),
+    Release(Key),
+    ReleaseAll,
+    WaitUntil(Target, u16, u64),
+}
+
+#[derive(Debug)]
+pub enum ScriptErr {
+    Parse(usize),
+    Timeout(usize),
+    Fault(MachineErr),
+}
+
+impl From<MachineErr> for ScriptErr {
+    fn from(err: MachineErr) -> Self {
+        ScriptErr::Fault(err)
+    }
+}
+
+fn parse_num(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn parse_key(text: &str) -> Option<Key> {
+    match u8::from_str_radix(text, 16) {
+        Ok(key) if text.len() == 1 => Some(Key::from(key)),
+        _ => None,
+    }
+}
+
+fn parse_target(text: &str) -> Option<Target> {
+    match text.to_ascii_lowercase().as_str() {
+        "pc" => Some(Target::Pc),
+        "i" => Some(Target::Index),
+        "dt" => Some(Target::DelayTimer),
+        "st" => Some(Target::SoundTimer),
+        reg => {
+            let reg = u8::from_str_radix(reg.strip_prefix('v')?, 16).ok()?;
+            (reg < 16).then(|| Target::Reg(Reg::from(reg)))
+        }
+    }
+}
+
+fn parse_command(words: &[&str], steps: &mut Vec<Step>) -> Option<()> {
+    match words {
+        ["wait", frames] => steps.push(Step::Wait(parse_num(frames)?)),
+        ["press", key] => steps.push(Step::Press(parse_key(key)?)),
+        ["press", key, "for", frames] => {
+            let key = parse_key(key)?;
+            steps.push(Step::Press(key));
+            steps.push(Step::Wait(parse_num(frames)?));
+            steps.push(Step::Release(key));
+        }
+        ["release", "all"] => steps.push(Step::ReleaseAll),
+        ["release", key] => steps.push(Step::Release(parse_key(key)?)),
+        ["wait-until", cond, rest @ ..] => {
+            let within = match rest {
+                [] => DEFAULT_WITHIN,
+                ["within", frames] => parse_num(frames)?,
+                _ => return None,
+            };
+            let (target, value) = cond.split_once('=')?;
+            let value = u16::try_from(parse_num(value)?).ok()?;
+            steps.push(Step::WaitUntil(parse_target(target)?, value, within));
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+#[derive(Debug, Clone)]
+pub struct Script {
+    steps: Vec<Step>,
+    lines: Vec<usize>,
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Self, ScriptErr> {
+        let mut steps = Vec::new();
+        let mut lines = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            for command in line.split(';') {
+                let words: Vec<&str> = command.split_whitespace().collect();
+                if words.is_empty() {
+                    continue;
+                }
+                parse_command(&words, &mut steps).ok_or(ScriptErr::Parse(i + 1))?;
+                lines.resize(steps.len(), i + 1);
+            }
+        }
+        Ok(Self { steps, lines })
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+}
+
+fn value(mach: &Machine, target: Target) -> u16 {
+    match target {
+        Target::Pc => mach.pc(),
+        Target::Index => mach.index(),
+        Target::DelayTimer => mach.delay_timer() as u16,
+        Target::SoundTimer => mach.sound_timer() as u16,
+        Target::Reg(reg) => mach.reg(reg) as u16,
+    }
+}
+
+/// Runs a script against a machine through its public input API.
+#[derive(Debug, Clone)]
+pub struct Driver {
+    script: Script,
+    next: usize,
+    waited: u64,
+}
+
+impl Driver {
+    pub fn new(script: Script) -> Self {
+        Self {
+            script,
+            next: 0,
+            waited: 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.script.steps.len()
+    }
+
+    /// Applies every step that is due. Call before each instruction.
+    pub fn before_step(&mut self, mach: &mut Machine) {
+        while let Some(step) = self.script.steps.get(self.next) {
+            match *step {
+                Step::Wait(frames) if self.waited < frames => return,
+                Step::WaitUntil(target, expected, _) if value(mach, target) != expected => return,
+                Step::Wait(_) | Step::WaitUntil(..) => {}
+                Step::Press(key) => mach.set_key(key, true),
+                Step::Release(key) => mach.set_key(key, false),
+                Step::ReleaseAll => {
+                    for key in 0..16 {
+                        mach.set_key(Key::from(key), false);
+                    }
+                }
+            }
+            self.next += 1;
+            self.waited = 0;
+        }
+    }
+
+    /// Counts a finished frame. Call after the timers tick.
+    pub fn end_frame(&mut self) -> Result<(), ScriptErr> {
+        if self.is_finished() {
+            return Ok(());
+        }
+        self.waited += 1;
+        match self.script.steps[self.next] {
+            Step::WaitUntil(_, _, within) if self.waited >= within => {
+                Err(ScriptErr::Timeout(self.script.lines[self.next]))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs frames until the script has finished, returning the frame count.
+    pub fn run(&mut self, mach: &mut Machine, instructions: usize) -> Result<u64, ScriptErr> {
+        let mut frames = 0;
+        loop {
+            self.before_step(mach);
+            if self.is_finished() {
+                return Ok(frames);
+            }
+            for _ in 0..instructions {
+                self.before_step(mach);
+                mach.step()?;
+            }
+            mach.tick_timers();
+            self.end_frame()?;
+            frames += 1;
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Per-instruction execution trace.
+//!
+//! Each executed instruction produces one line:
+//!
+//! ```text
+//! <cycle> <pc> <opcode> <mnemonic> ;[ <reg>=<value>]...
+//! ```
+//!
+//! `cycle` is the number of instructions executed before this one, in
+//! decimal. `pc` and `opcode` are four upper-case hex digits. `mnemonic` is
+//! the decoded [`Command`] as printed by its `Display` impl. After the `;`
+//! come the registers that the instruction changed, in the order `V0`..`VF`
+//! then `I`, each as `name=value` in upper-case hex (two digits for `Vx`,
+//! four for `I`). For example:
+//!
+//! ```text
+//! 12 0206 8014 ADD V0, V1 ; V0=03 VF=01
+//! ```
+//!
+//! A sink that fails to write does not stop emulation. The tracer stops
+//! writing, keeps the first error and returns it from [`Tracer::finish`].
+
+use std::{
+    io::{self, Write},
+    ops::RangeInclusive,
+};
+
+use enum_iterator::all;
+
+use crate::machine::{Command, Machine, MachineErr, Reg};
+use crate::probe::{self, Probe};
+
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    cycle: u64,
+    pc: u16,
+    opcode: u16,
+    regs: [u8; 16],
+    index: u16,
+}
+
+#[derive(Debug)]
+pub struct Tracer<W: Write> {
+    out: W,
+    range: RangeInclusive<u16>,
+    max_lines: Option<u64>,
+    lines: u64,
+    pending: Option<Pending>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            range: 0..=u16::MAX,
+            max_lines: None,
+            lines: 0,
+            pending: None,
+            error: None,
+        }
+    }
+
+    pub fn with_range(mut self, range: RangeInclusive<u16>) -> Self {
+        self.range = range;
+        self
+    }
+
+    pub fn with_max_lines(mut self, max_lines: u64) -> Self {
+        self.max_lines = Some(max_lines);
+        self
+    }
+
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    /// Flushes the sink and returns it, or the first error writing to it.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn is_full(&self) -> bool {
+        self.error.is_some() || self.max_lines.is_some_and(|max| self.lines >= max)
+    }
+
+    pub fn step(&mut self, mach: &mut Machine) -> Result<Command, MachineErr> {
+        probe::step(mach, &mut [self])
+    }
+}
+
+impl<W: Write> Probe for Tracer<W> {
+    fn before_step(&mut self, mach: &Machine) {
+        let pc = mach.pc();
+        self.pending = None;
+        if self.is_full() || !self.range.contains(&pc) {
+            return;
+        }
+        let Ok(opcode) = mach.peek_opcode(pc) else {
+            return;
+        };
+        let mut regs = [0; 16];
+        for (val, reg) in regs.iter_mut().zip(all::<Reg>()) {
+            *val = mach.reg(reg);
+        }
+        self.pending = Some(Pending {
+            cycle: mach.cycles(),
+            pc,
+            opcode,
+            regs,
+            index: mach.index(),
+        });
+    }
+
+    fn after_step(&mut self, mach: &Machine, command: Command) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        let mut line = format!(
+            "{} {:04X} {:04X} {} ;",
+            pending.cycle, pending.pc, pending.opcode, command
+        );
+        for (reg, before) in all::<Reg>().zip(pending.regs) {
+            let after = mach.reg(reg);
+            if after != before {
+                line.push_str(&format!(" {}={:02X}", reg, after));
+            }
+        }
+        if mach.index() != pending.index {
+            line.push_str(&format!(" I={:04X}", mach.index()));
+        }
+        let written = writeln!(self.out, "{}", line).and_then(|()| {
+            self.lines += 1;
+            if self.is_full() {
+                self.out.flush()?;
+            }
+            Ok(())
+        });
+        // A broken trace sink must not stop emulation.
+        if let Err(err) = written {
+            self.error = Some(err);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Checks that stepping a machine never touches the heap.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    fs,
+    path::PathBuf,
+};
+
+use chip8emu::machine::{Backend, Key, Machine, Platform, INSTRUCTIONS_PER_FRAME};
+
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// Touches every kind of side effect: BCD, store, load, random, font, draw,
+// call, shift and return.
+const ROM: [u8; 26] = [
+    0xA3, 0x00, 0x60, 0xFF, 0xF0, 0x33, 0xF2, 0x55, 0xF2, 0x65, 0xC1, 0x0F, 0xF1, 0x29, 0xD0, 0x15,
+    0x22, 0x16, 0x80, 0x14, 0x12, 0x00, 0x81, 0x06, 0x00, 0xEE,
+];
+
+fn allocations_while_running(mut mach: Machine, frames: usize) -> u64 {
+    let before = ALLOCATIONS.with(Cell::get);
+    for frame in 0..frames {
+        mach.set_key(Key::from(frame as u8), frame % 3 == 0);
+        mach.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+    }
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn step_does_not_allocate() {
+    let roms = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms");
+    let mut images = vec![ROM.to_vec()];
+    for name in ["font.ch8", "flags.ch8", "quirks.ch8", "keypad.ch8"] {
+        images.push(fs::read(roms.join(name)).unwrap());
+    }
+    for image in &images {
+        for platform in [Platform::Chip8, Platform::SuperChip, Platform::XoChip] {
+            for decode_cache in [true, false] {
+                for backend in [Backend::Interpreter, Backend::Threaded] {
+                    let mut mach = Machine::with_quirks(platform.quirks());
+                    mach.set_decode_cache(decode_cache);
+                    mach.set_backend(backend);
+                    mach.load(image).unwrap();
+                    assert_eq!(allocations_while_running(mach, 300), 0);
+                }
+            }
+        }
+    }
+}
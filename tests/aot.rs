@@ -0,0 +1,191 @@
+//! Recompiled ROMs against the interpreter.
+//!
+//! `tests/aot` holds the modules `chip8emu --recompile` writes for the
+//! conformance ROMs and for `selfmod.ch8`, which patches an instruction it
+//! has already run and then takes a `BNNN` to code that only the jump
+//! reaches. Each module must match what the recompiler emits today and must
+//! run in lockstep with the interpreter. Run with `CHIP8_BLESS=1` to rewrite
+//! the modules.
+
+use std::{env, fs, path::PathBuf};
+
+use chip8emu::{
+    aot::Program,
+    machine::{Key, Machine, MachineErr, Platform, Reg, INSTRUCTIONS_PER_FRAME},
+};
+use enum_iterator::all;
+
+#[path = "aot/flags.rs"]
+mod flags;
+#[path = "aot/font.rs"]
+mod font;
+#[path = "aot/keypad.rs"]
+mod keypad;
+#[path = "aot/quirks_chip8.rs"]
+mod quirks_chip8;
+#[path = "aot/quirks_schip.rs"]
+mod quirks_schip;
+#[path = "aot/selfmod.rs"]
+mod selfmod;
+
+type RunFrame = fn(&mut Machine, usize) -> Result<(), MachineErr>;
+
+struct Case {
+    rom: &'static str,
+    platform: Platform,
+    module: &'static str,
+    machine: fn() -> Machine,
+    run_frame: RunFrame,
+}
+
+const CASES: &[Case] = &[
+    Case {
+        rom: "roms/flags.ch8",
+        platform: Platform::Chip8,
+        module: "flags.rs",
+        machine: flags::machine,
+        run_frame: flags::run_frame,
+    },
+    Case {
+        rom: "roms/font.ch8",
+        platform: Platform::Chip8,
+        module: "font.rs",
+        machine: font::machine,
+        run_frame: font::run_frame,
+    },
+    Case {
+        rom: "roms/keypad.ch8",
+        platform: Platform::Chip8,
+        module: "keypad.rs",
+        machine: keypad::machine,
+        run_frame: keypad::run_frame,
+    },
+    Case {
+        rom: "roms/quirks.ch8",
+        platform: Platform::Chip8,
+        module: "quirks_chip8.rs",
+        machine: quirks_chip8::machine,
+        run_frame: quirks_chip8::run_frame,
+    },
+    Case {
+        rom: "roms/quirks.ch8",
+        platform: Platform::SuperChip,
+        module: "quirks_schip.rs",
+        machine: quirks_schip::machine,
+        run_frame: quirks_schip::run_frame,
+    },
+    Case {
+        rom: "aot/selfmod.ch8",
+        platform: Platform::Chip8,
+        module: "selfmod.rs",
+        machine: selfmod::machine,
+        run_frame: selfmod::run_frame,
+    },
+];
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+// Everything a save state holds, without its checksum.
+fn snapshot(mach: &Machine) -> impl PartialEq + std::fmt::Debug + '_ {
+    let regs: Vec<u8> = all::<Reg>().map(|reg| mach.reg(reg)).collect();
+    (
+        (mach.pc(), mach.index(), mach.cycles(), regs, mach.stack()),
+        (mach.delay_timer(), mach.sound_timer()),
+        (mach.memory(), mach.display().rows()),
+    )
+}
+
+// Runs the recompiled module and the interpreter side by side with a changing
+// keypad and returns how many frames ran before the first fault.
+fn assert_lockstep(case: &Case, instructions: usize, frames: usize) -> usize {
+    let rom = fs::read(root().join(case.rom)).unwrap();
+    let mut reference = Machine::with_quirks(case.platform.quirks());
+    reference.load(&rom).unwrap();
+    let mut recompiled = (case.machine)();
+    for frame in 0..frames {
+        for mach in [&mut reference, &mut recompiled] {
+            mach.set_key(Key::from(frame as u8 / 4), frame % 8 < 5);
+        }
+        let expected = reference.run_frame(instructions);
+        let actual = (case.run_frame)(&mut recompiled, instructions);
+        assert_eq!(
+            expected.is_ok(),
+            actual.is_ok(),
+            "{} frame {}",
+            case.module,
+            frame
+        );
+        assert_eq!(
+            snapshot(&reference),
+            snapshot(&recompiled),
+            "{} frame {}",
+            case.module,
+            frame
+        );
+        if expected.is_err() {
+            return frame;
+        }
+    }
+    frames
+}
+
+#[test]
+fn modules_are_current() {
+    for case in CASES {
+        let rom = fs::read(root().join(case.rom)).unwrap();
+        let emitted = Program::discover(&rom, case.platform).unwrap().emit();
+        let path = root().join("aot").join(case.module);
+        if env::var_os("CHIP8_BLESS").is_some() {
+            fs::write(&path, emitted).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&path).unwrap();
+        assert!(
+            expected == emitted,
+            "{} is stale; rerun with CHIP8_BLESS=1",
+            case.module
+        );
+    }
+}
+
+#[test]
+fn recompiled_matches_interpreter() {
+    for case in CASES {
+        assert_lockstep(case, INSTRUCTIONS_PER_FRAME, 60);
+    }
+}
+
+// Small frames leave blocks that do not fit to the interpreter fallback.
+#[test]
+fn recompiled_matches_interpreter_with_short_frames() {
+    for case in CASES {
+        for instructions in [1, 2, 3, 7] {
+            assert_lockstep(case, instructions, 300);
+        }
+    }
+}
+
+#[test]
+fn self_modifying_code_and_indirect_jumps_fall_back() {
+    let case = CASES
+        .iter()
+        .find(|case| case.module == "selfmod.rs")
+        .unwrap();
+    assert_eq!(assert_lockstep(case, INSTRUCTIONS_PER_FRAME, 60), 60);
+    // `JP V0, 0x224` with V0 = 4 lands on 0x228, which discovery never
+    // reaches, so no block starts there.
+    let module = fs::read_to_string(root().join("aot").join(case.module)).unwrap();
+    assert!(module.contains("mach.execute(Command::JumpWithOffset(548, V0))?;"));
+    assert!(!module.contains("fn block_228"));
+    let mut mach = (case.machine)();
+    for _ in 0..60 {
+        (case.run_frame)(&mut mach, INSTRUCTIONS_PER_FRAME).unwrap();
+    }
+    // Eight passes over `ADD V1, 1`, then eight over the patched `ADD V1, 5`,
+    // then `ADD V3, 0x10` once behind the jump and a spin on 0x22a.
+    assert_eq!(mach.reg(Reg::V1), 8 + 8 * 5);
+    assert_eq!(mach.reg(Reg::V3), 0x10);
+    assert_eq!(mach.pc(), 0x22A);
+}
@@ -0,0 +1,342 @@
+//! Generated by `chip8emu --recompile`; do not edit.
+//!
+//! 9 blocks, 77 instructions, platform `chip8`.
+
+#![allow(dead_code, unused_imports)]
+
+use chip8emu::machine::{mach::Reg::*, Command, Key, Machine, MachineErr, Platform};
+
+pub const PLATFORM: Platform = Platform::Chip8;
+
+pub static ROM: [u8; 154] = [
+    0x00, 0xe0, 0x60, 0xf0, 0x64, 0x20, 0x6f, 0x00, 0x80, 0x44, 0x81, 0xf0, 0x6a, 0x00, 0x6b, 0x00,
+    0x22, 0x74, 0x60, 0x10, 0x64, 0x20, 0x6f, 0x01, 0x80, 0x44, 0x81, 0xf0, 0x6a, 0x14, 0x6b, 0x00,
+    0x22, 0x74, 0x60, 0x30, 0x64, 0x10, 0x6f, 0x00, 0x80, 0x45, 0x81, 0xf0, 0x6a, 0x28, 0x6b, 0x00,
+    0x22, 0x74, 0x60, 0x10, 0x64, 0x30, 0x6f, 0x00, 0x80, 0x45, 0x81, 0xf0, 0x6a, 0x00, 0x6b, 0x06,
+    0x22, 0x74, 0x60, 0x10, 0x64, 0x30, 0x6f, 0x00, 0x80, 0x47, 0x81, 0xf0, 0x6a, 0x14, 0x6b, 0x06,
+    0x22, 0x74, 0x60, 0x05, 0x64, 0x00, 0x6f, 0x00, 0x80, 0x06, 0x81, 0xf0, 0x6a, 0x28, 0x6b, 0x06,
+    0x22, 0x74, 0x60, 0x81, 0x64, 0x00, 0x6f, 0x00, 0x80, 0x0e, 0x81, 0xf0, 0x6a, 0x00, 0x6b, 0x0c,
+    0x22, 0x74, 0x12, 0x72, 0x82, 0x00, 0x63, 0xf0, 0x82, 0x32, 0x82, 0x26, 0x82, 0x26, 0x82, 0x26,
+    0x82, 0x26, 0xf2, 0x29, 0xda, 0xb5, 0x7a, 0x05, 0x82, 0x00, 0x63, 0x0f, 0x82, 0x32, 0xf2, 0x29,
+    0xda, 0xb5, 0x7a, 0x07, 0xf1, 0x29, 0xda, 0xb5, 0x00, 0xee,
+];
+
+/// A machine for `PLATFORM` with the ROM loaded.
+pub fn machine() -> Machine {
+    let mut mach = Machine::with_quirks(PLATFORM.quirks());
+    mach.load(&ROM).unwrap();
+    mach
+}
+
+/// Runs `instructions` instructions and ticks the timers, like
+/// `Machine::run_frame`.
+pub fn run_frame(mach: &mut Machine, instructions: usize) -> Result<(), MachineErr> {
+    let mut left = instructions;
+    while left > 0 {
+        left -= match mach.pc() {
+            0x200 if left >= 9 && unchanged(mach, 0x200, 0x212) => {
+                block_200(mach)?;
+                9
+            }
+            0x212 if left >= 8 && unchanged(mach, 0x212, 0x222) => {
+                block_212(mach)?;
+                8
+            }
+            0x222 if left >= 8 && unchanged(mach, 0x222, 0x232) => {
+                block_222(mach)?;
+                8
+            }
+            0x232 if left >= 8 && unchanged(mach, 0x232, 0x242) => {
+                block_232(mach)?;
+                8
+            }
+            0x242 if left >= 8 && unchanged(mach, 0x242, 0x252) => {
+                block_242(mach)?;
+                8
+            }
+            0x252 if left >= 8 && unchanged(mach, 0x252, 0x262) => {
+                block_252(mach)?;
+                8
+            }
+            0x262 if left >= 8 && unchanged(mach, 0x262, 0x272) => {
+                block_262(mach)?;
+                8
+            }
+            0x272 if left >= 1 && unchanged(mach, 0x272, 0x274) => {
+                block_272(mach)?;
+                1
+            }
+            0x274 if left >= 19 && unchanged(mach, 0x274, 0x29a) => {
+                block_274(mach)?;
+                19
+            }
+            _ => {
+                mach.step()?;
+                1
+            }
+        };
+    }
+    mach.tick_timers();
+    Ok(())
+}
+
+fn unchanged(mach: &Machine, start: usize, end: usize) -> bool {
+    mach.memory()[start..end] == ROM[start - 0x200..end - 0x200]
+}
+
+fn block_200(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x200: CLS
+    mach.execute(Command::ClearScreen)?;
+    // 0x202: LD V0, 0xf0
+    mach.set_reg(V0, 0xf0);
+    // 0x204: LD V4, 0x20
+    mach.set_reg(V4, 0x20);
+    // 0x206: LD VF, 0x00
+    mach.set_reg(VF, 0x00);
+    // 0x208: ADD V0, V4
+    {
+        let (result, overflow) = mach.reg(V0).overflowing_add(mach.reg(V4));
+        mach.set_reg(V0, result);
+        mach.set_reg(VF, overflow as u8);
+    }
+    // 0x20a: LD V1, VF
+    mach.set_reg(V1, mach.reg(VF));
+    // 0x20c: LD VA, 0x00
+    mach.set_reg(VA, 0x00);
+    // 0x20e: LD VB, 0x00
+    mach.set_reg(VB, 0x00);
+    // 0x210: CALL 0x274
+    mach.set_pc(0x210);
+    mach.retire(7);
+    mach.execute(Command::Call(628))?;
+    Ok(())
+}
+
+fn block_212(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x212: LD V0, 0x10
+    mach.set_reg(V0, 0x10);
+    // 0x214: LD V4, 0x20
+    mach.set_reg(V4, 0x20);
+    // 0x216: LD VF, 0x01
+    mach.set_reg(VF, 0x01);
+    // 0x218: ADD V0, V4
+    {
+        let (result, overflow) = mach.reg(V0).overflowing_add(mach.reg(V4));
+        mach.set_reg(V0, result);
+        mach.set_reg(VF, overflow as u8);
+    }
+    // 0x21a: LD V1, VF
+    mach.set_reg(V1, mach.reg(VF));
+    // 0x21c: LD VA, 0x14
+    mach.set_reg(VA, 0x14);
+    // 0x21e: LD VB, 0x00
+    mach.set_reg(VB, 0x00);
+    // 0x220: CALL 0x274
+    mach.set_pc(0x220);
+    mach.retire(7);
+    mach.execute(Command::Call(628))?;
+    Ok(())
+}
+
+fn block_222(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x222: LD V0, 0x30
+    mach.set_reg(V0, 0x30);
+    // 0x224: LD V4, 0x10
+    mach.set_reg(V4, 0x10);
+    // 0x226: LD VF, 0x00
+    mach.set_reg(VF, 0x00);
+    // 0x228: SUB V0, V4
+    {
+        let (result, overflow) = mach.reg(V0).overflowing_sub(mach.reg(V4));
+        mach.set_reg(V0, result);
+        mach.set_reg(VF, !overflow as u8);
+    }
+    // 0x22a: LD V1, VF
+    mach.set_reg(V1, mach.reg(VF));
+    // 0x22c: LD VA, 0x28
+    mach.set_reg(VA, 0x28);
+    // 0x22e: LD VB, 0x00
+    mach.set_reg(VB, 0x00);
+    // 0x230: CALL 0x274
+    mach.set_pc(0x230);
+    mach.retire(7);
+    mach.execute(Command::Call(628))?;
+    Ok(())
+}
+
+fn block_232(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x232: LD V0, 0x10
+    mach.set_reg(V0, 0x10);
+    // 0x234: LD V4, 0x30
+    mach.set_reg(V4, 0x30);
+    // 0x236: LD VF, 0x00
+    mach.set_reg(VF, 0x00);
+    // 0x238: SUB V0, V4
+    {
+        let (result, overflow) = mach.reg(V0).overflowing_sub(mach.reg(V4));
+        mach.set_reg(V0, result);
+        mach.set_reg(VF, !overflow as u8);
+    }
+    // 0x23a: LD V1, VF
+    mach.set_reg(V1, mach.reg(VF));
+    // 0x23c: LD VA, 0x00
+    mach.set_reg(VA, 0x00);
+    // 0x23e: LD VB, 0x06
+    mach.set_reg(VB, 0x06);
+    // 0x240: CALL 0x274
+    mach.set_pc(0x240);
+    mach.retire(7);
+    mach.execute(Command::Call(628))?;
+    Ok(())
+}
+
+fn block_242(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x242: LD V0, 0x10
+    mach.set_reg(V0, 0x10);
+    // 0x244: LD V4, 0x30
+    mach.set_reg(V4, 0x30);
+    // 0x246: LD VF, 0x00
+    mach.set_reg(VF, 0x00);
+    // 0x248: SUBN V0, V4
+    {
+        let (result, overflow) = mach.reg(V4).overflowing_sub(mach.reg(V0));
+        mach.set_reg(V0, result);
+        mach.set_reg(VF, !overflow as u8);
+    }
+    // 0x24a: LD V1, VF
+    mach.set_reg(V1, mach.reg(VF));
+    // 0x24c: LD VA, 0x14
+    mach.set_reg(VA, 0x14);
+    // 0x24e: LD VB, 0x06
+    mach.set_reg(VB, 0x06);
+    // 0x250: CALL 0x274
+    mach.set_pc(0x250);
+    mach.retire(7);
+    mach.execute(Command::Call(628))?;
+    Ok(())
+}
+
+fn block_252(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x252: LD V0, 0x05
+    mach.set_reg(V0, 0x05);
+    // 0x254: LD V4, 0x00
+    mach.set_reg(V4, 0x00);
+    // 0x256: LD VF, 0x00
+    mach.set_reg(VF, 0x00);
+    // 0x258: SHR V0, V0
+    {
+        let val = mach.reg(V0);
+        mach.set_reg(V0, val >> 1);
+        mach.set_reg(VF, val & 0x01);
+    }
+    // 0x25a: LD V1, VF
+    mach.set_reg(V1, mach.reg(VF));
+    // 0x25c: LD VA, 0x28
+    mach.set_reg(VA, 0x28);
+    // 0x25e: LD VB, 0x06
+    mach.set_reg(VB, 0x06);
+    // 0x260: CALL 0x274
+    mach.set_pc(0x260);
+    mach.retire(7);
+    mach.execute(Command::Call(628))?;
+    Ok(())
+}
+
+fn block_262(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x262: LD V0, 0x81
+    mach.set_reg(V0, 0x81);
+    // 0x264: LD V4, 0x00
+    mach.set_reg(V4, 0x00);
+    // 0x266: LD VF, 0x00
+    mach.set_reg(VF, 0x00);
+    // 0x268: SHL V0, V0
+    {
+        let val = mach.reg(V0);
+        mach.set_reg(V0, val << 1);
+        mach.set_reg(VF, val >> 7);
+    }
+    // 0x26a: LD V1, VF
+    mach.set_reg(V1, mach.reg(VF));
+    // 0x26c: LD VA, 0x00
+    mach.set_reg(VA, 0x00);
+    // 0x26e: LD VB, 0x0c
+    mach.set_reg(VB, 0x0c);
+    // 0x270: CALL 0x274
+    mach.set_pc(0x270);
+    mach.retire(7);
+    mach.execute(Command::Call(628))?;
+    Ok(())
+}
+
+fn block_272(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x272: JP 0x272
+    mach.retire(1);
+    mach.set_pc(0x272);
+    Ok(())
+}
+
+fn block_274(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x274: LD V2, V0
+    mach.set_reg(V2, mach.reg(V0));
+    // 0x276: LD V3, 0xf0
+    mach.set_reg(V3, 0xf0);
+    // 0x278: AND V2, V3
+    mach.set_reg(V2, mach.reg(V3) & mach.reg(V2));
+    mach.set_reg(VF, 0);
+    // 0x27a: SHR V2, V2
+    {
+        let val = mach.reg(V2);
+        mach.set_reg(V2, val >> 1);
+        mach.set_reg(VF, val & 0x01);
+    }
+    // 0x27c: SHR V2, V2
+    {
+        let val = mach.reg(V2);
+        mach.set_reg(V2, val >> 1);
+        mach.set_reg(VF, val & 0x01);
+    }
+    // 0x27e: SHR V2, V2
+    {
+        let val = mach.reg(V2);
+        mach.set_reg(V2, val >> 1);
+        mach.set_reg(VF, val & 0x01);
+    }
+    // 0x280: SHR V2, V2
+    {
+        let val = mach.reg(V2);
+        mach.set_reg(V2, val >> 1);
+        mach.set_reg(VF, val & 0x01);
+    }
+    // 0x282: LD F, V2
+    mach.set_pc(0x282);
+    mach.retire(7);
+    mach.execute(Command::Font(V2))?;
+    // 0x284: DRW VA, VB, 0x5
+    mach.execute(Command::Display(VA, VB, 5))?;
+    // 0x286: ADD VA, 0x05
+    mach.set_reg(VA, mach.reg(VA).wrapping_add(0x05));
+    // 0x288: LD V2, V0
+    mach.set_reg(V2, mach.reg(V0));
+    // 0x28a: LD V3, 0x0f
+    mach.set_reg(V3, 0x0f);
+    // 0x28c: AND V2, V3
+    mach.set_reg(V2, mach.reg(V3) & mach.reg(V2));
+    mach.set_reg(VF, 0);
+    // 0x28e: LD F, V2
+    mach.set_pc(0x28e);
+    mach.retire(4);
+    mach.execute(Command::Font(V2))?;
+    // 0x290: DRW VA, VB, 0x5
+    mach.execute(Command::Display(VA, VB, 5))?;
+    // 0x292: ADD VA, 0x07
+    mach.set_reg(VA, mach.reg(VA).wrapping_add(0x07));
+    // 0x294: LD F, V1
+    mach.set_pc(0x294);
+    mach.retire(1);
+    mach.execute(Command::Font(V1))?;
+    // 0x296: DRW VA, VB, 0x5
+    mach.execute(Command::Display(VA, VB, 5))?;
+    // 0x298: RET
+    mach.execute(Command::Return)?;
+    Ok(())
+}
@@ -0,0 +1,142 @@
+//! Generated by `chip8emu --recompile`; do not edit.
+//!
+//! 7 blocks, 14 instructions, platform `chip8`.
+
+#![allow(dead_code, unused_imports)]
+
+use chip8emu::machine::{mach::Reg::*, Command, Key, Machine, MachineErr, Platform};
+
+pub const PLATFORM: Platform = Platform::Chip8;
+
+pub static ROM: [u8; 28] = [
+    0x60, 0x00, 0x6a, 0x00, 0x6b, 0x00, 0xf0, 0x29, 0xda, 0xb5, 0x7a, 0x08, 0x70, 0x01, 0x3a, 0x40,
+    0x12, 0x16, 0x6a, 0x00, 0x7b, 0x08, 0x30, 0x10, 0x12, 0x06, 0x12, 0x1a,
+];
+
+/// A machine for `PLATFORM` with the ROM loaded.
+pub fn machine() -> Machine {
+    let mut mach = Machine::with_quirks(PLATFORM.quirks());
+    mach.load(&ROM).unwrap();
+    mach
+}
+
+/// Runs `instructions` instructions and ticks the timers, like
+/// `Machine::run_frame`.
+pub fn run_frame(mach: &mut Machine, instructions: usize) -> Result<(), MachineErr> {
+    let mut left = instructions;
+    while left > 0 {
+        left -= match mach.pc() {
+            0x200 if left >= 3 && unchanged(mach, 0x200, 0x206) => {
+                block_200(mach)?;
+                3
+            }
+            0x206 if left >= 5 && unchanged(mach, 0x206, 0x210) => {
+                block_206(mach)?;
+                5
+            }
+            0x210 if left >= 1 && unchanged(mach, 0x210, 0x212) => {
+                block_210(mach)?;
+                1
+            }
+            0x212 if left >= 2 && unchanged(mach, 0x212, 0x216) => {
+                block_212(mach)?;
+                2
+            }
+            0x216 if left >= 1 && unchanged(mach, 0x216, 0x218) => {
+                block_216(mach)?;
+                1
+            }
+            0x218 if left >= 1 && unchanged(mach, 0x218, 0x21a) => {
+                block_218(mach)?;
+                1
+            }
+            0x21a if left >= 1 && unchanged(mach, 0x21a, 0x21c) => {
+                block_21a(mach)?;
+                1
+            }
+            _ => {
+                mach.step()?;
+                1
+            }
+        };
+    }
+    mach.tick_timers();
+    Ok(())
+}
+
+fn unchanged(mach: &Machine, start: usize, end: usize) -> bool {
+    mach.memory()[start..end] == ROM[start - 0x200..end - 0x200]
+}
+
+fn block_200(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x200: LD V0, 0x00
+    mach.set_reg(V0, 0x00);
+    // 0x202: LD VA, 0x00
+    mach.set_reg(VA, 0x00);
+    // 0x204: LD VB, 0x00
+    mach.set_reg(VB, 0x00);
+    mach.set_pc(0x206);
+    mach.retire(3);
+    Ok(())
+}
+
+fn block_206(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x206: LD F, V0
+    mach.execute(Command::Font(V0))?;
+    // 0x208: DRW VA, VB, 0x5
+    mach.execute(Command::Display(VA, VB, 5))?;
+    // 0x20a: ADD VA, 0x08
+    mach.set_reg(VA, mach.reg(VA).wrapping_add(0x08));
+    // 0x20c: ADD V0, 0x01
+    mach.set_reg(V0, mach.reg(V0).wrapping_add(0x01));
+    // 0x20e: SE VA, 0x40
+    mach.retire(3);
+    if mach.reg(VA) == 0x40 {
+        mach.set_pc(0x212);
+    } else {
+        mach.set_pc(0x210);
+    }
+    Ok(())
+}
+
+fn block_210(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x210: JP 0x216
+    mach.retire(1);
+    mach.set_pc(0x216);
+    Ok(())
+}
+
+fn block_212(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x212: LD VA, 0x00
+    mach.set_reg(VA, 0x00);
+    // 0x214: ADD VB, 0x08
+    mach.set_reg(VB, mach.reg(VB).wrapping_add(0x08));
+    mach.set_pc(0x216);
+    mach.retire(2);
+    Ok(())
+}
+
+fn block_216(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x216: SE V0, 0x10
+    mach.retire(1);
+    if mach.reg(V0) == 0x10 {
+        mach.set_pc(0x21a);
+    } else {
+        mach.set_pc(0x218);
+    }
+    Ok(())
+}
+
+fn block_218(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x218: JP 0x206
+    mach.retire(1);
+    mach.set_pc(0x206);
+    Ok(())
+}
+
+fn block_21a(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x21a: JP 0x21a
+    mach.retire(1);
+    mach.set_pc(0x21a);
+    Ok(())
+}
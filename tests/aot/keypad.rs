@@ -0,0 +1,79 @@
+//! Generated by `chip8emu --recompile`; do not edit.
+//!
+//! 3 blocks, 6 instructions, platform `chip8`.
+
+#![allow(dead_code, unused_imports)]
+
+use chip8emu::machine::{mach::Reg::*, Command, Key, Machine, MachineErr, Platform};
+
+pub const PLATFORM: Platform = Platform::Chip8;
+
+pub static ROM: [u8; 12] = [
+    0xf0, 0x0a, 0xf0, 0x29, 0x6a, 0x1c, 0x6b, 0x0d, 0xda, 0xb5, 0x12, 0x0a,
+];
+
+/// A machine for `PLATFORM` with the ROM loaded.
+pub fn machine() -> Machine {
+    let mut mach = Machine::with_quirks(PLATFORM.quirks());
+    mach.load(&ROM).unwrap();
+    mach
+}
+
+/// Runs `instructions` instructions and ticks the timers, like
+/// `Machine::run_frame`.
+pub fn run_frame(mach: &mut Machine, instructions: usize) -> Result<(), MachineErr> {
+    let mut left = instructions;
+    while left > 0 {
+        left -= match mach.pc() {
+            0x200 if left >= 1 && unchanged(mach, 0x200, 0x202) => {
+                block_200(mach)?;
+                1
+            }
+            0x202 if left >= 4 && unchanged(mach, 0x202, 0x20a) => {
+                block_202(mach)?;
+                4
+            }
+            0x20a if left >= 1 && unchanged(mach, 0x20a, 0x20c) => {
+                block_20a(mach)?;
+                1
+            }
+            _ => {
+                mach.step()?;
+                1
+            }
+        };
+    }
+    mach.tick_timers();
+    Ok(())
+}
+
+fn unchanged(mach: &Machine, start: usize, end: usize) -> bool {
+    mach.memory()[start..end] == ROM[start - 0x200..end - 0x200]
+}
+
+fn block_200(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x200: LD V0, K
+    mach.execute(Command::GetKey(V0))?;
+    Ok(())
+}
+
+fn block_202(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x202: LD F, V0
+    mach.execute(Command::Font(V0))?;
+    // 0x204: LD VA, 0x1c
+    mach.set_reg(VA, 0x1c);
+    // 0x206: LD VB, 0x0d
+    mach.set_reg(VB, 0x0d);
+    // 0x208: DRW VA, VB, 0x5
+    mach.set_pc(0x208);
+    mach.retire(2);
+    mach.execute(Command::Display(VA, VB, 5))?;
+    Ok(())
+}
+
+fn block_20a(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x20a: JP 0x20a
+    mach.retire(1);
+    mach.set_pc(0x20a);
+    Ok(())
+}
@@ -0,0 +1,241 @@
+//! Generated by `chip8emu --recompile`; do not edit.
+//!
+//! 8 blocks, 45 instructions, platform `schip`.
+
+#![allow(dead_code, unused_imports)]
+
+use chip8emu::machine::{mach::Reg::*, Command, Key, Machine, MachineErr, Platform};
+
+pub const PLATFORM: Platform = Platform::SuperChip;
+
+pub static ROM: [u8; 94] = [
+    0x60, 0x3c, 0x61, 0x00, 0xa2, 0x5a, 0xd0, 0x11, 0x60, 0x00, 0xa2, 0x5b, 0xd0, 0x11, 0x68, 0x01,
+    0x4f, 0x01, 0x68, 0x00, 0x00, 0xe0, 0x6f, 0x05, 0x60, 0x01, 0x61, 0x02, 0x80, 0x11, 0x82, 0xf0,
+    0x65, 0x00, 0x42, 0x00, 0x65, 0x01, 0xa2, 0x5c, 0xf0, 0x65, 0xf0, 0x65, 0x82, 0x00, 0x66, 0x00,
+    0x42, 0x22, 0x66, 0x01, 0x60, 0x01, 0x61, 0x02, 0x80, 0x16, 0x87, 0x00, 0x6a, 0x00, 0x6b, 0x00,
+    0xf5, 0x29, 0xda, 0xb5, 0x7a, 0x06, 0xf6, 0x29, 0xda, 0xb5, 0x7a, 0x06, 0xf7, 0x29, 0xda, 0xb5,
+    0x7a, 0x06, 0xf8, 0x29, 0xda, 0xb5, 0x7a, 0x06, 0x12, 0x58, 0xff, 0x80, 0x11, 0x22,
+];
+
+/// A machine for `PLATFORM` with the ROM loaded.
+pub fn machine() -> Machine {
+    let mut mach = Machine::with_quirks(PLATFORM.quirks());
+    mach.load(&ROM).unwrap();
+    mach
+}
+
+/// Runs `instructions` instructions and ticks the timers, like
+/// `Machine::run_frame`.
+pub fn run_frame(mach: &mut Machine, instructions: usize) -> Result<(), MachineErr> {
+    let mut left = instructions;
+    while left > 0 {
+        left -= match mach.pc() {
+            0x200 if left >= 9 && unchanged(mach, 0x200, 0x212) => {
+                block_200(mach)?;
+                9
+            }
+            0x212 if left >= 1 && unchanged(mach, 0x212, 0x214) => {
+                block_212(mach)?;
+                1
+            }
+            0x214 if left >= 8 && unchanged(mach, 0x214, 0x224) => {
+                block_214(mach)?;
+                8
+            }
+            0x224 if left >= 1 && unchanged(mach, 0x224, 0x226) => {
+                block_224(mach)?;
+                1
+            }
+            0x226 if left >= 6 && unchanged(mach, 0x226, 0x232) => {
+                block_226(mach)?;
+                6
+            }
+            0x232 if left >= 1 && unchanged(mach, 0x232, 0x234) => {
+                block_232(mach)?;
+                1
+            }
+            0x234 if left >= 18 && unchanged(mach, 0x234, 0x258) => {
+                block_234(mach)?;
+                18
+            }
+            0x258 if left >= 1 && unchanged(mach, 0x258, 0x25a) => {
+                block_258(mach)?;
+                1
+            }
+            _ => {
+                mach.step()?;
+                1
+            }
+        };
+    }
+    mach.tick_timers();
+    Ok(())
+}
+
+fn unchanged(mach: &Machine, start: usize, end: usize) -> bool {
+    mach.memory()[start..end] == ROM[start - 0x200..end - 0x200]
+}
+
+fn block_200(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x200: LD V0, 0x3c
+    mach.set_reg(V0, 0x3c);
+    // 0x202: LD V1, 0x00
+    mach.set_reg(V1, 0x00);
+    // 0x204: LD I, 0x25a
+    mach.set_index(0x25a);
+    // 0x206: DRW V0, V1, 0x1
+    mach.set_pc(0x206);
+    mach.retire(3);
+    mach.execute(Command::Display(V0, V1, 1))?;
+    // 0x208: LD V0, 0x00
+    mach.set_reg(V0, 0x00);
+    // 0x20a: LD I, 0x25b
+    mach.set_index(0x25b);
+    // 0x20c: DRW V0, V1, 0x1
+    mach.set_pc(0x20c);
+    mach.retire(2);
+    mach.execute(Command::Display(V0, V1, 1))?;
+    // 0x20e: LD V8, 0x01
+    mach.set_reg(V8, 0x01);
+    // 0x210: SNE VF, 0x01
+    mach.retire(2);
+    if mach.reg(VF) != 0x01 {
+        mach.set_pc(0x214);
+    } else {
+        mach.set_pc(0x212);
+    }
+    Ok(())
+}
+
+fn block_212(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x212: LD V8, 0x00
+    mach.set_reg(V8, 0x00);
+    mach.set_pc(0x214);
+    mach.retire(1);
+    Ok(())
+}
+
+fn block_214(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x214: CLS
+    mach.execute(Command::ClearScreen)?;
+    // 0x216: LD VF, 0x05
+    mach.set_reg(VF, 0x05);
+    // 0x218: LD V0, 0x01
+    mach.set_reg(V0, 0x01);
+    // 0x21a: LD V1, 0x02
+    mach.set_reg(V1, 0x02);
+    // 0x21c: OR V0, V1
+    mach.set_reg(V0, mach.reg(V1) | mach.reg(V0));
+    // 0x21e: LD V2, VF
+    mach.set_reg(V2, mach.reg(VF));
+    // 0x220: LD V5, 0x00
+    mach.set_reg(V5, 0x00);
+    // 0x222: SNE V2, 0x00
+    mach.retire(7);
+    if mach.reg(V2) != 0x00 {
+        mach.set_pc(0x226);
+    } else {
+        mach.set_pc(0x224);
+    }
+    Ok(())
+}
+
+fn block_224(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x224: LD V5, 0x01
+    mach.set_reg(V5, 0x01);
+    mach.set_pc(0x226);
+    mach.retire(1);
+    Ok(())
+}
+
+fn block_226(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x226: LD I, 0x25c
+    mach.set_index(0x25c);
+    // 0x228: LD V0, [I]
+    mach.set_pc(0x228);
+    mach.retire(1);
+    mach.execute(Command::Load(V0))?;
+    // 0x22a: LD V0, [I]
+    mach.execute(Command::Load(V0))?;
+    // 0x22c: LD V2, V0
+    mach.set_reg(V2, mach.reg(V0));
+    // 0x22e: LD V6, 0x00
+    mach.set_reg(V6, 0x00);
+    // 0x230: SNE V2, 0x22
+    mach.retire(3);
+    if mach.reg(V2) != 0x22 {
+        mach.set_pc(0x234);
+    } else {
+        mach.set_pc(0x232);
+    }
+    Ok(())
+}
+
+fn block_232(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x232: LD V6, 0x01
+    mach.set_reg(V6, 0x01);
+    mach.set_pc(0x234);
+    mach.retire(1);
+    Ok(())
+}
+
+fn block_234(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x234: LD V0, 0x01
+    mach.set_reg(V0, 0x01);
+    // 0x236: LD V1, 0x02
+    mach.set_reg(V1, 0x02);
+    // 0x238: SHR V0, V1
+    {
+        let val = mach.reg(V0);
+        mach.set_reg(V0, val >> 1);
+        mach.set_reg(VF, val & 0x01);
+    }
+    // 0x23a: LD V7, V0
+    mach.set_reg(V7, mach.reg(V0));
+    // 0x23c: LD VA, 0x00
+    mach.set_reg(VA, 0x00);
+    // 0x23e: LD VB, 0x00
+    mach.set_reg(VB, 0x00);
+    // 0x240: LD F, V5
+    mach.set_pc(0x240);
+    mach.retire(6);
+    mach.execute(Command::Font(V5))?;
+    // 0x242: DRW VA, VB, 0x5
+    mach.execute(Command::Display(VA, VB, 5))?;
+    // 0x244: ADD VA, 0x06
+    mach.set_reg(VA, mach.reg(VA).wrapping_add(0x06));
+    // 0x246: LD F, V6
+    mach.set_pc(0x246);
+    mach.retire(1);
+    mach.execute(Command::Font(V6))?;
+    // 0x248: DRW VA, VB, 0x5
+    mach.execute(Command::Display(VA, VB, 5))?;
+    // 0x24a: ADD VA, 0x06
+    mach.set_reg(VA, mach.reg(VA).wrapping_add(0x06));
+    // 0x24c: LD F, V7
+    mach.set_pc(0x24c);
+    mach.retire(1);
+    mach.execute(Command::Font(V7))?;
+    // 0x24e: DRW VA, VB, 0x5
+    mach.execute(Command::Display(VA, VB, 5))?;
+    // 0x250: ADD VA, 0x06
+    mach.set_reg(VA, mach.reg(VA).wrapping_add(0x06));
+    // 0x252: LD F, V8
+    mach.set_pc(0x252);
+    mach.retire(1);
+    mach.execute(Command::Font(V8))?;
+    // 0x254: DRW VA, VB, 0x5
+    mach.execute(Command::Display(VA, VB, 5))?;
+    // 0x256: ADD VA, 0x06
+    mach.set_reg(VA, mach.reg(VA).wrapping_add(0x06));
+    mach.set_pc(0x258);
+    mach.retire(1);
+    Ok(())
+}
+
+fn block_258(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x258: JP 0x258
+    mach.retire(1);
+    mach.set_pc(0x258);
+    Ok(())
+}
@@ -0,0 +1,162 @@
+//! Generated by `chip8emu --recompile`; do not edit.
+//!
+//! 9 blocks, 15 instructions, platform `chip8`.
+
+#![allow(dead_code, unused_imports)]
+
+use chip8emu::machine::{mach::Reg::*, Command, Key, Machine, MachineErr, Platform};
+
+pub const PLATFORM: Platform = Platform::Chip8;
+
+pub static ROM: [u8; 44] = [
+    0x61, 0x00, 0x62, 0x00, 0x71, 0x01, 0x72, 0x01, 0x32, 0x08, 0x12, 0x0e, 0x22, 0x20, 0x32, 0x10,
+    0x12, 0x04, 0x60, 0x04, 0xb2, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x60, 0x05, 0xa2, 0x05, 0xf0, 0x55, 0x00, 0xee, 0x73, 0x10, 0x12, 0x2a,
+];
+
+/// A machine for `PLATFORM` with the ROM loaded.
+pub fn machine() -> Machine {
+    let mut mach = Machine::with_quirks(PLATFORM.quirks());
+    mach.load(&ROM).unwrap();
+    mach
+}
+
+/// Runs `instructions` instructions and ticks the timers, like
+/// `Machine::run_frame`.
+pub fn run_frame(mach: &mut Machine, instructions: usize) -> Result<(), MachineErr> {
+    let mut left = instructions;
+    while left > 0 {
+        left -= match mach.pc() {
+            0x200 if left >= 2 && unchanged(mach, 0x200, 0x204) => {
+                block_200(mach)?;
+                2
+            }
+            0x204 if left >= 3 && unchanged(mach, 0x204, 0x20a) => {
+                block_204(mach)?;
+                3
+            }
+            0x20a if left >= 1 && unchanged(mach, 0x20a, 0x20c) => {
+                block_20a(mach)?;
+                1
+            }
+            0x20c if left >= 1 && unchanged(mach, 0x20c, 0x20e) => {
+                block_20c(mach)?;
+                1
+            }
+            0x20e if left >= 1 && unchanged(mach, 0x20e, 0x210) => {
+                block_20e(mach)?;
+                1
+            }
+            0x210 if left >= 1 && unchanged(mach, 0x210, 0x212) => {
+                block_210(mach)?;
+                1
+            }
+            0x212 if left >= 2 && unchanged(mach, 0x212, 0x216) => {
+                block_212(mach)?;
+                2
+            }
+            0x220 if left >= 3 && unchanged(mach, 0x220, 0x226) => {
+                block_220(mach)?;
+                3
+            }
+            0x226 if left >= 1 && unchanged(mach, 0x226, 0x228) => {
+                block_226(mach)?;
+                1
+            }
+            _ => {
+                mach.step()?;
+                1
+            }
+        };
+    }
+    mach.tick_timers();
+    Ok(())
+}
+
+fn unchanged(mach: &Machine, start: usize, end: usize) -> bool {
+    mach.memory()[start..end] == ROM[start - 0x200..end - 0x200]
+}
+
+fn block_200(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x200: LD V1, 0x00
+    mach.set_reg(V1, 0x00);
+    // 0x202: LD V2, 0x00
+    mach.set_reg(V2, 0x00);
+    mach.set_pc(0x204);
+    mach.retire(2);
+    Ok(())
+}
+
+fn block_204(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x204: ADD V1, 0x01
+    mach.set_reg(V1, mach.reg(V1).wrapping_add(0x01));
+    // 0x206: ADD V2, 0x01
+    mach.set_reg(V2, mach.reg(V2).wrapping_add(0x01));
+    // 0x208: SE V2, 0x08
+    mach.retire(3);
+    if mach.reg(V2) == 0x08 {
+        mach.set_pc(0x20c);
+    } else {
+        mach.set_pc(0x20a);
+    }
+    Ok(())
+}
+
+fn block_20a(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x20a: JP 0x20e
+    mach.retire(1);
+    mach.set_pc(0x20e);
+    Ok(())
+}
+
+fn block_20c(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x20c: CALL 0x220
+    mach.execute(Command::Call(544))?;
+    Ok(())
+}
+
+fn block_20e(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x20e: SE V2, 0x10
+    mach.retire(1);
+    if mach.reg(V2) == 0x10 {
+        mach.set_pc(0x212);
+    } else {
+        mach.set_pc(0x210);
+    }
+    Ok(())
+}
+
+fn block_210(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x210: JP 0x204
+    mach.retire(1);
+    mach.set_pc(0x204);
+    Ok(())
+}
+
+fn block_212(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x212: LD V0, 0x04
+    mach.set_reg(V0, 0x04);
+    // 0x214: JP V0, 0x224
+    mach.set_pc(0x214);
+    mach.retire(1);
+    mach.execute(Command::JumpWithOffset(548, V0))?;
+    Ok(())
+}
+
+fn block_220(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x220: LD V0, 0x05
+    mach.set_reg(V0, 0x05);
+    // 0x222: LD I, 0x205
+    mach.set_index(0x205);
+    // 0x224: LD [I+], V0
+    mach.set_pc(0x224);
+    mach.retire(2);
+    mach.execute(Command::StoreWithIndexIncrement(V0))?;
+    Ok(())
+}
+
+fn block_226(mach: &mut Machine) -> Result<(), MachineErr> {
+    // 0x226: RET
+    mach.execute(Command::Return)?;
+    Ok(())
+}
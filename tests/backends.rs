@@ -0,0 +1,110 @@
+//! Differential tests: the threaded and JIT backends against the reference
+//! interpreter, compared after every frame and through save states at the end.
+
+use std::{fs, path::PathBuf};
+
+use chip8emu::machine::{Backend, Key, Machine, Platform, Reg, INSTRUCTIONS_PER_FRAME};
+use enum_iterator::all;
+
+mod common;
+
+use common::{program, Rng};
+
+fn machine(image: &[u8], platform: Platform, strict_memory: bool, backend: Backend) -> Machine {
+    let mut mach = Machine::with_quirks(platform.quirks());
+    mach.set_strict_memory(strict_memory);
+    mach.set_backend(backend);
+    mach.load(image).unwrap();
+    mach
+}
+
+const BACKENDS: &[Backend] = &[
+    Backend::Threaded,
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    Backend::Jit,
+];
+
+// Runs `image` on every backend with a changing keypad, comparing each with
+// the interpreter after every frame, and returns how many frames ran before
+// the first fault.
+fn assert_same(image: &[u8], platform: Platform, strict_memory: bool, frames: usize) -> usize {
+    BACKENDS
+        .iter()
+        .map(|&backend| assert_backend(image, platform, strict_memory, frames, backend))
+        .min()
+        .unwrap()
+}
+
+fn assert_backend(
+    image: &[u8],
+    platform: Platform,
+    strict_memory: bool,
+    frames: usize,
+    backend: Backend,
+) -> usize {
+    let mut reference = machine(image, platform, strict_memory, Backend::Interpreter);
+    let mut candidate = machine(image, platform, strict_memory, backend);
+    assert_eq!(candidate.backend(), backend);
+    for frame in 0..frames {
+        for mach in [&mut reference, &mut candidate] {
+            mach.set_key(Key::from(frame as u8 / 4), frame % 8 < 5);
+        }
+        let expected = reference.run_frame(INSTRUCTIONS_PER_FRAME);
+        let actual = candidate.run_frame(INSTRUCTIONS_PER_FRAME);
+        assert_eq!(
+            expected.is_ok(),
+            actual.is_ok(),
+            "{:?} frame {}",
+            backend,
+            frame
+        );
+        assert_eq!(
+            snapshot(&reference),
+            snapshot(&candidate),
+            "{:?} frame {}",
+            backend,
+            frame
+        );
+        if expected.is_err() {
+            assert_eq!(reference.save_state(), candidate.save_state());
+            return frame;
+        }
+    }
+    assert_eq!(reference.save_state(), candidate.save_state());
+    frames
+}
+
+// Everything a save state holds, without its checksum.
+fn snapshot(mach: &Machine) -> impl PartialEq + std::fmt::Debug + '_ {
+    let regs: Vec<u8> = all::<Reg>().map(|reg| mach.reg(reg)).collect();
+    (
+        (mach.pc(), mach.index(), mach.cycles(), regs, mach.stack()),
+        (mach.delay_timer(), mach.sound_timer()),
+        (mach.memory(), mach.display().rows()),
+    )
+}
+
+#[test]
+fn conformance_roms_match_the_interpreter() {
+    let roms = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms");
+    for name in ["font.ch8", "flags.ch8", "quirks.ch8", "keypad.ch8"] {
+        let image = fs::read(roms.join(name)).unwrap();
+        for platform in [Platform::Chip8, Platform::SuperChip, Platform::XoChip] {
+            assert_eq!(assert_same(&image, platform, false, 300), 300, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn generated_programs_match_the_interpreter() {
+    let mut rng = Rng(0x5EED_C8C8);
+    let mut frames = 0;
+    for case in 0..300 {
+        let len = 8 + rng.next() % 56;
+        let image = program(&mut rng, len);
+        let platform = [Platform::Chip8, Platform::SuperChip, Platform::XoChip][case % 3];
+        frames += assert_same(&image, platform, case % 2 == 0, 60);
+    }
+    // Guards against the generator producing nothing but instant faults.
+    assert!(frames > 300 * 10, "only {} frames ran", frames);
+}
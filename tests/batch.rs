@@ -0,0 +1,169 @@
+//! Differential tests: `MachineBatch` lanes against independent machines, and
+//! the threaded executor against the single-threaded batch.
+
+use chip8emu::{
+    batch::Executor,
+    machine::{
+        CloneRandomSource, Key, Machine, MachineBatch, Platform, Reg, SeededRandom,
+        INSTRUCTIONS_PER_FRAME,
+    },
+};
+use enum_iterator::all;
+
+mod common;
+
+use common::{program, Rng};
+
+const LANES: usize = 8;
+
+fn seeded(lane: usize) -> Box<dyn CloneRandomSource> {
+    Box::new(SeededRandom::new(lane as u64 + 1))
+}
+
+fn keys(frame: usize, lanes: usize) -> Vec<u16> {
+    (0..lanes)
+        .map(|lane| {
+            if (frame + lane) % 8 < 5 {
+                1 << ((frame / 4 + lane) % 16)
+            } else {
+                0
+            }
+        })
+        .collect()
+}
+
+// Runs a different generated program on every lane, comparing each lane with
+// its own machine after every frame, and returns how many lane-frames ran
+// before faulting.
+fn assert_lanes(
+    images: &[Vec<u8>],
+    platform: Platform,
+    strict_memory: bool,
+    frames: usize,
+) -> usize {
+    let mut batch = MachineBatch::new(images.len(), platform.quirks(), seeded);
+    batch.set_strict_memory(strict_memory);
+    let mut machines = Vec::new();
+    for (lane, image) in images.iter().enumerate() {
+        batch.load_lane(lane, image).unwrap();
+        let mut mach = Machine::with_quirks(platform.quirks());
+        mach.set_strict_memory(strict_memory);
+        mach.set_random(seeded(lane));
+        mach.load(image).unwrap();
+        machines.push(mach);
+    }
+    let mut faulted = vec![false; images.len()];
+    let mut ran = 0;
+    for frame in 0..frames {
+        let keys = keys(frame, images.len());
+        for (lane, mach) in machines.iter_mut().enumerate() {
+            for key in 0..16 {
+                mach.set_key(Key::from(key), keys[lane] & (1 << key) != 0);
+            }
+            if faulted[lane] {
+                continue;
+            }
+            match mach.run_frame(INSTRUCTIONS_PER_FRAME) {
+                Ok(()) => ran += 1,
+                Err(_) => faulted[lane] = true,
+            }
+        }
+        let faults: Vec<bool> = batch
+            .run_frame(&keys, INSTRUCTIONS_PER_FRAME)
+            .unwrap()
+            .map(|observation| observation.fault)
+            .collect();
+        assert_eq!(faults, faulted, "frame {}", frame);
+        for (lane, mach) in machines.iter().enumerate() {
+            let regs: Vec<u8> = all::<Reg>().map(|reg| mach.reg(reg)).collect();
+            assert_eq!(
+                (mach.pc(), mach.index(), mach.cycles(), &regs[..]),
+                (
+                    batch.pcs()[lane],
+                    batch.indexes()[lane],
+                    batch.cycles()[lane],
+                    &batch.regs()[lane][..]
+                ),
+                "lane {} frame {}",
+                lane,
+                frame
+            );
+            assert_eq!(
+                mach.display().rows(),
+                batch.displays()[lane].rows(),
+                "lane {} frame {}",
+                lane,
+                frame
+            );
+        }
+    }
+    for (lane, mach) in machines.iter().enumerate() {
+        assert!(
+            mach.save_state() == batch.machine(lane).save_state(),
+            "lane {}",
+            lane
+        );
+    }
+    ran
+}
+
+#[test]
+fn generated_programs_match_independent_machines() {
+    let mut rng = Rng(0xBA7C_C8C8);
+    let mut ran = 0;
+    for case in 0..40 {
+        let images: Vec<Vec<u8>> = (0..LANES)
+            .map(|_| {
+                let len = 8 + rng.next() % 56;
+                program(&mut rng, len)
+            })
+            .collect();
+        let platform = [Platform::Chip8, Platform::SuperChip, Platform::XoChip][case % 3];
+        ran += assert_lanes(&images, platform, case % 2 == 0, 60);
+    }
+    // Guards against the generator producing nothing but instant faults.
+    assert!(ran > 40 * LANES * 10, "only {} lane-frames ran", ran);
+}
+
+#[test]
+fn executor_matches_the_single_threaded_batch() {
+    let mut rng = Rng(0x7E4D_C8C8);
+    let mut batch = MachineBatch::new(67, Platform::Chip8.quirks(), seeded);
+    for lane in 0..batch.len() {
+        let len = 8 + rng.next() % 56;
+        batch.load_lane(lane, &program(&mut rng, len)).unwrap();
+    }
+    let keys = keys(0, batch.len());
+    let mut reference = batch.clone();
+    for _ in 0..30 {
+        let _ = reference.run_frame(&keys, INSTRUCTIONS_PER_FRAME).unwrap();
+    }
+    for threads in [1, 3, 8, 100] {
+        let mut threaded = batch.clone();
+        let executor = Executor::new().with_threads(threads);
+        executor
+            .run_frames(&mut threaded, &keys, INSTRUCTIONS_PER_FRAME, 20)
+            .unwrap();
+        for _ in 0..10 {
+            let _ = executor
+                .run_frame(&mut threaded, &keys, INSTRUCTIONS_PER_FRAME)
+                .unwrap();
+        }
+        for lane in 0..batch.len() {
+            assert!(
+                reference.machine(lane).save_state() == threaded.machine(lane).save_state(),
+                "lane {} with {} threads",
+                lane,
+                threads
+            );
+        }
+    }
+}
+
+#[test]
+fn executor_rejects_a_short_key_array() {
+    let mut batch = MachineBatch::new(4, Platform::Chip8.quirks(), seeded);
+    assert!(Executor::new()
+        .run_frame(&mut batch, &[0; 3], INSTRUCTIONS_PER_FRAME)
+        .is_err());
+}
@@ -0,0 +1,50 @@
+//! Helpers shared by the differential tests.
+
+// xorshift64, so the generated programs are the same on every run.
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u16 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 32) as u16
+    }
+}
+
+// Mostly valid instructions whose jumps, calls and index loads stay inside
+// the program, so control flow keeps re-entering blocks mid-way and stores
+// land on code. `BNNN` starts inside it too, but the register it adds can
+// carry it past the end or onto an odd address.
+pub fn program(rng: &mut Rng, len: u16) -> Vec<u8> {
+    let mut image = Vec::new();
+    for _ in 0..len {
+        let target = 0x200 + rng.next() % len * 2;
+        let x = rng.next() & 0x0F00;
+        let xy = rng.next() & 0x0FF0;
+        let opcode = match rng.next() % 16 {
+            // 8XYF does not decode, so it stands in for the invalid opcodes
+            // next to an empty stack's 00EE.
+            0 => [0x00E0, 0x00E0, 0x00E0, 0x00E0, 0x00E0, 0x00EE, 0x800F][rng.next() as usize % 7],
+            // Calls are rarer than jumps so the stack seldom overflows.
+            2 if rng.next().is_multiple_of(4) => 0x2000 | target,
+            1 | 2 => 0x1000 | target,
+            0xA => 0xA000 | target,
+            nibble @ (5 | 9) => nibble << 12 | xy,
+            8 => {
+                let low = [0x0, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE];
+                0x8000 | xy | low[rng.next() as usize % low.len()]
+            }
+            0xE => 0xE000 | x | [0x9E, 0xA1][rng.next() as usize % 2],
+            0xF => {
+                let low = [0x07, 0x0A, 0x15, 0x18, 0x1E, 0x29, 0x33, 0x55, 0x65];
+                0xF000 | x | low[rng.next() as usize % low.len()]
+            }
+            0xB => 0xB000 | target,
+            nibble => nibble << 12 | (rng.next() & 0x0FFF),
+        };
+        image.extend_from_slice(&opcode.to_be_bytes());
+    }
+    image.extend_from_slice(&[0x12, 0x00]);
+    image
+}
@@ -0,0 +1,352 @@
+//! Conformance harness.
+//!
+//! Each case runs a ROM from `tests/roms` headlessly for a fixed number of
+//! frames under a platform profile, then compares the final screen with an
+//! ASCII fixture from `tests/fixtures` or with a screen hash. Every case runs
+//! on each execution backend. Run with `CHIP8_BLESS=1` to (re)write the
+//! fixtures from the interpreter's output.
+//!
+//! Cases under `roms/third_party` are `#[ignore]`d until their ROMs are
+//! vendored; see `tests/roms/third_party/README.md`.
+
+use std::{env, fs, path::PathBuf};
+
+use chip8emu::machine::{
+    Backend, Key, MachDisplay, Machine, Platform, DISPLAY_HEIGHT, DISPLAY_WIDTH,
+    INSTRUCTIONS_PER_FRAME,
+};
+
+const BACKENDS: &[Backend] = &[
+    Backend::Interpreter,
+    Backend::Threaded,
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    Backend::Jit,
+];
+
+enum Expected {
+    Fixture(&'static str),
+    Hash(u64),
+}
+
+struct Case {
+    rom: &'static str,
+    platform: Platform,
+    frames: u32,
+    keys: &'static [Key],
+    /// Written to 0x1FF before the ROM starts; Timendus' suite reads its
+    /// platform or test choice from there instead of showing a menu.
+    select: Option<u8>,
+    expected: Expected,
+}
+
+const SELECT_ADDR: u16 = 0x1FF;
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+fn screen(display: &MachDisplay) -> Vec<String> {
+    (0..DISPLAY_HEIGHT)
+        .map(|y| {
+            (0..DISPLAY_WIDTH)
+                .map(|x| match display.pixel(x, y) {
+                    Ok(true) => '#',
+                    _ => '.',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn screen_hash(rows: &[String]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in rows.iter().flat_map(|row| row.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn side_by_side(expected: &[String], actual: &[String]) -> String {
+    let width = DISPLAY_WIDTH;
+    let mut out = format!("   {:<width$}   actual\n", "expected");
+    for y in 0..expected.len().max(actual.len()) {
+        let left = expected.get(y).map(String::as_str).unwrap_or("");
+        let right = actual.get(y).map(String::as_str).unwrap_or("");
+        let marker = if left == right { ' ' } else { '!' };
+        out.push_str(&format!("{}{:2} {:<width$} | {}\n", marker, y, left, right));
+    }
+    out
+}
+
+fn run(case: Case) {
+    for &backend in BACKENDS {
+        run_on(&case, backend);
+    }
+}
+
+fn run_on(case: &Case, backend: Backend) {
+    let path = root().join("roms").join(case.rom);
+    let rom = fs::read(path).expect("missing ROM, see tests/roms/third_party/README.md");
+    let mut mach = Machine::with_quirks(case.platform.quirks());
+    mach.set_backend(backend);
+    mach.load(&rom).expect("ROM too large");
+    if let Some(select) = case.select {
+        mach.write_memory(SELECT_ADDR, &[select]).unwrap();
+    }
+    for key in case.keys {
+        mach.set_key(*key, true);
+    }
+    for _ in 0..case.frames {
+        mach.run_frame(INSTRUCTIONS_PER_FRAME).unwrap_or_else(|_| {
+            panic!(
+                "{} faulted at {:#06x} on {:?}",
+                case.rom,
+                mach.pc(),
+                backend
+            )
+        });
+    }
+
+    let actual = screen(mach.display());
+    let hash = screen_hash(&actual);
+    match case.expected {
+        Expected::Fixture(name) => {
+            let path = root().join("fixtures").join(name);
+            if backend == Backend::Interpreter && env::var_os("CHIP8_BLESS").is_some() {
+                fs::write(&path, actual.join("\n") + "\n").unwrap();
+                return;
+            }
+            let text = fs::read_to_string(&path).unwrap_or_else(|_| {
+                panic!(
+                    "missing fixture {}; check the screen and bless it with CHIP8_BLESS=1",
+                    name
+                )
+            });
+            let expected: Vec<String> = text.lines().map(str::to_string).collect();
+            assert!(
+                expected == actual,
+                "{} on {} ({:?}) differs from {}:\n{}",
+                case.rom,
+                case.platform,
+                backend,
+                name,
+                side_by_side(&expected, &actual)
+            );
+        }
+        Expected::Hash(expected) => assert!(
+            expected == hash,
+            "{} on {} ({:?}) hashed to {:#018x}, expected {:#018x}:\n{}",
+            case.rom,
+            case.platform,
+            backend,
+            hash,
+            expected,
+            actual.join("\n")
+        ),
+    }
+}
+
+#[test]
+fn font() {
+    run(Case {
+        rom: "font.ch8",
+        platform: Platform::Chip8,
+        frames: 20,
+        keys: &[],
+        select: None,
+        expected: Expected::Fixture("font.txt"),
+    });
+}
+
+#[test]
+fn font_is_platform_independent() {
+    for platform in [Platform::SuperChip, Platform::XoChip] {
+        run(Case {
+            rom: "font.ch8",
+            platform,
+            frames: 20,
+            keys: &[],
+            select: None,
+            expected: Expected::Hash(0xac77_5c82_270d_aa26),
+        });
+    }
+}
+
+#[test]
+fn flags() {
+    run(Case {
+        rom: "flags.ch8",
+        platform: Platform::Chip8,
+        frames: 30,
+        keys: &[],
+        select: None,
+        expected: Expected::Fixture("flags.txt"),
+    });
+}
+
+#[test]
+fn quirks_chip8() {
+    run(Case {
+        rom: "quirks.ch8",
+        platform: Platform::Chip8,
+        frames: 10,
+        keys: &[],
+        select: None,
+        expected: Expected::Fixture("quirks-chip8.txt"),
+    });
+}
+
+#[test]
+fn quirks_schip() {
+    run(Case {
+        rom: "quirks.ch8",
+        platform: Platform::SuperChip,
+        frames: 10,
+        keys: &[],
+        select: None,
+        expected: Expected::Fixture("quirks-schip.txt"),
+    });
+}
+
+#[test]
+fn quirks_xochip() {
+    run(Case {
+        rom: "quirks.ch8",
+        platform: Platform::XoChip,
+        frames: 10,
+        keys: &[],
+        select: None,
+        expected: Expected::Fixture("quirks-xochip.txt"),
+    });
+}
+
+#[test]
+fn keypad_waits_for_key() {
+    run(Case {
+        rom: "keypad.ch8",
+        platform: Platform::Chip8,
+        frames: 10,
+        keys: &[],
+        select: None,
+        expected: Expected::Fixture("blank.txt"),
+    });
+}
+
+#[test]
+fn keypad_shows_pressed_key() {
+    run(Case {
+        rom: "keypad.ch8",
+        platform: Platform::Chip8,
+        frames: 10,
+        keys: &[Key::Key7],
+        select: None,
+        expected: Expected::Fixture("keypad-7.txt"),
+    });
+}
+
+// The shifts in flags.ch8 use VX as VY, so every platform agrees.
+#[test]
+fn flags_is_platform_independent() {
+    for platform in [Platform::SuperChip, Platform::XoChip] {
+        run(Case {
+            rom: "flags.ch8",
+            platform,
+            frames: 30,
+            keys: &[],
+            select: None,
+            expected: Expected::Fixture("flags.txt"),
+        });
+    }
+}
+
+#[test]
+fn keypad_is_platform_independent() {
+    for platform in [Platform::SuperChip, Platform::XoChip] {
+        run(Case {
+            rom: "keypad.ch8",
+            platform,
+            frames: 10,
+            keys: &[Key::Key7],
+            select: None,
+            expected: Expected::Fixture("keypad-7.txt"),
+        });
+    }
+}
+
+#[test]
+#[ignore = "ROM not vendored"]
+fn timendus_ibm_logo() {
+    run(Case {
+        rom: "third_party/2-ibm-logo.ch8",
+        platform: Platform::Chip8,
+        frames: 20,
+        keys: &[],
+        select: None,
+        expected: Expected::Fixture("timendus-ibm-logo.txt"),
+    });
+}
+
+#[test]
+#[ignore = "ROM not vendored"]
+fn timendus_corax_plus() {
+    run(Case {
+        rom: "third_party/3-corax+.ch8",
+        platform: Platform::Chip8,
+        frames: 60,
+        keys: &[],
+        select: None,
+        expected: Expected::Fixture("timendus-corax+.txt"),
+    });
+}
+
+#[test]
+#[ignore = "ROM not vendored"]
+fn timendus_flags() {
+    for platform in enum_iterator::all::<Platform>() {
+        run(Case {
+            rom: "third_party/4-flags.ch8",
+            platform,
+            frames: 120,
+            keys: &[],
+            select: None,
+            expected: Expected::Fixture(match platform {
+                Platform::Chip8 => "timendus-flags-chip8.txt",
+                Platform::SuperChip => "timendus-flags-schip.txt",
+                Platform::XoChip => "timendus-flags-xochip.txt",
+            }),
+        });
+    }
+}
+
+#[test]
+#[ignore = "ROM not vendored"]
+fn timendus_quirks() {
+    for (platform, select, fixture) in [
+        (Platform::Chip8, 1, "timendus-quirks-chip8.txt"),
+        (Platform::SuperChip, 2, "timendus-quirks-schip.txt"),
+        (Platform::XoChip, 3, "timendus-quirks-xochip.txt"),
+    ] {
+        run(Case {
+            rom: "third_party/5-quirks.ch8",
+            platform,
+            frames: 600,
+            keys: &[],
+            select: Some(select),
+            expected: Expected::Fixture(fixture),
+        });
+    }
+}
+
+#[test]
+#[ignore = "ROM not vendored"]
+fn timendus_keypad_getkey() {
+    run(Case {
+        rom: "third_party/6-keypad.ch8",
+        platform: Platform::Chip8,
+        frames: 30,
+        keys: &[],
+        select: Some(3),
+        expected: Expected::Fixture("timendus-keypad-getkey.txt"),
+    });
+}
@@ -0,0 +1,131 @@
+use chip8emu::{
+    coverage::{fnv1a, Coverage},
+    machine::Machine,
+    probe,
+};
+
+// Draws one sprite row from 0x20E, writes V0's digits to 0x210 and reads two
+// of them back, then spins at 0x20A. 0x20C and 0x20F are never touched.
+const ROM: [u8; 19] = [
+    0xA2, 0x0E, 0xD0, 0x01, 0xA2, 0x10, 0xF0, 0x33, 0xF1, 0x65, 0x12, 0x0A, 0x00, 0x00, 0xF0, 0x0F,
+    0x00, 0x00, 0x00,
+];
+
+fn run(rom: &[u8], steps: usize) -> Coverage {
+    let mut mach = Machine::new();
+    mach.load(rom).unwrap();
+    let mut coverage = Coverage::new().with_rom(rom);
+    for _ in 0..steps {
+        probe::step(&mut mach, &mut [&mut coverage]).unwrap();
+    }
+    coverage
+}
+
+fn write(coverage: &Coverage) -> String {
+    let mut out = Vec::new();
+    coverage.write(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn probe_flags_code_and_data() {
+    let coverage = run(&ROM, 8);
+    assert_eq!(coverage.flags(0x200), Coverage::CODE);
+    assert_eq!(coverage.flags(0x20B), Coverage::CODE);
+    assert_eq!(coverage.flags(0x20C), 0);
+    assert_eq!(coverage.flags(0x20E), Coverage::SPRITE);
+    assert_eq!(coverage.flags(0x210), Coverage::READ | Coverage::WRITE);
+    assert_eq!(coverage.flags(0x212), Coverage::WRITE);
+    assert_eq!(coverage.rom(), Some(fnv1a(&ROM)));
+}
+
+#[test]
+fn files_start_with_the_rom_hash_and_round_trip() {
+    let coverage = run(&ROM, 8);
+    let text = write(&coverage);
+    let expected = format!(
+        "chip8-coverage 1 {:016x}\n\
+         200 1111111111110020cc8000000000000000000000000000000000000000000000\n",
+        fnv1a(&ROM)
+    );
+    assert_eq!(text, expected);
+    let parsed = Coverage::parse(&text).unwrap();
+    assert_eq!(parsed.rom(), coverage.rom());
+    assert_eq!(write(&parsed), text);
+}
+
+#[test]
+fn merge_ors_flags_of_the_same_rom() {
+    let mut early = run(&ROM, 2);
+    assert_eq!(early.flags(0x210), 0);
+    let late = Coverage::parse(&write(&run(&ROM, 8))).unwrap();
+    early.merge(&late).unwrap();
+    assert_eq!(write(&early), write(&late));
+}
+
+#[test]
+fn merge_rejects_another_rom() {
+    let mut coverage = run(&ROM, 8);
+    let before = write(&coverage);
+    let mut other_rom = ROM;
+    other_rom[1] = 0x0C;
+    assert!(coverage.merge(&run(&other_rom, 8)).is_err());
+    assert!(coverage.merge(&Coverage::new()).is_err());
+    assert_eq!(write(&coverage), before);
+}
+
+#[test]
+fn parse_rejects_malformed_files() {
+    for text in [
+        "",
+        "chip8-coverage 2\n",
+        "chip8-coverage 10\n",
+        "chip8-coverage 1 xyz\n",
+        "chip8-coverage 1\n200\n",
+        "chip8-coverage 1\n200 1g\n",
+        "chip8-coverage 1\nzz 11\n",
+        "chip8-coverage 1\nfff 11\n",
+    ] {
+        assert!(Coverage::parse(text).is_err(), "{:?}", text);
+    }
+    let unknown = Coverage::parse("chip8-coverage 1\n\n200 1\n").unwrap();
+    assert_eq!(unknown.rom(), None);
+    assert_eq!(unknown.flags(0x200), Coverage::CODE);
+}
+
+#[test]
+fn listing_annotates_each_rom_byte() {
+    let coverage = run(&ROM, 8);
+    let mut out = Vec::new();
+    coverage.write_listing(&ROM, &mut out).unwrap();
+    let expected = "\
+; 19 ROM bytes
+; code         12
+; sprite        1
+; read          2
+; written       3
+; untouched     3
+
+0x0200  A2 0E  LD I, 0x20e          ; code
+0x0202  D0 01  DRW V0, V0, 0x1      ; code
+0x0204  A2 10  LD I, 0x210          ; code
+0x0206  F0 33  LD B, V0             ; code
+0x0208  F1 65  LD V1, [I+]          ; code
+0x020a  12 0A  JP 0x20a             ; code
+0x020c  00     DB 0b00000000        ; untouched
+0x020d  00     DB 0b00000000        ; untouched
+0x020e  F0     DB 0b11110000        ; sprite
+0x020f  0F     DB 0b00001111        ; untouched
+0x0210  00     DB 0b00000000        ; read, write
+0x0211  00     DB 0b00000000        ; read, write
+0x0212  00     DB 0b00000000        ; write
+";
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+#[test]
+fn listing_rejects_a_rom_too_large_for_memory() {
+    let coverage = Coverage::new();
+    let mut out = Vec::new();
+    assert!(coverage.write_listing(&[0; 4096], &mut out).is_err());
+}
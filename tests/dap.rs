@@ -0,0 +1,357 @@
+//! The Debug Adapter Protocol server, driven over in-memory streams.
+//!
+//! `tests/dap/call.ch8` loads V0, calls a subroutine at 0x208 that loads V1,
+//! and then spins on a jump to itself; `call.map` maps it to `call.8o`.
+
+use std::{
+    io::{self, Cursor, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chip8emu::dap::{self, SourceMap};
+use serde_json::{json, Value};
+
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/dap")
+}
+
+fn frame(body: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+// Splits the server's output into messages, checking every Content-Length.
+fn parse_output(mut output: &str) -> Vec<Value> {
+    let mut messages = Vec::new();
+    while !output.is_empty() {
+        let (header, rest) = output.split_once("\r\n\r\n").unwrap();
+        let len: usize = header
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        messages.push(serde_json::from_str(&rest[..len]).unwrap());
+        output = &rest[len..];
+    }
+    messages
+}
+
+// Sends `requests` as one stream, numbering them from 1, and returns
+// everything the server wrote back.
+fn session(requests: &[(&str, Value)]) -> Vec<Value> {
+    let input: String = requests
+        .iter()
+        .enumerate()
+        .map(|(seq, (command, arguments))| {
+            let request = json!({
+                "seq": seq + 1,
+                "type": "request",
+                "command": command,
+                "arguments": arguments,
+            });
+            frame(&request.to_string())
+        })
+        .collect();
+    serve(input)
+}
+
+// Serves raw `input` and returns everything the server wrote back.
+fn serve(input: String) -> Vec<Value> {
+    let output = Output::default();
+    dap::serve(Cursor::new(input.into_bytes()), output.clone()).unwrap();
+    let bytes = output.0.lock().unwrap().clone();
+    parse_output(&String::from_utf8(bytes).unwrap())
+}
+
+fn launch() -> Vec<(&'static str, Value)> {
+    vec![
+        ("initialize", json!({})),
+        (
+            "launch",
+            json!({
+                "program": root().join("call.ch8"),
+                "sourceMap": root().join("call.map"),
+                "stopOnEntry": true,
+            }),
+        ),
+        ("configurationDone", json!({})),
+    ]
+}
+
+fn response(messages: &[Value], request_seq: usize) -> &Value {
+    messages
+        .iter()
+        .find(|m| m["type"] == "response" && m["request_seq"] == request_seq)
+        .unwrap()
+}
+
+fn stops(messages: &[Value]) -> Vec<&str> {
+    messages
+        .iter()
+        .filter(|m| m["event"] == "stopped")
+        .map(|m| m["body"]["reason"].as_str().unwrap())
+        .collect()
+}
+
+fn top_frame(messages: &[Value], request_seq: usize) -> &Value {
+    &response(messages, request_seq)["body"]["stackFrames"][0]
+}
+
+#[test]
+fn responses_are_framed_and_numbered() {
+    let messages = session(&launch());
+    let seqs: Vec<u64> = messages
+        .iter()
+        .map(|m| m["seq"].as_u64().unwrap())
+        .collect();
+    assert_eq!(seqs, (1..=seqs.len() as u64).collect::<Vec<_>>());
+    let capabilities = &response(&messages, 1)["body"];
+    assert_eq!(capabilities["supportsStepBack"], true);
+    assert_eq!(response(&messages, 2)["success"], true);
+    assert!(messages.iter().any(|m| m["event"] == "initialized"));
+    assert_eq!(stops(&messages), ["entry"]);
+}
+
+#[test]
+fn extra_headers_are_ignored() {
+    let body = json!({ "seq": 1, "type": "request", "command": "threads" }).to_string();
+    let input = format!(
+        "Content-Type: application/vscode-jsonrpc\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let messages = serve(input);
+    assert_eq!(messages[0]["body"]["threads"][0]["name"], "CHIP-8");
+}
+
+#[test]
+fn bodies_that_are_not_json_are_skipped() {
+    let body = json!({ "seq": 2, "type": "request", "command": "threads" }).to_string();
+    let messages = serve(frame("{ not json") + &frame(&body));
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["request_seq"], 2);
+}
+
+#[test]
+fn oversized_content_lengths_end_the_session() {
+    let body = json!({ "seq": 1, "type": "request", "command": "threads" }).to_string();
+    let messages = serve(format!(
+        "Content-Length: 99999999999\r\n\r\n{}",
+        frame(&body)
+    ));
+    assert!(messages.is_empty());
+}
+
+#[test]
+fn launch_takes_a_platform() {
+    let launch = |platform: &str| {
+        let messages = session(&[(
+            "launch",
+            json!({ "program": root().join("call.ch8"), "platform": platform }),
+        )]);
+        messages[0].clone()
+    };
+    assert_eq!(launch("schip")["success"], true);
+    let unknown = launch("vip");
+    assert_eq!(unknown["success"], false);
+    assert_eq!(unknown["message"], "unknown platform 'vip'");
+}
+
+#[test]
+fn unknown_requests_fail() {
+    let messages = session(&[("frobnicate", json!({}))]);
+    assert_eq!(messages[0]["success"], false);
+    assert_eq!(messages[0]["message"], "unsupported request 'frobnicate'");
+}
+
+#[test]
+fn line_breakpoints_stop_at_the_mapped_address() {
+    let mut requests = launch();
+    requests.push((
+        "setBreakpoints",
+        json!({
+            "source": { "path": root().join("call.8o") },
+            "breakpoints": [{ "line": 7 }, { "line": 5 }],
+        }),
+    ));
+    requests.push(("continue", json!({})));
+    requests.push(("stackTrace", json!({ "threadId": 1 })));
+    let messages = session(&requests);
+
+    let breakpoints = &response(&messages, 4)["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[0]["instructionReference"], "0x0208");
+    assert_eq!(breakpoints[1]["verified"], false);
+    assert_eq!(stops(&messages), ["entry", "breakpoint"]);
+
+    let frames = &response(&messages, 6)["body"]["stackFrames"];
+    assert_eq!(frames[0]["instructionPointerReference"], "0x0208");
+    assert_eq!(frames[0]["line"], 7);
+    assert!(frames[0]["source"]["path"]
+        .as_str()
+        .unwrap()
+        .ends_with("call.8o"));
+    // The caller's frame points at the CALL.
+    assert_eq!(frames[1]["instructionPointerReference"], "0x0202");
+    assert_eq!(frames[1]["line"], 2);
+}
+
+#[test]
+fn instruction_breakpoints_take_an_offset() {
+    let mut requests = launch();
+    requests.push((
+        "setInstructionBreakpoints",
+        json!({
+            "breakpoints": [
+                { "instructionReference": "0x0200", "offset": 4 },
+                { "instructionReference": "0x0000", "offset": -2 },
+            ],
+        }),
+    ));
+    requests.push(("continue", json!({})));
+    requests.push(("stackTrace", json!({ "threadId": 1 })));
+    let messages = session(&requests);
+    let breakpoints = &response(&messages, 4)["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["instructionReference"], "0x0204");
+    assert_eq!(breakpoints[1]["verified"], false);
+    assert_eq!(stops(&messages), ["entry", "breakpoint"]);
+    assert_eq!(
+        top_frame(&messages, 6)["instructionPointerReference"],
+        "0x0204"
+    );
+}
+
+#[test]
+fn step_back_undoes_one_instruction() {
+    let mut requests = launch();
+    requests.push(("stepIn", json!({ "threadId": 1 })));
+    requests.push(("stepIn", json!({ "threadId": 1 })));
+    requests.push(("stackTrace", json!({ "threadId": 1 })));
+    requests.push(("stepBack", json!({ "threadId": 1 })));
+    requests.push(("stackTrace", json!({ "threadId": 1 })));
+    requests.push(("variables", json!({ "variablesReference": 1 })));
+    requests.push(("stepBack", json!({ "threadId": 1 })));
+    requests.push(("stepBack", json!({ "threadId": 1 })));
+    let messages = session(&requests);
+
+    assert_eq!(
+        top_frame(&messages, 6)["instructionPointerReference"],
+        "0x0208"
+    );
+    assert_eq!(
+        top_frame(&messages, 8)["instructionPointerReference"],
+        "0x0202"
+    );
+    let registers = &response(&messages, 9)["body"]["variables"];
+    assert_eq!(registers[0]["name"], "V0");
+    assert_eq!(registers[0]["value"], "0x05");
+    let last = messages.iter().rev().find(|m| m["event"] == "stopped");
+    assert_eq!(
+        last.unwrap()["body"]["description"],
+        "reached the start of the rewind buffer"
+    );
+}
+
+#[test]
+fn read_memory_encodes_base64_and_counts_unreadable_bytes() {
+    let mut requests = launch();
+    for count in 1..=4 {
+        requests.push((
+            "readMemory",
+            json!({ "memoryReference": "0x200", "count": count }),
+        ));
+    }
+    requests.push((
+        "readMemory",
+        json!({ "memoryReference": "0xFFE", "count": 4 }),
+    ));
+    requests.push((
+        "readMemory",
+        json!({ "memoryReference": "0x200", "offset": i64::MAX, "count": u64::MAX }),
+    ));
+    let messages = session(&requests);
+    let data: Vec<&str> = (4..=7)
+        .map(|seq| response(&messages, seq)["body"]["data"].as_str().unwrap())
+        .collect();
+    assert_eq!(data, ["YA==", "YAU=", "YAUi", "YAUiCA=="]);
+    let past_end = &response(&messages, 8)["body"];
+    assert_eq!(past_end["data"], "AAA=");
+    assert_eq!(past_end["unreadableBytes"], 2);
+    assert_eq!(response(&messages, 9)["success"], false);
+}
+
+#[test]
+fn disassemble_is_bounded() {
+    let mut requests = launch();
+    requests.push((
+        "disassemble",
+        json!({ "memoryReference": "0x200", "instructionOffset": -1, "instructionCount": 3 }),
+    ));
+    requests.push((
+        "disassemble",
+        json!({ "memoryReference": "0x200", "instructionCount": i64::MAX }),
+    ));
+    requests.push((
+        "disassemble",
+        json!({ "memoryReference": "0x200", "instructionOffset": i64::MAX, "instructionCount": 1 }),
+    ));
+    let messages = session(&requests);
+
+    let window = response(&messages, 4)["body"]["instructions"]
+        .as_array()
+        .unwrap();
+    assert_eq!(window[0]["address"], "0x01fe");
+    assert_eq!(window[1]["instruction"], "LD V0, 0x05");
+    assert_eq!(window[1]["instructionBytes"], "60 05");
+    assert_eq!(window[1]["line"], 1);
+    assert_eq!(window[2]["instruction"], "CALL 0x208");
+
+    let everything = response(&messages, 5)["body"]["instructions"]
+        .as_array()
+        .unwrap();
+    assert_eq!(everything.len(), 2048);
+    assert_eq!(everything.last().unwrap()["presentationHint"], "invalid");
+    assert_eq!(response(&messages, 6)["success"], false);
+}
+
+#[test]
+fn source_maps_resolve_relative_paths() {
+    let text = "# comment\n\n0x202 game.8o:3\n0x200 game.8o:1\n516 /abs/lib.8o:9\n";
+    let map = SourceMap::parse(text, Path::new("roms")).unwrap();
+    assert_eq!(map.lookup_addr(0x200), Some((Path::new("roms/game.8o"), 1)));
+    assert_eq!(map.lookup_addr(0x204), Some((Path::new("/abs/lib.8o"), 9)));
+    assert_eq!(
+        map.lookup_line(Path::new("/src/roms/game.8o"), 3),
+        Some(0x202)
+    );
+    assert_eq!(map.lookup_line(Path::new("game.8o"), 2), None);
+    assert_eq!(map.lookup_addr(0x206), None);
+}
+
+#[test]
+fn source_maps_reject_malformed_lines() {
+    for text in [
+        "0x200",
+        "0x200 game.8o",
+        "0x200 game.8o:x",
+        "0xZZ game.8o:1",
+    ] {
+        assert!(SourceMap::parse(text, Path::new("")).is_err(), "{}", text);
+    }
+    assert!(SourceMap::load(&root().join("missing.map")).is_err());
+    assert!(SourceMap::load(&root().join("call.map")).is_ok());
+}
@@ -0,0 +1,121 @@
+use std::{fs, path::PathBuf};
+
+use chip8emu::{
+    diff::{DiffErr, Field, Lockstep, Reference, TraceLockstep},
+    machine::{Key, Machine, Platform, Reg, INSTRUCTIONS_PER_FRAME},
+    trace::Tracer,
+};
+
+fn machine(rom: &str, platform: Platform) -> Machine {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/roms")
+        .join(rom);
+    let mut mach = Machine::with_quirks(platform.quirks());
+    mach.load(&fs::read(path).unwrap()).unwrap();
+    mach
+}
+
+fn trace(rom: &str, platform: Platform, frames: usize) -> String {
+    let mut mach = machine(rom, platform);
+    let mut tracer = Tracer::new(Vec::new());
+    for _ in 0..frames * INSTRUCTIONS_PER_FRAME {
+        tracer.step(&mut mach).unwrap();
+    }
+    String::from_utf8(tracer.get_ref().clone()).unwrap()
+}
+
+fn run(lockstep: &mut Lockstep, frames: usize) -> Result<(), DiffErr> {
+    for _ in 0..frames {
+        lockstep.run_frame(INSTRUCTIONS_PER_FRAME)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn same_platform_never_diverges() {
+    let mut lockstep = Lockstep::new(
+        machine("flags.ch8", Platform::Chip8),
+        machine("flags.ch8", Platform::Chip8),
+    );
+    run(&mut lockstep, 30).unwrap();
+}
+
+#[test]
+fn vf_reset_quirk_diverges() {
+    let mut lockstep = Lockstep::new(
+        machine("quirks.ch8", Platform::Chip8),
+        machine("quirks.ch8", Platform::SuperChip),
+    )
+    .with_context(3);
+    let Err(DiffErr::Diverged(divergence)) = run(&mut lockstep, 10) else {
+        panic!("expected a divergence");
+    };
+    let instruction = divergence.instruction.unwrap();
+    assert_eq!(instruction.opcode, 0x8011);
+    assert_eq!(divergence.fields, [Field::Reg(Reg::VF, 0x00, 0x05)]);
+    assert_eq!(divergence.context.len(), 3);
+    assert!(divergence.context[2].contains("8011 OR V0, V1"));
+}
+
+#[test]
+fn right_mirrors_left_keypad() {
+    let mut lockstep = Lockstep::new(
+        machine("keypad.ch8", Platform::Chip8),
+        machine("keypad.ch8", Platform::Chip8),
+    );
+    run(&mut lockstep, 2).unwrap();
+    lockstep.left_mut().set_key(Key::Key7, true);
+    run(&mut lockstep, 5).unwrap();
+    assert!(lockstep.right().key(Key::Key7));
+    assert_eq!(lockstep.right().reg(Reg::V0), 7);
+}
+
+#[test]
+fn trace_reference_matches() {
+    let reference = Reference::parse(&trace("flags.ch8", Platform::Chip8, 3)).unwrap();
+    assert_eq!(reference.len(), 3 * INSTRUCTIONS_PER_FRAME);
+    let mut lockstep = TraceLockstep::new(machine("flags.ch8", Platform::Chip8), reference);
+    while !lockstep.is_finished() {
+        lockstep.step().unwrap();
+    }
+}
+
+#[test]
+fn trace_reference_diverges() {
+    let reference = Reference::parse(&trace("flags.ch8", Platform::Chip8, 3)).unwrap();
+    let mut lockstep = TraceLockstep::new(machine("flags.ch8", Platform::XoChip), reference);
+    let err = loop {
+        if let Err(err) = lockstep.step() {
+            break err;
+        }
+    };
+    let DiffErr::Diverged(divergence) = err else {
+        panic!("expected a divergence");
+    };
+    assert_eq!(divergence.instruction.unwrap().opcode, 0x8232);
+    assert_eq!(divergence.fields, [Field::Reg(Reg::VF, 0x01, 0x00)]);
+}
+
+#[test]
+fn trace_reference_reports_jump() {
+    let text = trace("flags.ch8", Platform::Chip8, 1);
+    let mut lines: Vec<&str> = text.lines().collect();
+    lines.remove(2);
+    let reference = Reference::parse(&lines.join("\n")).unwrap();
+    let mut lockstep = TraceLockstep::new(machine("flags.ch8", Platform::Chip8), reference);
+    lockstep.step().unwrap();
+    lockstep.step().unwrap();
+    let Err(DiffErr::Diverged(divergence)) = lockstep.step() else {
+        panic!("expected a divergence");
+    };
+    assert!(matches!(divergence.fields[..], [Field::Pc(..)]));
+    assert_eq!(divergence.instruction.unwrap().cycle, 1);
+}
+
+#[test]
+fn trace_reference_rejects_garbage() {
+    assert!(matches!(
+        Reference::parse("0 0200 6001 LD V0, 0x01 ; V0=01\nnonsense\n"),
+        Err(DiffErr::Parse(2))
+    ));
+}
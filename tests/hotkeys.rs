@@ -0,0 +1,66 @@
+use chip8emu::hotkeys::{Hotkey, Input, Keyboard};
+
+fn feed(keyboard: &mut Keyboard, bytes: &[u8]) -> Vec<Input> {
+    bytes
+        .iter()
+        .filter_map(|&byte| keyboard.feed(byte))
+        .collect()
+}
+
+fn hotkeys(bytes: &[u8]) -> Vec<Hotkey> {
+    feed(&mut Keyboard::new(), bytes)
+        .into_iter()
+        .filter_map(|input| match input {
+            Input::Hotkey(hotkey) => Some(hotkey),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn function_keys_save_and_shifted_ones_load() {
+    assert_eq!(hotkeys(b"\x1bOP"), [Hotkey::Save(1)]);
+    assert_eq!(hotkeys(b"\x1bOS"), [Hotkey::Save(4)]);
+    assert_eq!(hotkeys(b"\x1b[15~"), [Hotkey::Save(5)]);
+    assert_eq!(hotkeys(b"\x1b[20~"), [Hotkey::Save(9)]);
+    assert_eq!(hotkeys(b"\x1b[1;2P"), [Hotkey::Load(1)]);
+    assert_eq!(hotkeys(b"\x1bO2R"), [Hotkey::Load(3)]);
+    assert_eq!(hotkeys(b"\x1b[18;2~"), [Hotkey::Load(7)]);
+    assert_eq!(Hotkey::Save(3).command(), "save 3");
+    assert_eq!(Hotkey::Load(9).command(), "load 9");
+}
+
+#[test]
+fn console_and_vt220_encodings_are_understood() {
+    assert_eq!(
+        hotkeys(b"\x1b[[A\x1b[[E"),
+        [Hotkey::Save(1), Hotkey::Save(5)]
+    );
+    assert_eq!(
+        hotkeys(b"\x1b[11~\x1b[14~"),
+        [Hotkey::Save(1), Hotkey::Save(4)]
+    );
+}
+
+#[test]
+fn other_keys_and_escapes_are_ignored() {
+    // F10, Ctrl+F1, an arrow key and an overlong sequence.
+    assert!(hotkeys(b"\x1b[21~\x1b[1;5P\x1b[A\x1b[123456789~").is_empty());
+    let mut keyboard = Keyboard::new();
+    assert_eq!(
+        feed(&mut keyboard, b"\x1b[Aq\n"),
+        [Input::Echo("q".to_string()), Input::Line("q".to_string())]
+    );
+}
+
+#[test]
+fn typed_lines_are_echoed_and_edited() {
+    let mut keyboard = Keyboard::new();
+    let inputs = feed(&mut keyboard, b"sac\x7fve 2\x1bOQ\r");
+    assert_eq!(inputs.last(), Some(&Input::Line("save 2".to_string())));
+    assert!(inputs.contains(&Input::Echo("\u{8} \u{8}".to_string())));
+    assert!(inputs.contains(&Input::Hotkey(Hotkey::Save(2))));
+    // Backspace on an empty line has nothing to erase.
+    assert!(feed(&mut keyboard, b"\x7f").is_empty());
+    assert_eq!(feed(&mut keyboard, b"\x03"), [Input::Interrupt]);
+}
@@ -0,0 +1,130 @@
+use std::{fs, path::PathBuf};
+
+use chip8emu::{
+    machine::{Key, Machine, Platform, SeededRandom, INSTRUCTIONS_PER_FRAME},
+    movie::{Movie, MovieErr, Player},
+};
+
+// Draws a random digit at a random position every frame, then waits for a key.
+const RANDOM_ROM: [u8; 14] = [
+    0xC0, 0x0F, 0xF0, 0x29, 0xCA, 0x3F, 0xCB, 0x1F, 0xDA, 0xB5, 0xF1, 0x0A, 0x12, 0x00,
+];
+
+fn keypad_rom() -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms/keypad.ch8");
+    fs::read(path).unwrap()
+}
+
+fn record(rom: &[u8], seed: u64, presses: &[(u64, Key, bool)], frames: u64) -> Movie {
+    let mut mach = Machine::new();
+    mach.set_random(Box::new(SeededRandom::new(seed)));
+    mach.load(rom).unwrap();
+    let mut movie = Movie::new(rom, &mach, seed);
+    for frame in 0..frames {
+        for (_, key, pressed) in presses.iter().filter(|(at, ..)| *at == frame) {
+            mach.set_key(*key, *pressed);
+            movie.record_key(frame, *key, *pressed);
+        }
+        mach.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+        if frame % 5 == 4 {
+            movie.record_check(frame, &mach);
+        }
+    }
+    movie
+}
+
+fn play(movie: Movie, rom: &[u8]) -> Result<Machine, MovieErr> {
+    let mut mach = movie.machine(rom)?;
+    let frames = movie.frames;
+    let mut player = Player::new(movie).with_verify(true);
+    for frame in 0..frames {
+        player.before_frame(frame, &mut mach);
+        mach.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+        player.after_frame(frame, &mach)?;
+    }
+    assert!(player.is_finished(frames));
+    Ok(mach)
+}
+
+fn round_trip(movie: &Movie) -> Movie {
+    let mut text = Vec::new();
+    movie.write(&mut text).unwrap();
+    Movie::parse(&String::from_utf8(text).unwrap()).unwrap()
+}
+
+#[test]
+fn playback_reproduces_recording() {
+    let rom = keypad_rom();
+    let movie = record(&rom, 1, &[(3, Key::Key7, true), (8, Key::Key7, false)], 20);
+    let movie = round_trip(&movie);
+    let mach = play(movie, &rom).unwrap();
+    assert!(!mach.key(Key::Key7));
+}
+
+#[test]
+fn playback_reproduces_random_draws() {
+    let movie = record(
+        &RANDOM_ROM,
+        42,
+        &[(2, Key::Key1, true), (3, Key::Key1, false)],
+        30,
+    );
+    play(round_trip(&movie), &RANDOM_ROM).unwrap();
+
+    let mut other_seed = round_trip(&movie);
+    other_seed.seed = 43;
+    assert!(matches!(
+        play(other_seed, &RANDOM_ROM),
+        Err(MovieErr::Desync { .. })
+    ));
+}
+
+#[test]
+fn playback_detects_missing_input() {
+    let rom = keypad_rom();
+    let movie = record(&rom, 1, &[(3, Key::Key7, true)], 20);
+    let mut text = Vec::new();
+    movie.write(&mut text).unwrap();
+    let text = String::from_utf8(text).unwrap().replace("3 press 7\n", "");
+    let result = play(Movie::parse(&text).unwrap(), &rom);
+    assert!(matches!(result, Err(MovieErr::Desync { frame: 4, .. })));
+}
+
+#[test]
+fn playback_rejects_other_rom() {
+    let movie = record(&keypad_rom(), 1, &[], 5);
+    assert!(matches!(
+        movie.machine(&RANDOM_ROM),
+        Err(MovieErr::RomMismatch)
+    ));
+}
+
+#[test]
+fn parse_rejects_out_of_order_events() {
+    let text = "chip8-movie 1\nrom 0\nframes 10\n5 press 1\n4 press 2\n";
+    assert!(matches!(Movie::parse(text), Err(MovieErr::Parse(5))));
+    let text = "chip8-movie 1\nframes 10\n5 check 0\n5 press 2\n";
+    assert!(matches!(Movie::parse(text), Err(MovieErr::Parse(4))));
+}
+
+#[test]
+fn every_quirk_is_recorded() {
+    let mut movie = Movie::parse("chip8-movie 1\nframes 1\n").unwrap();
+    movie.quirks = Platform::SuperChip.quirks();
+    assert_eq!(round_trip(&movie).quirks, Platform::SuperChip.quirks());
+    let text = "chip8-movie 1\nquirks 0 0 0 1\n";
+    assert!(matches!(Movie::parse(text), Err(MovieErr::Parse(2))));
+}
+
+#[test]
+fn start_state_is_restored() {
+    let rom = keypad_rom();
+    let mut mach = Machine::new();
+    mach.load(&rom).unwrap();
+    mach.set_key(Key::Key9, true);
+    mach.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+    mach.set_key(Key::Key9, false);
+    let movie = round_trip(&Movie::new(&rom, &mach, 0).with_start_state(mach.save_state()));
+    let restored = movie.machine(&rom).unwrap();
+    assert_eq!(restored.save_state(), mach.save_state());
+}
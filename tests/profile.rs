@@ -0,0 +1,92 @@
+use chip8emu::{
+    machine::Machine,
+    probe::{self, Probe},
+    profile::Profiler,
+};
+
+// main calls sub_206, which calls sub_20c, then calls sub_20c itself and
+// jumps back to the start. One pass is ten instructions: three in main,
+// three in sub_206, two in sub_20c under sub_206 and two under main.
+const ROM: [u8; 16] = [
+    0x22, 0x06, 0x22, 0x0C, 0x12, 0x00, 0x22, 0x0C, 0x70, 0x01, 0x00, 0xEE, 0x71, 0x01, 0x00, 0xEE,
+];
+
+// Ten passes in frames of 25 instructions.
+fn profile() -> (Machine, Profiler) {
+    let mut mach = Machine::new();
+    mach.load(&ROM).unwrap();
+    let mut profiler = Profiler::new();
+    for _ in 0..4 {
+        for _ in 0..25 {
+            probe::step(&mut mach, &mut [&mut profiler]).unwrap();
+        }
+        profiler.end_frame(&mach);
+    }
+    (mach, profiler)
+}
+
+#[test]
+fn hits_count_each_address() {
+    let (_, profiler) = profile();
+    assert_eq!(profiler.instructions(), 100);
+    for addr in (0x200..0x210).step_by(2) {
+        let expected = if addr == 0x20C || addr == 0x20E {
+            20
+        } else {
+            10
+        };
+        assert_eq!(profiler.hits(addr), expected, "{:#x}", addr);
+    }
+    assert_eq!(profiler.hits(0x210), 0);
+}
+
+#[test]
+fn collapsed_stacks_charge_each_call_path() {
+    let (_, profiler) = profile();
+    let mut out = Vec::new();
+    profiler.write_collapsed(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "main 30\nmain;sub_206 30\nmain;sub_206;sub_20c 20\nmain;sub_20c 20\n"
+    );
+}
+
+#[test]
+fn report_lists_hot_spots_subroutines_and_frames() {
+    let (mach, profiler) = profile();
+    let mut out = Vec::new();
+    profiler.write_report(&mach, &mut out).unwrap();
+    let report = String::from_utf8(out).unwrap();
+    let expected = "\
+Hot spots (100 instructions)
+    addr        count       %  instruction
+   0x20c           20  20.00%  ADD V1, 0x01
+   0x20e           20  20.00%  RET
+   0x200           10  10.00%  CALL 0x206
+   0x202           10  10.00%  CALL 0x20c
+   0x204           10  10.00%  JP 0x200
+   0x206           10  10.00%  CALL 0x20c
+   0x208           10  10.00%  ADD V0, 0x01
+   0x20a           10  10.00%  RET
+
+Subroutines
+    addr    calls    inclusive       %    exclusive       %
+   0x206       10           50  50.00%           30  30.00%
+   0x20c       20           40  40.00%           40  40.00%
+
+Instructions per frame over 4 frames: min 25, avg 25.0, max 25
+";
+    assert_eq!(report, expected);
+}
+
+#[test]
+fn report_without_frames_says_so() {
+    let mut mach = Machine::new();
+    mach.load(&ROM).unwrap();
+    let mut profiler = Profiler::new();
+    probe::step(&mut mach, &mut [&mut profiler]).unwrap();
+    let mut out = Vec::new();
+    profiler.write_report(&mach, &mut out).unwrap();
+    let report = String::from_utf8(out).unwrap();
+    assert!(report.ends_with("Instructions per frame: no complete frames\n"));
+}
@@ -0,0 +1,126 @@
+use chip8emu::{
+    machine::{Machine, INSTRUCTIONS_PER_FRAME},
+    rewind::Rewind,
+};
+
+// Counts in V0, stores V0 and V1 at 0x300 and calls a subroutine, so
+// memory, registers and the stack depth change from one instruction to the
+// next.
+const ROM: [u8; 16] = [
+    0x70, 0x01, 0xA3, 0x00, 0xF1, 0x55, 0x22, 0x0C, 0x12, 0x00, 0x00, 0x00, 0x71, 0x01, 0x00, 0xEE,
+];
+
+const FRAME: usize = 7;
+
+fn machine() -> Machine {
+    let mut mach = Machine::new();
+    mach.load(&ROM).unwrap();
+    mach
+}
+
+// Runs `frames` frames of `FRAME` instructions, pushing a snapshot before the
+// first and after each one, and returns the save state after each frame.
+fn record(mach: &mut Machine, rewind: &mut Rewind, frames: usize) -> Vec<Vec<u8>> {
+    rewind.push(mach);
+    let mut states = vec![mach.save_state()];
+    for _ in 0..frames {
+        mach.run_frame(FRAME).unwrap();
+        rewind.push(mach);
+        states.push(mach.save_state());
+    }
+    states
+}
+
+// Older snapshots are XOR deltas against newer ones; the stack depth makes
+// consecutive states differ in length as well as content.
+#[test]
+fn step_back_restores_every_frame() {
+    let mut mach = machine();
+    let mut rewind = Rewind::with_frames(100);
+    let states = record(&mut mach, &mut rewind, 40);
+    assert_eq!(rewind.len(), states.len());
+    for expected in states.iter().rev().skip(1) {
+        assert!(rewind.step_back(&mut mach));
+        assert_eq!(&mach.save_state(), expected);
+    }
+    assert!(!rewind.step_back(&mut mach));
+    assert_eq!(mach.save_state(), states[0]);
+}
+
+#[test]
+fn step_back_mid_frame_returns_to_the_frame_start() {
+    let mut mach = machine();
+    let mut rewind = Rewind::with_frames(100);
+    let states = record(&mut mach, &mut rewind, 3);
+    mach.step().unwrap();
+    mach.step().unwrap();
+    assert!(rewind.step_back(&mut mach));
+    assert_eq!(&mach.save_state(), states.last().unwrap());
+    assert!(rewind.step_back(&mut mach));
+    assert_eq!(mach.save_state(), states[2]);
+}
+
+#[test]
+fn capacity_evicts_the_oldest_frames() {
+    let mut mach = machine();
+    let mut rewind = Rewind::with_frames(4);
+    let states = record(&mut mach, &mut rewind, 10);
+    assert_eq!(rewind.len(), 4);
+    for expected in states.iter().rev().skip(1).take(3) {
+        assert!(rewind.step_back(&mut mach));
+        assert_eq!(&mach.save_state(), expected);
+    }
+    assert!(!rewind.step_back(&mut mach));
+    assert_eq!(mach.save_state(), states[7]);
+}
+
+#[test]
+fn step_back_instruction_crosses_frame_boundaries() {
+    let mut mach = machine();
+    let mut rewind = Rewind::with_frames(100);
+    let mut states = vec![mach.save_state()];
+    rewind.push(&mach);
+    for cycle in 1..=3 * FRAME + 2 {
+        mach.step().unwrap();
+        if cycle % FRAME == 0 {
+            rewind.push(&mach);
+        }
+        states.push(mach.save_state());
+    }
+    for cycle in (0..3 * FRAME + 2).rev() {
+        assert!(rewind.step_back_instruction(&mut mach));
+        assert_eq!(mach.cycles(), cycle as u64);
+        assert_eq!(mach.save_state(), states[cycle], "cycle {}", cycle);
+    }
+    assert!(!rewind.step_back_instruction(&mut mach));
+}
+
+#[test]
+fn clear_forgets_every_snapshot() {
+    let mut mach = machine();
+    let mut rewind = Rewind::with_frames(10);
+    record(&mut mach, &mut rewind, 3);
+    rewind.clear();
+    assert!(rewind.is_empty());
+    assert!(!rewind.step_back(&mut mach));
+    assert!(!rewind.step_back_instruction(&mut mach));
+}
+
+#[test]
+fn seconds_are_counted_in_frames() {
+    let mut mach = machine();
+    let mut rewind = Rewind::new(1);
+    for _ in 0..100 {
+        mach.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+        rewind.push(&mach);
+    }
+    assert_eq!(rewind.len(), 60);
+}
+
+#[test]
+fn huge_lengths_do_not_overflow() {
+    let mut mach = machine();
+    let mut rewind = Rewind::new(u32::MAX);
+    record(&mut mach, &mut rewind, 3);
+    assert_eq!(rewind.len(), 4);
+}
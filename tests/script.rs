@@ -0,0 +1,79 @@
+use std::{fs, path::PathBuf};
+
+use chip8emu::{
+    machine::{Key, Machine, INSTRUCTIONS_PER_FRAME},
+    movie::screen_hash,
+    script::{Driver, Script, ScriptErr, Step},
+};
+
+fn keypad() -> Machine {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms/keypad.ch8");
+    let mut mach = Machine::new();
+    mach.load(&fs::read(path).unwrap()).unwrap();
+    mach
+}
+
+fn run(text: &str, mach: &mut Machine) -> Result<u64, ScriptErr> {
+    Driver::new(Script::parse(text)?).run(mach, INSTRUCTIONS_PER_FRAME)
+}
+
+#[test]
+fn parse_expands_timed_press() {
+    let script = Script::parse("wait 2; press a for 3 # hold A\nrelease all").unwrap();
+    assert_eq!(
+        script.steps(),
+        &[
+            Step::Wait(2),
+            Step::Press(Key::KeyA),
+            Step::Wait(3),
+            Step::Release(Key::KeyA),
+            Step::ReleaseAll,
+        ]
+    );
+}
+
+#[test]
+fn parse_reports_line() {
+    assert!(matches!(
+        Script::parse("wait 1\npress G"),
+        Err(ScriptErr::Parse(2))
+    ));
+    assert!(matches!(
+        Script::parse("wait-until pc=0x200 after 5"),
+        Err(ScriptErr::Parse(1))
+    ));
+    assert!(matches!(
+        Script::parse("wait-until vg=1"),
+        Err(ScriptErr::Parse(1))
+    ));
+}
+
+#[test]
+fn press_shows_key() {
+    let mut scripted = keypad();
+    let frames = run("wait 2; press 7 for 3; wait-until pc=0x20a", &mut scripted).unwrap();
+    assert_eq!(frames, 5);
+    assert!(!scripted.key(Key::Key7));
+    assert_eq!(scripted.reg(0.into()), 7);
+
+    let mut held = keypad();
+    held.set_key(Key::Key7, true);
+    for _ in 0..10 {
+        held.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+    }
+    assert_eq!(screen_hash(&scripted), screen_hash(&held));
+}
+
+#[test]
+fn wait_until_register() {
+    let mut mach = keypad();
+    run("press c; wait-until v0=0xc within 2", &mut mach).unwrap();
+    assert!(mach.key(Key::KeyC));
+}
+
+#[test]
+fn wait_until_times_out() {
+    let mut mach = keypad();
+    let result = run("wait 1\nwait-until pc=0x20a within 5", &mut mach);
+    assert!(matches!(result, Err(ScriptErr::Timeout(2))));
+}
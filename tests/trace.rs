@@ -0,0 +1,113 @@
+use std::io::{self, Write};
+
+use chip8emu::{machine::Machine, trace::Tracer};
+
+// Sets V0, V1 and I, adds with a carry, calls a subroutine at 0x20C and then
+// spins on a jump at 0x20A.
+const ROM: [u8; 16] = [
+    0x60, 0xFF, 0x61, 0x02, 0x80, 0x14, 0xA3, 0x0F, 0x22, 0x0C, 0x12, 0x0A, 0x70, 0x01, 0x00, 0xEE,
+];
+
+const TRACE: &str = "\
+0 0200 60FF LD V0, 0xff ; V0=FF
+1 0202 6102 LD V1, 0x02 ; V1=02
+2 0204 8014 ADD V0, V1 ; V0=01 VF=01
+3 0206 A30F LD I, 0x30f ; I=030F
+4 0208 220C CALL 0x20c ;
+5 020C 7001 ADD V0, 0x01 ; V0=02
+6 020E 00EE RET ;
+7 020A 120A JP 0x20a ;
+8 020A 120A JP 0x20a ;
+";
+
+fn trace<W: Write>(mut tracer: Tracer<W>, steps: usize) -> io::Result<W> {
+    let mut mach = Machine::new();
+    mach.load(&ROM).unwrap();
+    for _ in 0..steps {
+        tracer.step(&mut mach).unwrap();
+    }
+    tracer.finish()
+}
+
+fn text(out: Vec<u8>) -> String {
+    String::from_utf8(out).unwrap()
+}
+
+// Accepts `capacity` bytes and then fails every write.
+#[derive(Debug)]
+struct Full {
+    capacity: usize,
+    writes: usize,
+}
+
+impl Write for Full {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writes += 1;
+        if buf.len() > self.capacity {
+            return Err(io::Error::new(io::ErrorKind::StorageFull, "sink is full"));
+        }
+        self.capacity -= buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn lines_follow_the_documented_format() {
+    assert_eq!(text(trace(Tracer::new(Vec::new()), 9).unwrap()), TRACE);
+}
+
+#[test]
+fn range_keeps_only_instructions_inside_it() {
+    let tracer = Tracer::new(Vec::new()).with_range(0x20C..=0x20E);
+    let expected: String = TRACE
+        .lines()
+        .skip(5)
+        .take(2)
+        .map(|line| format!("{}\n", line))
+        .collect();
+    assert_eq!(text(trace(tracer, 9).unwrap()), expected);
+}
+
+#[test]
+fn limit_stops_after_that_many_lines() {
+    let mut mach = Machine::new();
+    mach.load(&ROM).unwrap();
+    let mut tracer = Tracer::new(Vec::new()).with_max_lines(3);
+    for _ in 0..9 {
+        tracer.step(&mut mach).unwrap();
+    }
+    assert_eq!(tracer.lines(), 3);
+    assert_eq!(mach.pc(), 0x20A);
+    let expected: String = TRACE
+        .lines()
+        .take(3)
+        .map(|line| format!("{}\n", line))
+        .collect();
+    assert_eq!(text(tracer.finish().unwrap()), expected);
+}
+
+#[test]
+fn write_errors_surface_when_the_trace_finishes() {
+    let mut mach = Machine::new();
+    mach.load(&ROM).unwrap();
+    let sink = Full {
+        capacity: 40,
+        writes: 0,
+    };
+    let mut tracer = Tracer::new(sink);
+    for _ in 0..9 {
+        tracer.step(&mut mach).unwrap();
+    }
+    // Emulation carries on, but nothing more is written after the failure.
+    assert_eq!(mach.pc(), 0x20A);
+    assert_eq!(tracer.lines(), 1);
+    let writes = tracer.get_ref().writes;
+    tracer.step(&mut mach).unwrap();
+    assert_eq!(tracer.get_ref().writes, writes);
+    let err = tracer.finish().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+}